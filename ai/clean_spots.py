@@ -22,6 +22,8 @@ def _patched_jit_load(*args, **kwargs):
 torch.jit.load = _patched_jit_load
 from PIL import Image
 import cv2
+import protocol
+import model_cache
 
 
 def get_device():
@@ -40,7 +42,7 @@ def detect_spots(image_path):
     2. White and black top-hat transforms for bright/dark anomalies
     3. Per-channel analysis (not just grayscale)
 
-    Returns a binary mask (uint8, 255=spot) or None if no spots found.
+    Returns (mask, metrics): binary mask (uint8, 255=spot) or None if no spots found.
     """
     img = cv2.imread(image_path, cv2.IMREAD_COLOR)
     if img is None:
@@ -114,15 +116,16 @@ def detect_spots(image_path):
     total = h * w
     pct = (n_pixels / total) * 100
     print(f'Taches détectées: {spots_found} taches, {n_pixels} pixels ({pct:.2f}% de l\'image)', file=sys.stderr)
+    metrics = {'spots': spots_found, 'spot_pixels': int(n_pixels), 'spot_pct': round(pct, 2)}
 
     if n_pixels == 0:
-        return None
+        return None, metrics
 
     # Dilate to ensure full coverage of spots
     kernel = cv2.getStructuringElement(cv2.MORPH_ELLIPSE, (5, 5))
     mask = cv2.dilate(mask, kernel, iterations=1)
 
-    return mask
+    return mask, metrics
 
 
 def inpaint_lama(input_path, spot_mask, device):
@@ -134,7 +137,7 @@ def inpaint_lama(input_path, spot_mask, device):
     pil_image = Image.fromarray(img_rgb)
     pil_mask = Image.fromarray(spot_mask)
 
-    lama = SimpleLama(device=device)
+    lama = model_cache.cached(('lama', str(device)), lambda: SimpleLama(device=device))
     result = lama(pil_image, pil_mask)
     return cv2.cvtColor(np.array(result), cv2.COLOR_RGB2BGR)
 
@@ -151,30 +154,37 @@ def main(input_path, output_path, inpaint_model='lama'):
 
     # Step 1: Detect spots
     print('Détection des taches (analyse multi-échelle)...', file=sys.stderr)
-    spot_mask = detect_spots(input_path)
+    protocol.phase('Détection des taches', 0, 2)
+    spot_mask, metrics = detect_spots(input_path)
 
     if spot_mask is None:
         print('Aucune tache détectée, copie de l\'entrée', file=sys.stderr)
         img = cv2.imread(input_path)
         cv2.imwrite(output_path, img)
-        print(f'OK {output_path}')
+        protocol.result(output_path, noop=True, reason='Aucune tache détectée', metrics=metrics)
         return
 
     # Step 2: Inpaint
     if inpaint_model == 'opencv':
         print('Inpainting avec OpenCV Navier-Stokes (rapide)...', file=sys.stderr)
+        protocol.phase('Inpainting OpenCV', 1, 2)
         result_bgr = inpaint_opencv(input_path, spot_mask)
     else:
         print('Inpainting avec LaMa (meilleure qualité)...', file=sys.stderr)
+        protocol.phase('Inpainting LaMa', 1, 2)
         result_bgr = inpaint_lama(input_path, spot_mask, device)
 
     cv2.imwrite(output_path, result_bgr)
-    print(f'OK {output_path}')
+    protocol.result(output_path, metrics=metrics, model=inpaint_model)
 
 
-if __name__ == '__main__':
-    if len(sys.argv) < 3:
+def run(argv):
+    if len(argv) < 2:
         print('Usage: python clean_spots.py <input> <output> [lama|opencv]', file=sys.stderr)
         sys.exit(1)
-    model = sys.argv[3] if len(sys.argv) > 3 else 'lama'
-    main(sys.argv[1], sys.argv[2], model)
+    model = argv[2] if len(argv) > 2 else 'lama'
+    main(argv[0], argv[1], model)
+
+
+if __name__ == '__main__':
+    run(sys.argv[1:])
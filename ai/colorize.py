@@ -11,6 +11,8 @@ import numpy as np
 import cv2
 import torch
 from colorizers import siggraph17, eccv16, load_img, preprocess_img, postprocess_tens
+import protocol
+import model_cache
 
 def get_device():
     if torch.cuda.is_available():
@@ -22,10 +24,11 @@ def get_device():
 def is_already_color(img_path, sat_threshold=30, pct_threshold=15, hue_std_threshold=20):
     """Detect if an image is already in color via HSV saturation + hue diversity.
     Sepia/toned photos have saturation but all on the same hue — not truly colored.
+    Returns (is_color, metrics).
     """
     img = cv2.imread(img_path, cv2.IMREAD_COLOR)
     if img is None:
-        return False
+        return False, {}
     hsv = cv2.cvtColor(img, cv2.COLOR_BGR2HSV)
     saturation = hsv[:, :, 1]
     colored_mask = saturation > sat_threshold
@@ -40,32 +43,37 @@ def is_already_color(img_path, sat_threshold=30, pct_threshold=15, hue_std_thres
         hue_std = np.sqrt(-2 * np.log(max(1e-10, np.sqrt(sin_mean**2 + cos_mean**2)))) * (180 / (2 * np.pi))
 
     print(f'Couleur: {pct_colored:.1f}% pixels saturés, diversité teinte: {hue_std:.1f}° (seuils: {pct_threshold}%, {hue_std_threshold}°)', file=sys.stderr)
-    return pct_colored > pct_threshold and hue_std > hue_std_threshold
+    metrics = {'saturated_pct': round(float(pct_colored), 1), 'hue_std': round(float(hue_std), 1)}
+    return pct_colored > pct_threshold and hue_std > hue_std_threshold, metrics
 
 
 def main(input_path, output_path, model_name='siggraph17'):
     device = get_device()
     print(f'Using device: {device}', file=sys.stderr)
 
-    if is_already_color(input_path):
+    already_color, metrics = is_already_color(input_path)
+    if already_color:
         print('Image déjà en couleur, copie sans traitement', file=sys.stderr)
         img = cv2.imread(input_path)
         cv2.imwrite(output_path, img)
-        print(f'OK {output_path}')
+        protocol.result(output_path, noop=True, reason='Image déjà en couleur', metrics=metrics)
         return
 
+    protocol.phase('Chargement du modèle', 0, 2)
     if model_name == 'eccv16':
         print('Loading ECCV16 colorizer...', file=sys.stderr)
-        colorizer = eccv16(pretrained=True).eval().to(device)
+        colorizer = model_cache.cached(('eccv16', str(device)), lambda: eccv16(pretrained=True).eval().to(device))
     else:
         print('Loading Siggraph17 colorizer...', file=sys.stderr)
-        colorizer = siggraph17(pretrained=True).eval().to(device)
+        colorizer = model_cache.cached(('siggraph17', str(device)),
+                                       lambda: siggraph17(pretrained=True).eval().to(device))
 
     img = load_img(input_path)
     if img is None:
         print(f'Error: cannot read {input_path}', file=sys.stderr)
         sys.exit(1)
 
+    protocol.phase('Colorisation', 1, 2)
     tens_l_orig, tens_l_rs = preprocess_img(img, HW=(256, 256))
 
     with torch.no_grad():
@@ -74,11 +82,15 @@ def main(input_path, output_path, model_name='siggraph17'):
     result = postprocess_tens(tens_l_orig, output)
     result_bgr = cv2.cvtColor((result * 255).astype(np.uint8), cv2.COLOR_RGB2BGR)
     cv2.imwrite(output_path, result_bgr)
-    print(f'OK {output_path}')
+    protocol.result(output_path, metrics=metrics, model=model_name)
 
-if __name__ == '__main__':
-    if len(sys.argv) < 3:
+def run(argv):
+    if len(argv) < 2:
         print('Usage: python colorize.py <input> <output> [siggraph17|eccv16]', file=sys.stderr)
         sys.exit(1)
-    model = sys.argv[3] if len(sys.argv) > 3 else 'siggraph17'
-    main(sys.argv[1], sys.argv[2], model)
+    model = argv[2] if len(argv) > 2 else 'siggraph17'
+    main(argv[0], argv[1], model)
+
+
+if __name__ == '__main__':
+    run(sys.argv[1:])
@@ -13,6 +13,8 @@ import torch.nn.functional as F
 
 sys.path.insert(0, os.path.dirname(os.path.abspath(__file__)))
 from ddcolor import DDColor
+import protocol
+import model_cache
 
 
 def get_device():
@@ -82,6 +84,7 @@ def colorize(model, img_bgr, device, input_size=512):
 def is_already_color(img, sat_threshold=30, pct_threshold=15, hue_std_threshold=20):
     """Detect if an image is already in color via HSV saturation + hue diversity.
     Sepia/toned photos have saturation but all on the same hue — not truly colored.
+    Returns (is_color, metrics).
     """
     hsv = cv2.cvtColor(img, cv2.COLOR_BGR2HSV)
     saturation = hsv[:, :, 1]
@@ -99,7 +102,8 @@ def is_already_color(img, sat_threshold=30, pct_threshold=15, hue_std_threshold=
         hue_std = np.sqrt(-2 * np.log(max(1e-10, np.sqrt(sin_mean**2 + cos_mean**2)))) * (180 / (2 * np.pi))
 
     print(f'Couleur: {pct_colored:.1f}% pixels saturés, diversité teinte: {hue_std:.1f}° (seuils: {pct_threshold}%, {hue_std_threshold}°)', file=sys.stderr)
-    return pct_colored > pct_threshold and hue_std > hue_std_threshold
+    metrics = {'saturated_pct': round(float(pct_colored), 1), 'hue_std': round(float(hue_std), 1)}
+    return pct_colored > pct_threshold and hue_std > hue_std_threshold, metrics
 
 
 def main(input_path, output_path):
@@ -111,28 +115,35 @@ def main(input_path, output_path):
         print(f'Error: cannot read {input_path}', file=sys.stderr)
         sys.exit(1)
 
-    if is_already_color(img):
+    already_color, metrics = is_already_color(img)
+    if already_color:
         print('Image déjà en couleur, copie sans traitement', file=sys.stderr)
         cv2.imwrite(output_path, img)
-        print(f'OK {output_path}')
+        protocol.result(output_path, noop=True, reason='Image déjà en couleur', metrics=metrics)
         return
 
     print('Loading DDColor model (ICCV 2023)...', file=sys.stderr)
-    model = load_model(device)
+    protocol.phase('Chargement du modèle', 0, 2)
+    model = model_cache.cached(('ddcolor', str(device)), lambda: load_model(device))
 
     print('Colorizing...', file=sys.stderr)
+    protocol.phase('Colorisation', 1, 2)
     result = colorize(model, img, device)
 
     cv2.imwrite(output_path, result)
-    print(f'OK {output_path}')
+    protocol.result(output_path, metrics=metrics, model='ddcolor')
 
     gc.collect()
     if device.type == 'cuda':
         torch.cuda.empty_cache()
 
 
-if __name__ == '__main__':
-    if len(sys.argv) != 3:
+def run(argv):
+    if len(argv) != 2:
         print('Usage: python colorize_ddcolor.py <input> <output>', file=sys.stderr)
         sys.exit(1)
-    main(sys.argv[1], sys.argv[2])
+    main(argv[0], argv[1])
+
+
+if __name__ == '__main__':
+    run(sys.argv[1:])
@@ -9,6 +9,8 @@ import os
 import gc
 import warnings
 import numpy as np
+import protocol
+import model_cache
 
 warnings.filterwarnings('ignore', category=UserWarning)
 
@@ -35,6 +37,7 @@ def download_model(weights_name):
 
     os.makedirs(MODELS_DIR, exist_ok=True)
     print(f'Downloading {weights_name} (~255MB)...', file=sys.stderr)
+    protocol.phase('Téléchargement du modèle')
 
     import urllib.request
     urllib.request.urlretrieve(url, model_path)
@@ -44,12 +47,13 @@ def download_model(weights_name):
 def is_already_color(img_path, sat_threshold=30, pct_threshold=15, hue_std_threshold=20):
     """Detect if an image is already in color via HSV saturation + hue diversity.
     Sepia/toned photos have saturation but all on the same hue — not truly colored.
+    Returns (is_color, metrics).
     """
     import cv2
     import numpy as np
     img = cv2.imread(img_path, cv2.IMREAD_COLOR)
     if img is None:
-        return False
+        return False, {}
     hsv = cv2.cvtColor(img, cv2.COLOR_BGR2HSV)
     saturation = hsv[:, :, 1]
     colored_mask = saturation > sat_threshold
@@ -64,27 +68,32 @@ def is_already_color(img_path, sat_threshold=30, pct_threshold=15, hue_std_thres
         hue_std = np.sqrt(-2 * np.log(max(1e-10, np.sqrt(sin_mean**2 + cos_mean**2)))) * (180 / (2 * np.pi))
 
     print(f'Couleur: {pct_colored:.1f}% pixels saturés, diversité teinte: {hue_std:.1f}° (seuils: {pct_threshold}%, {hue_std_threshold}°)', file=sys.stderr)
-    return pct_colored > pct_threshold and hue_std > hue_std_threshold
+    metrics = {'saturated_pct': round(float(pct_colored), 1), 'hue_std': round(float(hue_std), 1)}
+    return pct_colored > pct_threshold and hue_std > hue_std_threshold, metrics
 
 
 def main(input_path, output_path, variant='artistic'):
-    if is_already_color(input_path):
+    already_color, metrics = is_already_color(input_path)
+    if already_color:
         print('Image déjà en couleur, copie sans traitement', file=sys.stderr)
         import shutil
         shutil.copy2(input_path, output_path)
-        print(f'OK {output_path}')
+        protocol.result(output_path, noop=True, reason='Image déjà en couleur', metrics=metrics)
         return
 
     import torch
     from pathlib import Path
 
     # PyTorch 2.6+ defaults weights_only=True which breaks fastai v1 model loading.
-    # Monkey-patch torch.load to use weights_only=False for DeOldify models.
-    _original_torch_load = torch.load
-    def _patched_load(*args, **kwargs):
-        kwargs.setdefault('weights_only', False)
-        return _original_torch_load(*args, **kwargs)
-    torch.load = _patched_load
+    # Monkey-patch torch.load to use weights_only=False for DeOldify models
+    # (une seule fois : en mode worker main() est appelé pour chaque image).
+    if not getattr(torch.load, '_deoldify_patched', False):
+        _original_torch_load = torch.load
+        def _patched_load(*args, **kwargs):
+            kwargs.setdefault('weights_only', False)
+            return _original_torch_load(*args, **kwargs)
+        _patched_load._deoldify_patched = True
+        torch.load = _patched_load
 
     # Determine device
     if torch.cuda.is_available():
@@ -111,17 +120,19 @@ def main(input_path, output_path, variant='artistic'):
     download_model(weights_name)
 
     print(f'Loading DeOldify {variant} model...', file=sys.stderr)
+    protocol.phase('Chargement du modèle', 0, 2)
     from deoldify.visualize import get_image_colorizer
 
     render_factor = 35 if artistic else 25
-    colorizer = get_image_colorizer(
+    colorizer = model_cache.cached(('deoldify', variant, device_name), lambda: get_image_colorizer(
         root_folder=Path(SCRIPT_DIR),
         render_factor=render_factor,
         artistic=artistic,
-    )
+    ))
 
     # Colorize
     print('Colorizing...', file=sys.stderr)
+    protocol.phase('Colorisation', 1, 2)
     result_image = colorizer.get_transformed_image(
         path=Path(input_path),
         render_factor=render_factor,
@@ -132,16 +143,20 @@ def main(input_path, output_path, variant='artistic'):
     result_bgr = np.array(result_image)[:, :, ::-1]  # RGB -> BGR
     import cv2
     cv2.imwrite(output_path, result_bgr)
-    print(f'OK {output_path}')
+    protocol.result(output_path, metrics=metrics, model=f'deoldify_{variant}')
 
     gc.collect()
     if device_name == 'cuda':
         torch.cuda.empty_cache()
 
 
-if __name__ == '__main__':
-    if len(sys.argv) < 3:
+def run(argv):
+    if len(argv) < 2:
         print('Usage: python colorize_deoldify.py <input> <output> [artistic|stable]', file=sys.stderr)
         sys.exit(1)
-    variant = sys.argv[3] if len(sys.argv) > 3 else 'artistic'
-    main(sys.argv[1], sys.argv[2], variant)
+    variant = argv[2] if len(argv) > 2 else 'artistic'
+    main(argv[0], argv[1], variant)
+
+
+if __name__ == '__main__':
+    run(sys.argv[1:])
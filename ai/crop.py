@@ -8,6 +8,7 @@ Usage:
 import sys
 import numpy as np
 import cv2
+import protocol
 
 
 def crop_rect(img, crop_str):
@@ -80,18 +81,22 @@ def main(input_path, output_path, crop_str):
         sys.exit(1)
 
     if crop_str.startswith('P:'):
-        result = crop_perspective(img, crop_str)
+        mode, result = 'perspective', crop_perspective(img, crop_str)
     elif crop_str.startswith('E:'):
-        result = crop_ellipse(img, crop_str)
+        mode, result = 'ellipse', crop_ellipse(img, crop_str)
     else:
-        result = crop_rect(img, crop_str)
+        mode, result = 'rect', crop_rect(img, crop_str)
 
     cv2.imwrite(output_path, result)
-    print(f'OK {output_path}')
+    protocol.result(output_path, metrics={'width': result.shape[1], 'height': result.shape[0]}, model=mode)
 
 
-if __name__ == '__main__':
-    if len(sys.argv) < 4:
+def run(argv):
+    if len(argv) < 3:
         print('Usage: python crop.py <input> <output> <x,y,w,h | E:x,y,w,h | P:x1,y1,...,x4,y4>', file=sys.stderr)
         sys.exit(1)
-    main(sys.argv[1], sys.argv[2], sys.argv[3])
+    main(argv[0], argv[1], argv[2])
+
+
+if __name__ == '__main__':
+    run(sys.argv[1:])
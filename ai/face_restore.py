@@ -2,13 +2,17 @@
 Restores and enhances faces in old or damaged photos.
 Model auto-downloads on first run (~330MB).
 
-Usage: python face_restore.py input.jpg output.jpg
+Usage: python face_restore.py input.jpg output.jpg [weight]
+
+weight (0-1, default 0.5): blend between the original face and the GFPGAN one.
 """
 import sys
 import os
 import torch
 import cv2
 from gfpgan import GFPGANer
+import protocol
+import model_cache
 
 
 def get_device():
@@ -19,9 +23,10 @@ def get_device():
     return torch.device('cpu')
 
 
-def main(input_path, output_path):
+def main(input_path, output_path, weight=0.5):
     device = get_device()
     print(f'Using device: {device}', file=sys.stderr)
+    protocol.phase('Chargement du modèle', 0, 2)
 
     kwargs = dict(
         model_path='https://github.com/TencentARC/GFPGAN/releases/download/v1.3.4/GFPGANv1.4.pth',
@@ -40,26 +45,36 @@ def main(input_path, output_path):
             kwargs['model_rootpath'] = model_dir
         elif 'root_dir' in params:
             kwargs['root_dir'] = model_dir
-    restorer = GFPGANer(**kwargs)
+    restorer = model_cache.cached(('gfpgan', str(device)), lambda: GFPGANer(**kwargs))
 
     img = cv2.imread(input_path, cv2.IMREAD_COLOR)
     if img is None:
         print(f'Error: cannot read {input_path}', file=sys.stderr)
         sys.exit(1)
 
-    _, _, output = restorer.enhance(
+    protocol.phase('Restauration des visages', 1, 2)
+    cropped_faces, _, output = restorer.enhance(
         img,
         has_aligned=False,
         only_center_face=False,
         paste_back=True,
+        weight=weight,
     )
 
     cv2.imwrite(output_path, output)
-    print(f'OK {output_path}')
+    faces = len(cropped_faces)
+    print(f'Faces restored: {faces}', file=sys.stderr)
+    protocol.result(output_path, noop=faces == 0, reason='Aucun visage détecté' if faces == 0 else None,
+                    metrics={'faces': faces}, model='gfpgan_v1.4')
 
 
-if __name__ == '__main__':
-    if len(sys.argv) != 3:
-        print('Usage: python face_restore.py <input> <output>', file=sys.stderr)
+def run(argv):
+    if len(argv) not in (2, 3):
+        print('Usage: python face_restore.py <input> <output> [weight]', file=sys.stderr)
         sys.exit(1)
-    main(sys.argv[1], sys.argv[2])
+    weight = float(argv[2]) if len(argv) > 2 else 0.5
+    main(argv[0], argv[1], weight)
+
+
+if __name__ == '__main__':
+    run(sys.argv[1:])
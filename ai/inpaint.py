@@ -11,6 +11,8 @@ import numpy as np
 import torch
 from PIL import Image
 import cv2
+import protocol
+import model_cache
 
 # Monkey-patch torch.jit.load pour forcer map_location="cpu" sur PyTorch CPU-only
 # (le modèle LaMa TorchScript contient des tenseurs CUDA)
@@ -61,26 +63,32 @@ def inpaint(image_path, mask_path, output_path):
     total = mask.shape[0] * mask.shape[1]
     pct = (n_pixels / total) * 100
     print(f'Mask: {n_pixels} pixels to inpaint ({pct:.1f}% of image)', file=sys.stderr)
+    metrics = {'mask_pixels': int(n_pixels), 'mask_pct': round(pct, 2)}
 
     if n_pixels == 0:
         print('Empty mask, copying input', file=sys.stderr)
         cv2.imwrite(output_path, img)
-        print(f'OK {output_path}')
+        protocol.result(output_path, noop=True, reason='Masque vide', metrics=metrics)
         return
 
     pil_mask = Image.fromarray(mask)
 
     print('Inpainting with LaMa...', file=sys.stderr)
-    lama = SimpleLama(device=device)
+    protocol.phase('Inpainting LaMa')
+    lama = model_cache.cached(('lama', str(device)), lambda: SimpleLama(device=device))
     result = lama(pil_image, pil_mask)
 
     result_bgr = cv2.cvtColor(np.array(result), cv2.COLOR_RGB2BGR)
     cv2.imwrite(output_path, result_bgr)
-    print(f'OK {output_path}')
+    protocol.result(output_path, metrics=metrics, model='lama')
 
 
-if __name__ == '__main__':
-    if len(sys.argv) < 4:
+def run(argv):
+    if len(argv) < 3:
         print('Usage: python inpaint.py <image> <mask> <output>', file=sys.stderr)
         sys.exit(1)
-    inpaint(sys.argv[1], sys.argv[2], sys.argv[3])
+    inpaint(argv[0], argv[1], argv[2])
+
+
+if __name__ == '__main__':
+    run(sys.argv[1:])
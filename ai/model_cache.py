@@ -0,0 +1,21 @@
+"""Models kept in memory for the lifetime of the process.
+
+In one-shot mode (python script.py ...) this is a no-op: the process exits after
+one image. Under worker.py the same process handles many images, so the second
+call to cached() with the same key skips the load entirely.
+"""
+import gc
+
+_models = {}
+
+
+def cached(key, loader):
+    """Return the model stored under `key`, calling `loader()` the first time."""
+    if key not in _models:
+        _models[key] = loader()
+    return _models[key]
+
+
+def clear():
+    _models.clear()
+    gc.collect()
@@ -0,0 +1,82 @@
+"""Machine-readable lines read by server/python.js while a step is running.
+
+  @progress {"current": 12, "total": 40}   sub-step progress (tiles, passes...)
+  @phase Détection des rayures             current phase, shown as-is in the UI
+  @result {"output": "...", "noop": false, ...}   final record, see result()
+
+Logs go to stderr.
+"""
+import sys
+import re
+import json
+
+_TILE_RE = re.compile(r'Tile (\d+)/(\d+)')
+
+
+def _emit(line):
+    sys.stdout.write(line + '\n')
+    sys.stdout.flush()
+
+
+def phase(text, current=None, total=None):
+    """Announce a new phase, optionally with its position in the step."""
+    _emit(f'@phase {text}')
+    if current is not None and total:
+        progress(current, total)
+
+
+def progress(current, total):
+    _emit('@progress ' + json.dumps({'current': int(current), 'total': int(total)}))
+
+
+def result(output, noop=False, reason=None, metrics=None, warnings=None, model=None):
+    """Final record of a step, stored by the server on job.stepResults.
+
+    noop:     the output is identical to the input (nothing to do)
+    reason:   short human-readable explanation, mostly for no-ops
+    metrics:  flat dict of numbers (pixels detected, timings, sizes...)
+    warnings: list of strings worth surfacing in the UI
+    model:    model actually used
+    """
+    _emit('@result ' + json.dumps({
+        'output': output,
+        'noop': bool(noop),
+        'reason': reason,
+        'metrics': metrics or {},
+        'warnings': warnings or [],
+        'model': model,
+    }, ensure_ascii=False))
+
+
+class _TileWriter:
+    """stdout replacement turning Real-ESRGAN's 'Tile 12/40' prints into @progress lines."""
+
+    def __init__(self, stdout):
+        self._stdout = stdout
+
+    def write(self, text):
+        match = _TILE_RE.search(text)
+        if match:
+            self._stdout.write('@progress ' + json.dumps({
+                'current': int(match.group(1)), 'total': int(match.group(2)),
+            }) + '\n')
+            self._stdout.flush()
+        else:
+            sys.stderr.write(text)
+        return len(text)
+
+    def flush(self):
+        self._stdout.flush()
+
+
+class tile_progress:
+    """Context manager reporting RealESRGANer tile progress while enhance() runs."""
+
+    def __enter__(self):
+        self._stdout = sys.stdout
+        sys.stdout = _TileWriter(self._stdout)
+        return self
+
+    def __exit__(self, *exc):
+        sys.stdout = self._stdout
+        return False
@@ -31,6 +31,8 @@ GLOBAL_DIR = os.path.join(SCRIPT_DIR, 'bopbtl', 'Global')
 sys.path.insert(0, GLOBAL_DIR)
 
 from detection_models import networks
+import protocol
+import model_cache
 
 
 def get_device():
@@ -41,16 +43,7 @@ def get_device():
     return torch.device('cpu')
 
 
-def detect_scratches_ai(image_path, device):
-    """Use Microsoft's trained UNet to detect scratches in the image.
-    Returns a binary mask (numpy uint8, 255=scratch) or None if no scratches found.
-    """
-    checkpoint_path = os.path.join(GLOBAL_DIR, 'checkpoints', 'detection', 'FT_Epoch_latest.pt')
-    if not os.path.exists(checkpoint_path):
-        print('WARNING: Scratch detection model not found, skipping', file=sys.stderr)
-        return None
-
-    # Load model
+def load_detection_model(checkpoint_path, device):
     model = networks.UNet(
         in_channels=1,
         out_channels=1,
@@ -69,6 +62,20 @@ def detect_scratches_ai(image_path, device):
     model.load_state_dict(checkpoint['model_state'])
     model.to(device)
     model.eval()
+    return model
+
+
+def detect_scratches_ai(image_path, device):
+    """Use Microsoft's trained UNet to detect scratches in the image.
+    Returns a binary mask (numpy uint8, 255=scratch) or None if no scratches found.
+    """
+    checkpoint_path = os.path.join(GLOBAL_DIR, 'checkpoints', 'detection', 'FT_Epoch_latest.pt')
+    if not os.path.exists(checkpoint_path):
+        print('WARNING: Scratch detection model not found, skipping', file=sys.stderr)
+        return None
+
+    model = model_cache.cached(('bopbtl_detection', str(device)),
+                               lambda: load_detection_model(checkpoint_path, device))
 
     # Load and preprocess image
     img = Image.open(image_path).convert('RGB')
@@ -139,7 +146,7 @@ def inpaint_lama(input_path, scratch_mask, device):
     pil_image = Image.fromarray(img_rgb)
     pil_mask = Image.fromarray(scratch_mask)
 
-    lama = SimpleLama(device=device)
+    lama = model_cache.cached(('lama', str(device)), lambda: SimpleLama(device=device))
     result = lama(pil_image, pil_mask)
     return cv2.cvtColor(np.array(result), cv2.COLOR_RGB2BGR)
 
@@ -156,41 +163,50 @@ def main(input_path, output_path, inpaint_model='lama'):
 
     # Step 1: AI scratch detection
     print('Detecting scratches (Microsoft BOPBTL model)...', file=sys.stderr)
+    protocol.phase('Détection des rayures', 0, 2)
     scratch_mask = detect_scratches_ai(input_path, device)
 
     if scratch_mask is None:
         img = cv2.imread(input_path)
         cv2.imwrite(output_path, img)
-        print(f'OK {output_path}')
+        protocol.result(output_path, noop=True, reason='Modèle de détection des rayures absent',
+                        warnings=['Scratch detection model not found'])
         return
 
     n_pixels = np.count_nonzero(scratch_mask)
     total = scratch_mask.shape[0] * scratch_mask.shape[1]
     pct = (n_pixels / total) * 100
     print(f'Scratches detected: {n_pixels} pixels ({pct:.1f}% of image)', file=sys.stderr)
+    metrics = {'scratch_pixels': int(n_pixels), 'scratch_pct': round(pct, 2)}
 
     if n_pixels == 0 or pct < 0.1:
         print('No significant scratches detected, copying input', file=sys.stderr)
         img = cv2.imread(input_path)
         cv2.imwrite(output_path, img)
-        print(f'OK {output_path}')
+        protocol.result(output_path, noop=True, reason='Aucune rayure détectée', metrics=metrics)
         return
 
     # Step 2: Inpaint
     if inpaint_model == 'opencv':
         print('Inpainting with OpenCV Navier-Stokes (fast)...', file=sys.stderr)
+        protocol.phase('Inpainting OpenCV', 1, 2)
         result_bgr = inpaint_opencv(input_path, scratch_mask)
     else:
         print('Inpainting with LaMa (best quality)...', file=sys.stderr)
+        protocol.phase('Inpainting LaMa', 1, 2)
         result_bgr = inpaint_lama(input_path, scratch_mask, device)
 
     cv2.imwrite(output_path, result_bgr)
-    print(f'OK {output_path}')
+    protocol.result(output_path, metrics=metrics, model=inpaint_model)
 
 
-if __name__ == '__main__':
-    if len(sys.argv) < 3:
+def run(argv):
+    if len(argv) < 2:
         print('Usage: python restore.py <input> <output> [lama|opencv]', file=sys.stderr)
         sys.exit(1)
-    model = sys.argv[3] if len(sys.argv) > 3 else 'lama'
-    main(sys.argv[1], sys.argv[2], model)
+    model = argv[2] if len(argv) > 2 else 'lama'
+    main(argv[0], argv[1], model)
+
+
+if __name__ == '__main__':
+    run(sys.argv[1:])
@@ -19,6 +19,8 @@ import logging
 from datetime import datetime
 from pathlib import Path
 
+import protocol
+
 # --- Logging setup ---
 
 SCRIPT_DIR = Path(__file__).resolve().parent
@@ -198,6 +200,7 @@ def main(input_path, output_path, steps_str='restore,colorize,enhance'):
     client = OpenAI(api_key=api_key)
 
     # Phase 1: Analyze photo with GPT-4o vision
+    protocol.phase('Analyse de la photo', 0, 2)
     description = analyze_photo(client, input_path)
 
     # Phase 2: Build prompt + edit image with gpt-image-1
@@ -205,6 +208,7 @@ def main(input_path, output_path, steps_str='restore,colorize,enhance'):
     logger.info('Prompt construit: %d chars', len(prompt))
     logger.debug('=== PROMPT COMPLET ===\n%s\n=== FIN PROMPT ===', prompt)
 
+    protocol.phase('Édition de l\'image', 1, 2)
     img_bytes = edit_image(client, input_path, prompt)
 
     if not img_bytes:
@@ -214,12 +218,16 @@ def main(input_path, output_path, steps_str='restore,colorize,enhance'):
     with open(output_path, 'wb') as f:
         f.write(img_bytes)
     logger.info('Image sauvegardée: %s (%d bytes)', output_path, len(img_bytes))
-    print(f'OK {output_path}')
+    protocol.result(output_path, metrics={'bytes': len(img_bytes)}, model='gpt-image-1.5')
 
 
-if __name__ == '__main__':
-    if len(sys.argv) < 3:
+def run(argv):
+    if len(argv) < 2:
         print('Usage: python restore_openai.py <input> <output> [restore,colorize,enhance]', file=sys.stderr)
         sys.exit(1)
-    steps = sys.argv[3] if len(sys.argv) > 3 else 'restore,colorize,enhance'
-    main(sys.argv[1], sys.argv[2], steps)
+    steps = argv[2] if len(argv) > 2 else 'restore,colorize,enhance'
+    main(argv[0], argv[1], steps)
+
+
+if __name__ == '__main__':
+    run(sys.argv[1:])
@@ -10,6 +10,8 @@ import os
 import time
 import cv2
 import numpy as np
+import protocol
+import model_cache
 
 
 def get_device():
@@ -31,9 +33,11 @@ def upscale_lanczos(input_path, output_path, outscale):
     h, w = img.shape[:2]
     new_w, new_h = int(w * outscale), int(h * outscale)
     print(f'Lanczos upscale {w}x{h} -> {new_w}x{new_h}', file=sys.stderr)
+    protocol.phase('Interpolation Lanczos')
     output = cv2.resize(img, (new_w, new_h), interpolation=cv2.INTER_LANCZOS4)
     cv2.imwrite(output_path, output)
-    print(f'OK {output_path}')
+    protocol.result(output_path, metrics={'width': w, 'height': h, 'out_width': new_w, 'out_height': new_h},
+                    model='lanczos')
 
 
 # ---- Real-ESRGAN models (RRDBNet architecture) ----
@@ -99,14 +103,7 @@ def upscale_rrdb(input_path, output_path, model_name, outscale):
     use_half = device.type == 'cuda'
 
     print(f'Device: {device} | Model: {model_name} | Half: {use_half}', file=sys.stderr)
-
-    model = RRDBNet(
-        num_in_ch=3, num_out_ch=3,
-        num_feat=cfg['num_feat'],
-        num_block=cfg['num_block'],
-        num_grow_ch=cfg['num_grow_ch'],
-        scale=cfg['scale'],
-    )
+    protocol.phase('Chargement du modèle')
 
     img = cv2.imread(input_path, cv2.IMREAD_UNCHANGED)
     if img is None:
@@ -116,27 +113,42 @@ def upscale_rrdb(input_path, output_path, model_name, outscale):
     tile = compute_tile_size(img, device, cfg['num_block'])
     print(f'Image: {img.shape[1]}x{img.shape[0]} | Tile: {tile or "full"}', file=sys.stderr)
 
-    upsampler = RealESRGANer(
-        scale=cfg['scale'],
-        model_path=cfg['url'],
-        model=model,
-        tile=tile,
-        tile_pad=10,
-        pre_pad=0,
-        half=use_half,
-        device=device,
-    )
-
+    def load():
+        model = RRDBNet(
+            num_in_ch=3, num_out_ch=3,
+            num_feat=cfg['num_feat'],
+            num_block=cfg['num_block'],
+            num_grow_ch=cfg['num_grow_ch'],
+            scale=cfg['scale'],
+        )
+        return RealESRGANer(
+            scale=cfg['scale'],
+            model_path=cfg['url'],
+            model=model,
+            tile=0,
+            tile_pad=10,
+            pre_pad=0,
+            half=use_half,
+            device=device,
+        )
+
+    upsampler = model_cache.cached(('realesrgan', model_name, str(device)), load)
+    upsampler.tile_size = tile  # la tuile dépend de chaque image, pas du modèle en cache
+
+    protocol.phase('Super-résolution')
     t0 = time.time()
-    output, _ = upsampler.enhance(img, outscale=outscale)
+    with protocol.tile_progress():
+        output, _ = upsampler.enhance(img, outscale=outscale)
     elapsed = time.time() - t0
     print(f'Done in {elapsed:.1f}s -> {output.shape[1]}x{output.shape[0]}', file=sys.stderr)
 
     cv2.imwrite(output_path, output)
-    print(f'OK {output_path}')
+    protocol.result(output_path, model=model_name, metrics={
+        'width': img.shape[1], 'height': img.shape[0], 'out_width': output.shape[1], 'out_height': output.shape[0],
+        'tile': tile, 'seconds': round(elapsed, 1),
+    })
 
-    # Free GPU memory
-    del upsampler, model
+    # Free GPU memory (le modèle reste en cache en mode worker)
     if device.type == 'cuda':
         torch.cuda.empty_cache()
     elif device.type == 'mps':
@@ -153,16 +165,11 @@ def upscale_compact(input_path, output_path, outscale):
     device = get_device()
     use_half = device.type == 'cuda'
     print(f'Device: {device} | Model: compact (SRVGGNet) | Half: {use_half}', file=sys.stderr)
+    protocol.phase('Chargement du modèle')
 
     # SRVGGNetCompact - need to import from realesrgan
     from realesrgan.archs.srvgg_arch import SRVGGNetCompact
 
-    model = SRVGGNetCompact(
-        num_in_ch=3, num_out_ch=3,
-        num_feat=64, num_conv=32,
-        upscale=4, act_type='prelu',
-    )
-
     img = cv2.imread(input_path, cv2.IMREAD_UNCHANGED)
     if img is None:
         print(f'Error: cannot read {input_path}', file=sys.stderr)
@@ -172,26 +179,39 @@ def upscale_compact(input_path, output_path, outscale):
     tile = 0 if (h * w) <= 1024*1024 else 400
     print(f'Image: {w}x{h} | Tile: {tile or "full"}', file=sys.stderr)
 
-    upsampler = RealESRGANer(
-        scale=4,
-        model_path='https://github.com/xinntao/Real-ESRGAN/releases/download/v0.2.5.0/realesr-general-x4v3.pth',
-        model=model,
-        tile=tile,
-        tile_pad=10,
-        pre_pad=0,
-        half=use_half,
-        device=device,
-    )
-
+    def load():
+        model = SRVGGNetCompact(
+            num_in_ch=3, num_out_ch=3,
+            num_feat=64, num_conv=32,
+            upscale=4, act_type='prelu',
+        )
+        return RealESRGANer(
+            scale=4,
+            model_path='https://github.com/xinntao/Real-ESRGAN/releases/download/v0.2.5.0/realesr-general-x4v3.pth',
+            model=model,
+            tile=0,
+            tile_pad=10,
+            pre_pad=0,
+            half=use_half,
+            device=device,
+        )
+
+    upsampler = model_cache.cached(('realesrgan', 'compact', str(device)), load)
+    upsampler.tile_size = tile
+
+    protocol.phase('Super-résolution')
     t0 = time.time()
-    output, _ = upsampler.enhance(img, outscale=outscale)
+    with protocol.tile_progress():
+        output, _ = upsampler.enhance(img, outscale=outscale)
     elapsed = time.time() - t0
     print(f'Done in {elapsed:.1f}s -> {output.shape[1]}x{output.shape[0]}', file=sys.stderr)
 
     cv2.imwrite(output_path, output)
-    print(f'OK {output_path}')
+    protocol.result(output_path, model='compact', metrics={
+        'width': w, 'height': h, 'out_width': output.shape[1], 'out_height': output.shape[0],
+        'tile': tile, 'seconds': round(elapsed, 1),
+    })
 
-    del upsampler, model
     if device.type == 'cuda':
         torch.cuda.empty_cache()
     elif device.type == 'mps':
@@ -213,10 +233,14 @@ def main(input_path, output_path, model_name='x4plus', outscale=2):
         sys.exit(1)
 
 
-if __name__ == '__main__':
-    if len(sys.argv) < 3:
+def run(argv):
+    if len(argv) < 2:
         print('Usage: python upscale.py <input> <output> [model] [scale]', file=sys.stderr)
         sys.exit(1)
-    model_name = sys.argv[3] if len(sys.argv) > 3 else 'x4plus'
-    outscale = int(sys.argv[4]) if len(sys.argv) > 4 else 2
-    main(sys.argv[1], sys.argv[2], model_name, outscale)
+    model_name = argv[2] if len(argv) > 2 else 'x4plus'
+    outscale = int(argv[3]) if len(argv) > 3 else 2
+    main(argv[0], argv[1], model_name, outscale)
+
+
+if __name__ == '__main__':
+    run(sys.argv[1:])
@@ -0,0 +1,57 @@
+"""Long-lived worker: runs step scripts in-process so torch and the weights stay loaded.
+
+Started by server/python.js as `python worker.py`, one worker per script. Reads one
+JSON request per line on stdin:
+
+  {"script": "upscale.py", "args": ["in.png", "out.png", "compact", "2"]}
+
+and answers with the usual protocol lines (@phase, @progress, @result, see
+protocol.py) followed by a terminator:
+
+  @done                 the script returned
+  @error <message>      the script raised or called sys.exit(non-zero)
+
+Each script exposes run(argv), the same entry point as its __main__ block.
+"""
+import sys
+import os
+import json
+import importlib
+import traceback
+
+sys.path.insert(0, os.path.dirname(os.path.abspath(__file__)))
+
+
+def handle(script, args):
+    name = os.path.splitext(os.path.basename(script))[0]
+    module = importlib.import_module(name)  # importé une seule fois, puis en cache dans sys.modules
+    module.run(args)
+
+
+def reply(line):
+    sys.stdout.write(line + '\n')
+    sys.stdout.flush()
+
+
+def main():
+    for line in sys.stdin:
+        line = line.strip()
+        if not line:
+            continue
+        try:
+            request = json.loads(line)
+            handle(request['script'], [str(a) for a in request.get('args', [])])
+            reply('@done')
+        except SystemExit as e:
+            if e.code in (None, 0):
+                reply('@done')
+            else:
+                reply(f'@error exit {e.code}')
+        except Exception as e:
+            traceback.print_exc(file=sys.stderr)
+            sys.stderr.flush()
+            reply('@error ' + (str(e).splitlines() or [type(e).__name__])[0])
+
+
+if __name__ == '__main__':
+    main()
@@ -0,0 +1,479 @@
+import { createReadStream, createWriteStream, openSync, readSync, closeSync, fstatSync, statSync, unlinkSync } from 'fs'
+import { Readable, Transform } from 'stream'
+import { pipeline } from 'stream/promises'
+import { createInflateRaw, createGunzip, crc32 } from 'zlib'
+import { MAX_ARCHIVE_SIZE } from './config.js'
+
+// Lecture de ZIP et de tar (éventuellement gzippés) sans dépendance et sans charger
+// l'archive en mémoire : chaque entrée est décompressée directement vers son fichier.
+//   ZIP : répertoire central lu à la fin du fichier (ZIP64 compris), puis une lecture par entrée
+//   tar : un seul passage séquentiel sur le flux (ustar, noms longs GNU et pax)
+// Écriture ZIP en flux pour les exports (ZipWriter, en fin de fichier).
+
+export const ARCHIVE_EXTENSIONS = ['.zip', '.tar', '.tar.gz', '.tgz']
+
+export function archiveKind(filename) {
+  const lower = filename.toLowerCase()
+  if (lower.endsWith('.zip')) return 'zip'
+  if (lower.endsWith('.tar')) return 'tar'
+  if (lower.endsWith('.tar.gz') || lower.endsWith('.tgz')) return 'tgz'
+  return null
+}
+
+/**
+ * Walk the entries of an archive. `onEntry({ name, size })` is called for every file
+ * and returns the path to write it to, or a rejection reason as `{ reason }`, or null
+ * to ignore it silently. Entries larger than `maxEntrySize` once unpacked are rejected
+ * (the declared size is not trusted). Returns `{ extracted, rejected }`.
+ */
+export async function extractArchive(file, kind, onEntry, { maxEntrySize = Infinity, maxEntries = Infinity } = {}) {
+  const extracted = []
+  const rejected = []
+  let count = 0
+
+  const handle = async (name, size, write) => {
+    if (++count > maxEntries) {
+      rejected.push({ name, reason: `Plus de ${maxEntries} fichiers dans l'archive` })
+      return
+    }
+    if (size > maxEntrySize) {
+      rejected.push({ name, reason: `Fichier trop volumineux (${Math.round(size / 1024 / 1024)} Mo)` })
+      return
+    }
+    const decision = onEntry({ name, size })
+    if (!decision) return
+    if (decision.reason) {
+      rejected.push({ name, reason: decision.reason })
+      return
+    }
+    try {
+      const written = await write(decision, sizeGuard(maxEntrySize))
+      extracted.push({ name, path: decision, size: written })
+    } catch (err) {
+      try { unlinkSync(decision) } catch {}
+      rejected.push({ name, reason: err.message })
+    }
+  }
+
+  if (kind === 'zip') await readZip(file, handle)
+  else await readTar(file, kind === 'tgz', handle)
+  return { extracted, rejected }
+}
+
+/** Counts the bytes going through and fails past `max` (zip bombs, lying headers) */
+function sizeGuard(max) {
+  let total = 0
+  const guard = new Transform({
+    transform(chunk, _enc, cb) {
+      total += chunk.length
+      if (total > max) return cb(new Error(`Fichier trop volumineux (plus de ${Math.round(max / 1024 / 1024)} Mo)`))
+      cb(null, chunk)
+    },
+  })
+  guard.bytes = () => total
+  return guard
+}
+
+// --- ZIP ---
+
+const EOCD_SIG = 0x06054b50
+const ZIP64_LOCATOR_SIG = 0x07064b50
+const ZIP64_EOCD_SIG = 0x06064b50
+const CENTRAL_SIG = 0x02014b50
+const LOCAL_SIG = 0x04034b50
+const MAX_U32 = 0xffffffff
+
+function readAt(fd, position, length) {
+  const buf = Buffer.alloc(length)
+  const read = readSync(fd, buf, 0, length, position)
+  return buf.subarray(0, read)
+}
+
+/** Locate the central directory: { offset, size, entries } */
+function readEndOfCentralDirectory(fd, fileSize) {
+  // EOCD = 22 octets + commentaire (65535 max), à chercher depuis la fin
+  const tailSize = Math.min(fileSize, 22 + 0xffff)
+  const tail = readAt(fd, fileSize - tailSize, tailSize)
+  let pos = -1
+  for (let i = tail.length - 22; i >= 0; i--) {
+    if (tail.readUInt32LE(i) === EOCD_SIG) { pos = i; break }
+  }
+  if (pos < 0) throw new Error('Archive ZIP invalide (répertoire central introuvable)')
+
+  let entries = tail.readUInt16LE(pos + 10)
+  let size = tail.readUInt32LE(pos + 12)
+  let offset = tail.readUInt32LE(pos + 16)
+
+  if (entries === 0xffff || size === MAX_U32 || offset === MAX_U32) {
+    const locatorPos = fileSize - tailSize + pos - 20
+    const locator = readAt(fd, locatorPos, 20)
+    if (locator.length === 20 && locator.readUInt32LE(0) === ZIP64_LOCATOR_SIG) {
+      const record = readAt(fd, Number(locator.readBigUInt64LE(8)), 56)
+      if (record.length < 56 || record.readUInt32LE(0) !== ZIP64_EOCD_SIG) throw new Error('Archive ZIP64 invalide')
+      entries = Number(record.readBigUInt64LE(32))
+      size = Number(record.readBigUInt64LE(40))
+      offset = Number(record.readBigUInt64LE(48))
+    }
+  }
+  // Taille déclarée allouée telle quelle par readAt : elle doit tenir dans le fichier reçu
+  if (size > MAX_ARCHIVE_SIZE || offset + size > fileSize) {
+    throw new Error('Archive ZIP invalide (répertoire central hors du fichier)')
+  }
+  return { entries, size, offset }
+}
+
+/** 64-bit sizes/offset from the ZIP64 extra field, for the values saturated at 0xffffffff */
+function applyZip64Extra(entry, extra) {
+  let pos = 0
+  while (pos + 4 <= extra.length) {
+    const id = extra.readUInt16LE(pos)
+    const len = extra.readUInt16LE(pos + 2)
+    if (id === 0x0001) {
+      let p = pos + 4
+      for (const key of ['usize', 'csize', 'localOffset']) {
+        if (entry[key] === MAX_U32 && p + 8 <= pos + 4 + len) {
+          entry[key] = Number(extra.readBigUInt64LE(p))
+          p += 8
+        }
+      }
+      return
+    }
+    pos += 4 + len
+  }
+}
+
+async function readZip(file, handle) {
+  const fd = openSync(file, 'r')
+  let entries
+  try {
+    const { size: fileSize } = fstatSync(fd)
+    const cd = readEndOfCentralDirectory(fd, fileSize)
+    const dir = readAt(fd, cd.offset, cd.size)
+
+    entries = []
+    let pos = 0
+    for (let i = 0; i < cd.entries && pos + 46 <= dir.length; i++) {
+      if (dir.readUInt32LE(pos) !== CENTRAL_SIG) throw new Error('Archive ZIP invalide (entrée corrompue)')
+      const flags = dir.readUInt16LE(pos + 8)
+      const nameLen = dir.readUInt16LE(pos + 28)
+      const extraLen = dir.readUInt16LE(pos + 30)
+      const commentLen = dir.readUInt16LE(pos + 32)
+      const entry = {
+        name: dir.toString('utf8', pos + 46, pos + 46 + nameLen),
+        flags,
+        method: dir.readUInt16LE(pos + 10),
+        csize: dir.readUInt32LE(pos + 20),
+        usize: dir.readUInt32LE(pos + 24),
+        localOffset: dir.readUInt32LE(pos + 42),
+      }
+      applyZip64Extra(entry, dir.subarray(pos + 46 + nameLen, pos + 46 + nameLen + extraLen))
+      entries.push(entry)
+      pos += 46 + nameLen + extraLen + commentLen
+    }
+
+    // Début des données : après l'en-tête local, dont le champ extra peut différer du central
+    for (const entry of entries) {
+      const local = readAt(fd, entry.localOffset, 30)
+      if (local.length < 30 || local.readUInt32LE(0) !== LOCAL_SIG) {
+        entry.error = 'Entrée corrompue'
+        continue
+      }
+      entry.dataOffset = entry.localOffset + 30 + local.readUInt16LE(26) + local.readUInt16LE(28)
+    }
+  } finally {
+    closeSync(fd)
+  }
+
+  for (const entry of entries) {
+    if (entry.name.endsWith('/')) continue // dossier
+    await handle(entry.name, entry.usize, async (dest, guard) => {
+      if (entry.error) throw new Error(entry.error)
+      if (entry.flags & 0x1) throw new Error('Fichier chiffré')
+      if (entry.method !== 0 && entry.method !== 8) throw new Error(`Compression non prise en charge (méthode ${entry.method})`)
+      const stages = [entry.csize
+        ? createReadStream(file, { start: entry.dataOffset, end: entry.dataOffset + entry.csize - 1 })
+        : Readable.from([])]
+      if (entry.method === 8) stages.push(createInflateRaw())
+      await pipeline(...stages, guard, createWriteStream(dest))
+      if (guard.bytes() !== entry.usize) throw new Error('Entrée corrompue (taille inattendue)')
+      return guard.bytes()
+    })
+  }
+}
+
+// --- tar ---
+
+const BLOCK = 512
+
+function tarString(header, start, length) {
+  const end = header.indexOf(0, start)
+  return header.toString('utf8', start, end >= 0 && end < start + length ? end : start + length)
+}
+
+function tarNumber(header, start, length) {
+  // Extension GNU : base 256 si le bit de poids fort est levé (fichiers > 8 Go)
+  if (header[start] & 0x80) {
+    let value = 0
+    for (let i = start + 1; i < start + length; i++) value = value * 256 + header[i]
+    return value
+  }
+  return parseInt(tarString(header, start, length).trim() || '0', 8)
+}
+
+/** Resolves once `stream` can take more data, or is gone */
+function writable(stream) {
+  return new Promise((resolve) => {
+    const done = () => {
+      stream.off('drain', done)
+      stream.off('close', done)
+      resolve()
+    }
+    stream.on('drain', done)
+    stream.on('close', done)
+  })
+}
+
+/** `path=...` record of a pax extended header */
+function paxPath(data) {
+  for (const record of data.toString('utf8').split('\n')) {
+    const match = record.match(/^\d+ path=(.*)$/)
+    if (match) return match[1]
+  }
+  return null
+}
+
+async function readTar(file, gzipped, handle) {
+  const raw = createReadStream(file)
+  const source = gzipped ? raw.pipe(createGunzip()) : raw
+
+  let buf = Buffer.alloc(0)
+  let current = null // entrée en cours : { remaining, skip, sinkReady?, done?, collect? }
+  let nextName = null // nom long (GNU 'L' ou pax 'x') pour l'entrée suivante
+
+  // Les données d'un fichier gardé sont écrites dans le flux que handle() branche sur sa destination
+  const startEntry = (name, size) => {
+    let resolveSink
+    const entry = {
+      remaining: size,
+      skip: (BLOCK - (size % BLOCK)) % BLOCK,
+      sinkReady: new Promise((resolve) => { resolveSink = resolve }),
+    }
+    entry.done = handle(name, size, async (dest, guard) => {
+      const written = pipeline(guard, createWriteStream(dest))
+      resolveSink(guard)
+      await written
+      return guard.bytes()
+    }).finally(() => resolveSink(null))
+    return entry
+  }
+
+  const closeEntry = async (entry, err) => {
+    const sink = await entry.sinkReady
+    if (sink && !sink.destroyed) {
+      if (err) sink.destroy(err)
+      else sink.end()
+    }
+    await entry.done
+  }
+
+  try {
+    read: for await (const chunk of source) {
+      buf = buf.length ? Buffer.concat([buf, chunk]) : chunk
+
+      for (;;) {
+        if (current) {
+          if (current.remaining > 0) {
+            if (!buf.length) break
+            const part = buf.subarray(0, Math.min(buf.length, current.remaining))
+            buf = buf.subarray(part.length)
+            current.remaining -= part.length
+            if (current.collect) current.collect.push(part)
+            else if (current.sinkReady) {
+              const sink = await current.sinkReady
+              if (sink && !sink.destroyed && !sink.write(part)) await writable(sink)
+            }
+            continue
+          }
+          if (current.skip > 0) {
+            if (!buf.length) break
+            const n = Math.min(buf.length, current.skip)
+            buf = buf.subarray(n)
+            current.skip -= n
+            continue
+          }
+          if (current.collect) current.onCollected(Buffer.concat(current.collect))
+          else if (current.sinkReady) await closeEntry(current)
+          current = null
+        }
+
+        if (buf.length < BLOCK) break
+        const header = buf.subarray(0, BLOCK)
+        buf = buf.subarray(BLOCK)
+        if (header.every((b) => b === 0)) break read // fin d'archive
+
+        const type = String.fromCharCode(header[156] || 0x30)
+        const size = tarNumber(header, 124, 12)
+        const skip = (BLOCK - (size % BLOCK)) % BLOCK
+        const ustar = header.toString('ascii', 257, 262) === 'ustar'
+        const prefix = ustar ? tarString(header, 345, 155) : ''
+        const name = nextName || (prefix ? `${prefix}/${tarString(header, 0, 100)}` : tarString(header, 0, 100))
+
+        if (type === 'L' || type === 'x') {
+          current = {
+            remaining: size, skip, collect: [],
+            onCollected: (data) => { nextName = type === 'L' ? data.toString('utf8').replace(/\0+$/, '') : paxPath(data) },
+          }
+          continue
+        }
+        nextName = null
+        if (type === '0' || type === '7') current = startEntry(name, size)
+        else current = { remaining: size, skip } // dossiers, liens, en-têtes globaux : ignorés
+      }
+    }
+    // Archive tronquée au milieu d'un fichier
+    if (current?.sinkReady && current.remaining > 0) await closeEntry(current, new Error('Archive tronquée'))
+    else if (current?.sinkReady) await closeEntry(current)
+  } catch (err) {
+    if (current?.sinkReady) await closeEntry(current, new Error('Archive illisible'))
+    throw new Error(`Archive tar illisible : ${err.message}`)
+  } finally {
+    source.destroy()
+    raw.destroy()
+  }
+}
+
+// --- Écriture ZIP ---
+
+// Entrées stockées sans compression (images déjà compressées) : le CRC est calculé par
+// une première lecture du fichier, puis en-tête local complet et copie en flux vers la
+// réponse. Aucun fichier temporaire ; ZIP64 au-delà de 4 Go ou de 65535 entrées.
+
+function dosDateTime(date) {
+  return {
+    time: (date.getHours() << 11) | (date.getMinutes() << 5) | (date.getSeconds() >> 1),
+    day: ((Math.max(date.getFullYear(), 1980) - 1980) << 9) | ((date.getMonth() + 1) << 5) | date.getDate(),
+  }
+}
+
+function zip64Extra(values) {
+  const extra = Buffer.alloc(4 + 8 * values.length)
+  extra.writeUInt16LE(0x0001, 0)
+  extra.writeUInt16LE(8 * values.length, 2)
+  values.forEach((v, i) => extra.writeBigUInt64LE(BigInt(v), 4 + 8 * i))
+  return extra
+}
+
+export class ZipWriter {
+  constructor(out) {
+    this.out = out
+    this.offset = 0
+    this.entries = []
+  }
+
+  async write(buf) {
+    if (this.out.destroyed) throw new Error('Téléchargement interrompu')
+    this.offset += buf.length
+    if (!this.out.write(buf)) await writable(this.out)
+  }
+
+  /** Copy a file from disk as `name` (path inside the archive, "/" separated) */
+  async addFile(name, file) {
+    let crc = 0
+    let size = 0
+    for await (const chunk of createReadStream(file)) {
+      crc = crc32(chunk, crc)
+      size += chunk.length
+    }
+    await this.header(name, crc, size, statSync(file).mtime)
+    let copied = 0
+    for await (const chunk of createReadStream(file)) {
+      await this.write(chunk)
+      copied += chunk.length
+    }
+    if (copied !== size) throw new Error(`${name} modifié pendant l'export`)
+  }
+
+  async addBuffer(name, data, mtime = new Date()) {
+    await this.header(name, crc32(data), data.length, mtime)
+    await this.write(data)
+  }
+
+  async header(name, crc, size, mtime) {
+    const nameBuf = Buffer.from(name, 'utf8')
+    const zip64 = size >= MAX_U32
+    const { time, day } = dosDateTime(mtime)
+    const extra = zip64 ? zip64Extra([size, size]) : Buffer.alloc(0)
+    const local = Buffer.alloc(30)
+    local.writeUInt32LE(LOCAL_SIG, 0)
+    local.writeUInt16LE(zip64 ? 45 : 20, 4)
+    local.writeUInt16LE(0x0800, 6) // noms en UTF-8
+    local.writeUInt16LE(0, 8) // stored
+    local.writeUInt16LE(time, 10)
+    local.writeUInt16LE(day, 12)
+    local.writeUInt32LE(crc, 14)
+    local.writeUInt32LE(zip64 ? MAX_U32 : size, 18)
+    local.writeUInt32LE(zip64 ? MAX_U32 : size, 22)
+    local.writeUInt16LE(nameBuf.length, 26)
+    local.writeUInt16LE(extra.length, 28)
+    this.entries.push({ nameBuf, crc, size, time, day, offset: this.offset })
+    await this.write(Buffer.concat([local, nameBuf, extra]))
+  }
+
+  /** Write the central directory and end the output stream */
+  async finish() {
+    const cdOffset = this.offset
+    for (const e of this.entries) {
+      const big = e.size >= MAX_U32
+      const farOffset = e.offset >= MAX_U32
+      const extra = big || farOffset
+        ? zip64Extra([...(big ? [e.size, e.size] : []), ...(farOffset ? [e.offset] : [])])
+        : Buffer.alloc(0)
+      const central = Buffer.alloc(46)
+      central.writeUInt32LE(CENTRAL_SIG, 0)
+      central.writeUInt16LE((3 << 8) | 45, 4) // Unix, 4.5
+      central.writeUInt16LE(extra.length ? 45 : 20, 6)
+      central.writeUInt16LE(0x0800, 8)
+      central.writeUInt16LE(0, 10)
+      central.writeUInt16LE(e.time, 12)
+      central.writeUInt16LE(e.day, 14)
+      central.writeUInt32LE(e.crc, 16)
+      central.writeUInt32LE(big ? MAX_U32 : e.size, 20)
+      central.writeUInt32LE(big ? MAX_U32 : e.size, 24)
+      central.writeUInt16LE(e.nameBuf.length, 28)
+      central.writeUInt16LE(extra.length, 30)
+      central.writeUInt32LE(0o100644 * 0x10000, 38) // -rw-r--r--
+      central.writeUInt32LE(farOffset ? MAX_U32 : e.offset, 42)
+      await this.write(Buffer.concat([central, e.nameBuf, extra]))
+    }
+    const cdSize = this.offset - cdOffset
+    const count = this.entries.length
+
+    const zip64 = count >= 0xffff || cdOffset >= MAX_U32 || cdSize >= MAX_U32
+    if (zip64) {
+      const recordOffset = this.offset
+      const record = Buffer.alloc(56)
+      record.writeUInt32LE(ZIP64_EOCD_SIG, 0)
+      record.writeBigUInt64LE(44n, 4)
+      record.writeUInt16LE(45, 12)
+      record.writeUInt16LE(45, 14)
+      record.writeBigUInt64LE(BigInt(count), 24)
+      record.writeBigUInt64LE(BigInt(count), 32)
+      record.writeBigUInt64LE(BigInt(cdSize), 40)
+      record.writeBigUInt64LE(BigInt(cdOffset), 48)
+      const locator = Buffer.alloc(20)
+      locator.writeUInt32LE(ZIP64_LOCATOR_SIG, 0)
+      locator.writeBigUInt64LE(BigInt(recordOffset), 8)
+      locator.writeUInt32LE(1, 16)
+      await this.write(Buffer.concat([record, locator]))
+    }
+
+    const end = Buffer.alloc(22)
+    end.writeUInt32LE(EOCD_SIG, 0)
+    end.writeUInt16LE(Math.min(count, 0xffff), 8)
+    end.writeUInt16LE(Math.min(count, 0xffff), 10)
+    end.writeUInt32LE(zip64 ? MAX_U32 : cdSize, 12)
+    end.writeUInt32LE(zip64 ? MAX_U32 : cdOffset, 16)
+    await this.write(end)
+    this.out.end()
+  }
+}
@@ -0,0 +1,24 @@
+import { test } from 'node:test'
+import assert from 'node:assert/strict'
+import { mkdtempSync, writeFileSync } from 'fs'
+import os from 'os'
+import path from 'path'
+
+const dir = mkdtempSync(path.join(os.tmpdir(), 'oldphotos-test-'))
+process.env.UPLOADS_DIR = path.join(dir, 'uploads')
+process.env.RESULTS_DIR = path.join(dir, 'results')
+
+const { extractArchive } = await import('./archive.js')
+
+test('a central directory larger than the archive is rejected before being read', async () => {
+  // EOCD seul, annonçant un répertoire central de près de 4 Go
+  const eocd = Buffer.alloc(22)
+  eocd.writeUInt32LE(0x06054b50, 0)
+  eocd.writeUInt16LE(1, 8)
+  eocd.writeUInt16LE(1, 10)
+  eocd.writeUInt32LE(0xfffffff0, 12)
+  eocd.writeUInt32LE(0, 16)
+  const file = path.join(dir, 'bad.zip')
+  writeFileSync(file, eocd)
+  await assert.rejects(extractArchive(file, 'zip', () => null), /répertoire central hors du fichier/)
+})
@@ -0,0 +1,221 @@
+import { createHash, createHmac, randomBytes, scryptSync, timingSafeEqual } from 'crypto'
+import { existsSync, readFileSync, statSync, writeFileSync } from 'fs'
+import path from 'path'
+import { fileURLToPath } from 'url'
+import { AUTH_PASSWORD, AUTH_USERS_FILE, AUTH_SECRET, AUTH_SESSION_MS, DATA_DIR } from './config.js'
+import { apiTokens } from './storage.js'
+import { readCookie } from './utils.js'
+
+// Trois façons d'être authentifié :
+//  - cookie de connexion signé (HMAC) posé par POST /api/auth/login, pour l'interface
+//  - en-tête `Authorization: Bearer <jeton>` pour les scripts (jetons créés depuis l'interface)
+//  - rien du tout si ni AUTH_PASSWORD ni AUTH_USERS_FILE ne sont définis
+
+export const AUTH_COOKIE = 'oldphotos_auth'
+export const ADMIN_USER = 'admin'
+const TOKEN_PREFIX = 'opt_'
+
+// Accessibles sans authentification (healthcheck Docker)
+const PUBLIC_PATHS = new Set(['/api/status'])
+
+export function authMode() {
+  if (AUTH_USERS_FILE) return 'users'
+  if (AUTH_PASSWORD) return 'password'
+  return null
+}
+
+export function authEnabled() {
+  return authMode() !== null
+}
+
+// --- Mots de passe ---
+
+/** 'scrypt$<salt>$<hash>' (hex), the format expected in AUTH_USERS_FILE and AUTH_PASSWORD */
+export function hashPassword(password) {
+  const salt = randomBytes(16)
+  return `scrypt$${salt.toString('hex')}$${scryptSync(password, salt, 32).toString('hex')}`
+}
+
+function verifyPassword(password, stored) {
+  if (typeof password !== 'string' || !stored) return false
+  const [scheme, saltHex, hashHex] = stored.split('$')
+  if (scheme !== 'scrypt' || !saltHex || !hashHex) {
+    // Mot de passe en clair dans l'env : comparaison à temps constant sur les empreintes
+    return timingSafeEqual(sha256(password), sha256(stored))
+  }
+  const expected = Buffer.from(hashHex, 'hex')
+  const actual = scryptSync(password, Buffer.from(saltHex, 'hex'), expected.length)
+  return timingSafeEqual(actual, expected)
+}
+
+let usersCache = null  // { mtimeMs, users }
+
+/** Users file, one `name:scrypt$salt$hash` per line ('#' comments). Re-read when modified. */
+function readUsers() {
+  const users = new Map()
+  let content
+  try {
+    const { mtimeMs } = statSync(AUTH_USERS_FILE)
+    if (usersCache?.mtimeMs === mtimeMs) return usersCache.users
+    content = readFileSync(AUTH_USERS_FILE, 'utf8')
+    usersCache = { mtimeMs, users }
+  } catch (err) {
+    console.error(`Auth : fichier d'utilisateurs illisible (${AUTH_USERS_FILE}) :`, err.message)
+    usersCache = null
+    return users
+  }
+  for (const line of content.split('\n')) {
+    const trimmed = line.trim()
+    if (!trimmed || trimmed.startsWith('#')) continue
+    const colon = trimmed.indexOf(':')
+    if (colon > 0) users.set(trimmed.slice(0, colon), trimmed.slice(colon + 1))
+  }
+  return users
+}
+
+/** Checked credentials → user name, null if wrong */
+export function checkCredentials(username, password) {
+  if (AUTH_USERS_FILE) {
+    const name = typeof username === 'string' ? username.trim() : ''
+    const stored = readUsers().get(name)
+    if (stored && verifyPassword(password, stored)) return name
+  }
+  // Le mot de passe unique reste valable à côté du fichier (compte « admin »)
+  if (AUTH_PASSWORD && (!username || username === ADMIN_USER) && verifyPassword(password, AUTH_PASSWORD)) {
+    return ADMIN_USER
+  }
+  return null
+}
+
+/** The user can still log in: listed in the users file, or `admin` while AUTH_PASSWORD is set */
+function userExists(user) {
+  if (AUTH_USERS_FILE && readUsers().has(user)) return true
+  return user === ADMIN_USER && !!AUTH_PASSWORD
+}
+
+// --- Cookie de connexion signé ---
+
+let secret = null
+
+function getSecret() {
+  if (secret) return secret
+  if (AUTH_SECRET) return (secret = Buffer.from(AUTH_SECRET))
+  // Gardée sur le volume de données pour que les connexions survivent aux redémarrages
+  const file = path.join(DATA_DIR, 'auth.secret')
+  if (existsSync(file)) {
+    secret = Buffer.from(readFileSync(file, 'utf8').trim(), 'hex')
+  } else {
+    secret = randomBytes(32)
+    writeFileSync(file, secret.toString('hex'), { mode: 0o600 })
+  }
+  return secret
+}
+
+function sign(payload) {
+  return createHmac('sha256', getSecret()).update(payload).digest('base64url')
+}
+
+/** Cookie value: base64url(JSON { u, exp }) + '.' + HMAC */
+export function createSessionCookie(user) {
+  const payload = Buffer.from(JSON.stringify({ u: user, exp: Date.now() + AUTH_SESSION_MS })).toString('base64url')
+  return `${payload}.${sign(payload)}`
+}
+
+function readSessionCookie(value) {
+  const dot = value?.lastIndexOf('.') ?? -1
+  if (dot < 1) return null
+  const payload = value.slice(0, dot)
+  const mac = Buffer.from(value.slice(dot + 1))
+  const expected = Buffer.from(sign(payload))
+  if (mac.length !== expected.length || !timingSafeEqual(mac, expected)) return null
+  try {
+    const { u, exp } = JSON.parse(Buffer.from(payload, 'base64url').toString())
+    return typeof u === 'string' && exp > Date.now() ? u : null
+  } catch {
+    return null
+  }
+}
+
+export function setSessionCookie(req, res, user) {
+  res.cookie(AUTH_COOKIE, createSessionCookie(user), {
+    httpOnly: true,
+    sameSite: 'lax',
+    secure: req.secure,
+    maxAge: AUTH_SESSION_MS,
+  })
+}
+
+// --- Jetons d'API ---
+
+function sha256(value) {
+  return createHash('sha256').update(value).digest()
+}
+
+/** New token for `user`: the clear value is returned once, only its hash is stored */
+export function createApiToken(user, name) {
+  const token = TOKEN_PREFIX + randomBytes(32).toString('base64url')
+  const record = {
+    id: randomBytes(8).toString('hex'),
+    name,
+    user,
+    hash: sha256(token).toString('hex'),
+    hint: token.slice(-4),
+    createdAt: new Date().toISOString(),
+    lastUsedAt: null,
+  }
+  apiTokens.set(record.id, record)
+  return { token, record }
+}
+
+/** Token record as listed in the UI (no hash) */
+export function toPublicToken({ hash, ...rest }) {
+  return rest
+}
+
+function userFromToken(token) {
+  if (!token.startsWith(TOKEN_PREFIX)) return null
+  const hash = sha256(token).toString('hex')
+  for (const record of apiTokens.values()) {
+    if (record.hash !== hash) continue
+    // Date de dernière utilisation, écrite au plus une fois par heure dans le journal
+    if (!record.lastUsedAt || Date.now() - Date.parse(record.lastUsedAt) > 3600000) {
+      record.lastUsedAt = new Date().toISOString()
+      apiTokens.set(record.id, record)
+    }
+    return record.user
+  }
+  return null
+}
+
+/** Authenticated user of a request (cookie or bearer token), null if none */
+export function userFromRequest(req) {
+  const header = req.get('Authorization')
+  const user = header?.startsWith('Bearer ')
+    ? userFromToken(header.slice(7).trim())
+    : readSessionCookie(readCookie(req, AUTH_COOKIE))
+  // Cookie et jetons d'un utilisateur retiré du fichier (ou d'admin sans AUTH_PASSWORD) refusés
+  return user && userExists(user) ? user : null
+}
+
+/** Express middleware: sets `req.user`, 401 without valid credentials (no-op when auth is off) */
+export function requireAuth(req, res, next) {
+  if (!authEnabled() || PUBLIC_PATHS.has(req.path)) return next()
+  const user = userFromRequest(req)
+  if (!user) return res.status(401).json({ error: 'Authentication required' })
+  req.user = user
+  next()
+}
+
+// node server/auth.js hash-password < mot_de_passe → ligne pour AUTH_USERS_FILE / valeur d'AUTH_PASSWORD
+if (process.argv[1] && path.resolve(process.argv[1]) === fileURLToPath(import.meta.url)) {
+  if (process.argv[2] !== 'hash-password') {
+    console.error('Usage : node server/auth.js hash-password < mot_de_passe')
+    process.exit(1)
+  }
+  const password = readFileSync(0, 'utf8').replace(/\r?\n$/, '')
+  if (!password) {
+    console.error('Mot de passe vide')
+    process.exit(1)
+  }
+  console.log(hashPassword(password))
+}
@@ -0,0 +1,97 @@
+import path from 'path'
+import { randomUUID } from 'crypto'
+import { UPLOADS_DIR } from './config.js'
+import { photos, jobs } from './storage.js'
+import { STEPS } from './steps/index.js'
+import { publishJob } from './events.js'
+import { getUrlForPath } from './utils.js'
+
+// Pipelines en arbre : un job exécute ses `steps` (le tronc), puis chaque entrée de
+// `branches` devient un job enfant qui repart de son résultat, sans refaire l'amont.
+//   branches: [{ steps: ['colorize'], options: { colorize: 'ddcolor' }, label?, branches: [...] }]
+// Une branche sans étapes garde le résultat du parent comme variante à part entière.
+
+const MAX_DEPTH = 8
+const MAX_BRANCHES = 16
+
+/** Validate a branch tree from the API; returns null if there is nothing usable */
+export function normalizeBranches(branches, depth = 0) {
+  if (!Array.isArray(branches) || !branches.length || depth >= MAX_DEPTH) return null
+  const out = []
+  for (const branch of branches.slice(0, MAX_BRANCHES)) {
+    if (!branch || typeof branch !== 'object') continue
+    const steps = (Array.isArray(branch.steps) ? branch.steps : []).filter(s => STEPS[s])
+    const options = {}
+    for (const step of steps) {
+      const model = branch.options?.[step]
+      if (model && STEPS[step].models?.[model]) options[step] = model
+    }
+    out.push({
+      steps,
+      options,
+      label: typeof branch.label === 'string' && branch.label.trim() ? branch.label.trim().slice(0, 80) : null,
+      branches: normalizeBranches(branch.branches, depth + 1),
+    })
+  }
+  return out.length ? out : null
+}
+
+function branchLabel(branch) {
+  if (branch.label) return branch.label
+  if (!branch.steps.length) return null // même image que le parent
+  return branch.steps.map((step) => {
+    const model = branch.options[step]
+    const modelName = model ? STEPS[step].models?.[model]?.name : null
+    return modelName ? `${STEPS[step].name} (${modelName})` : STEPS[step].name
+  }).join(' + ')
+}
+
+/**
+ * Create the child jobs of a finished job, one per branch. Their input is the parent's
+ * output; called once, right before the parent is published as completed.
+ */
+export function spawnBranches(job) {
+  if (!job.branches?.length || job.childJobIds?.length) return []
+
+  const photo = photos.get(job.photoId)
+  const inputPath = job.currentInputPath || job.inputPath || (photo ? path.join(UPLOADS_DIR, photo.filename) : null)
+  if (!inputPath) return []
+
+  const children = job.branches.map((branch, i) => {
+    const label = branchLabel(branch)
+    const child = {
+      id: randomUUID(),
+      sessionId: job.sessionId,
+      detached: !!job.detached,
+      batchId: job.batchId || null,
+      photoId: job.photoId,
+      photoName: job.photoName,
+      original: job.original,
+      parentJobId: job.id,
+      rootJobId: job.rootJobId || job.id,
+      branchLabel: [job.branchLabel, label].filter(Boolean).join(' › ') || 'Base',
+      base: job.result || getUrlForPath(inputPath),
+      inputPath,
+      steps: branch.steps,
+      options: { ...job.options, ...branch.options },
+      params: job.params,
+      branches: branch.branches,
+      maskPath: null,
+      cropRect: null,
+      status: 'pending',
+      progress: 0,
+      createdAt: new Date().toISOString(),
+      result: null,
+      stepResults: [],
+      // Juste après le parent dans la file, dans l'ordre des branches
+      priority: (job.priority || Date.now()) + (i + 1) / 1000,
+    }
+    jobs.set(child.id, child)
+    publishJob('job_created', child)
+    return child
+  })
+
+  job.childJobIds = children.map(c => c.id)
+  console.log(`Job ${job.id} | ${children.length} variante(s) : ${children.map(c => c.branchLabel).join(', ')}`)
+  return children
+}
@@ -1,39 +1,214 @@
 import { existsSync, readdirSync, statSync, unlinkSync } from 'fs'
 import path from 'path'
-import { UPLOADS_DIR, RESULTS_DIR, ROOT, CLEANUP_INTERVAL_MS, CLEANUP_MAX_AGE_MS } from './config.js'
+import { UPLOADS_DIR, RESULTS_DIR, CLEANUP_INTERVAL_MS, CLEANUP_MAX_AGE_MS, CLEANUP_DETACHED_MAX_AGE_MS } from './config.js'
 import { photos, jobs } from './storage.js'
+import { lastSeen, forgetSession } from './heartbeat.js'
+import { getPathForUrl, getUrlForPath } from './utils.js'
 
-function cleanupOldFiles() {
-  const now = Date.now()
-  let removed = 0
-  for (const dir of [UPLOADS_DIR, RESULTS_DIR]) {
-    let files
-    try { files = readdirSync(dir) } catch { continue }
+const ACTIVE_STATUSES = new Set(['pending', 'processing', 'waiting_input', 'paused'])
+// Un job échoué peut encore être relancé ou son étape sautée : ses fichiers restent utiles
+const LIVE_STATUSES = new Set([...ACTIVE_STATUSES, 'failed'])
+
+/** Files on disk produced or used by a job: mask, intermediates, comparison candidates, result */
+export function jobFiles(job) {
+  const urls = [job.result, job.waitingImage]
+  for (const sr of job.stepResults || []) {
+    urls.push(sr.result)
+    for (const candidate of sr.comparison || []) urls.push(candidate.result)
+  }
+  return [job.maskPath, ...urls.map(getPathForUrl)].filter(Boolean)
+}
+
+/**
+ * Detached job finished while its session was away (bilan « Pendant votre absence » pas
+ * encore vu), for less than CLEANUP_DETACHED_MAX_AGE_MS. Watch folder jobs have the outbox.
+ */
+function awaitingReview(job, now) {
+  if (!job.detached || job.watch || ACTIVE_STATUSES.has(job.status)) return false
+  const finishedAt = Date.parse(job.completedAt || job.failedAt || job.createdAt)
+  return now - finishedAt <= CLEANUP_DETACHED_MAX_AGE_MS && (lastSeen(job.sessionId) ?? 0) < finishedAt
+}
+
+/**
+ * Files that must not be removed → why: still needed by a live job (original, current input,
+ * mask, step results), or pinned (photo original, job final result)
+ */
+export function referencedFiles() {
+  const refs = new Map()  // chemin → { jobId, status } | { photoId | jobId, pinned }
+  for (const job of jobs.values()) {
+    if (!LIVE_STATUSES.has(job.status)) continue
+    const photo = photos.get(job.photoId)
+    const files = [
+      photo && path.join(UPLOADS_DIR, photo.filename),
+      getPathForUrl(job.original),
+      getPathForUrl(job.base),
+      job.inputPath,
+      job.currentInputPath,
+      ...jobFiles(job),
+    ]
     for (const file of files) {
-      if (file === '.gitkeep') continue
-      const filePath = path.join(dir, file)
-      try {
-        const { mtimeMs } = statSync(filePath)
-        if (now - mtimeMs > CLEANUP_MAX_AGE_MS) {
-          unlinkSync(filePath)
-          removed++
-        }
-      } catch {}
+      if (file && !refs.has(file)) refs.set(file, { jobId: job.id, status: job.status })
     }
   }
-  // Purger les références en mémoire vers des fichiers supprimés
+  for (const photo of photos.values()) {
+    if (photo.pinned) refs.set(path.join(UPLOADS_DIR, photo.filename), { photoId: photo.id, pinned: true })
+  }
+  for (const job of jobs.values()) {
+    const result = job.pinned && getPathForUrl(job.result)
+    if (result) refs.set(result, { jobId: job.id, pinned: true })
+  }
+  return refs
+}
+
+/**
+ * Sessions idle for more than CLEANUP_MAX_AGE_MS, with the records and files to remove.
+ * Activity is the last request of the session, or its newest photo/job after a restart.
+ * Sessions with an unfinished job, or a detached result not seen yet, are kept; pinned photos
+ * and jobs (and their files) survive.
+ */
+function idleSessions(now, referenced) {
+  const sessions = new Map()  // sessionId → { photos, jobs }
+  const entry = (id) => {
+    if (!sessions.has(id)) sessions.set(id, { photos: [], jobs: [] })
+    return sessions.get(id)
+  }
+  for (const photo of photos.values()) if (photo.sessionId && !photo.pinned) entry(photo.sessionId).photos.push(photo)
+  for (const job of jobs.values()) if (job.sessionId && !job.pinned) entry(job.sessionId).jobs.push(job)
+
+  const idle = []
+  for (const [sessionId, owned] of sessions) {
+    if (owned.jobs.some(j => ACTIVE_STATUSES.has(j.status) || awaitingReview(j, now))) continue
+    const dates = [
+      ...owned.photos.map(p => Date.parse(p.uploadedAt)),
+      ...owned.jobs.map(j => Date.parse(j.completedAt || j.createdAt)),
+    ].filter(Number.isFinite)
+    const lastActivity = Math.max(lastSeen(sessionId) ?? 0, ...dates)
+    if (now - lastActivity <= CLEANUP_MAX_AGE_MS) continue
+    const files = new Set([
+      ...owned.photos.map(p => path.join(UPLOADS_DIR, p.filename)),
+      ...owned.jobs.flatMap(jobFiles),
+    ].filter(f => !referenced.has(f)))
+    idle.push({ sessionId, lastActivity, photos: owned.photos, jobs: owned.jobs, files: [...files] })
+  }
+  return idle
+}
+
+/** Files of the photos and jobs owned by a session */
+function sessionFiles(sessionId) {
+  return new Set([
+    ...[...photos.values()].filter(p => p.sessionId === sessionId).map(p => path.join(UPLOADS_DIR, p.filename)),
+    ...[...jobs.values()].filter(j => j.sessionId === sessionId).flatMap(jobFiles),
+  ])
+}
+
+/**
+ * What a cleanup run would do right now, without touching anything:
+ * idle sessions to drop, old files to delete, old files kept (live job or pinned),
+ * and the photo/job records that would lose their file. With `sessionId`, only that
+ * session's records and files are considered (manual runs from the API).
+ */
+export function planCleanup({ sessionId = null, now = Date.now() } = {}) {
+  const referenced = referencedFiles()
+  // Résultats de nuit pas encore vus : gardés ici, mais pas protégés du quota disque
+  for (const job of jobs.values()) {
+    if (!awaitingReview(job, now)) continue
+    const photo = photos.get(job.photoId)
+    for (const file of [photo && path.join(UPLOADS_DIR, photo.filename), ...jobFiles(job)]) {
+      if (file && !referenced.has(file)) referenced.set(file, { jobId: job.id, detached: true })
+    }
+  }
+  const sessions = idleSessions(now, referenced).filter(s => !sessionId || s.sessionId === sessionId)
+  const idleFiles = new Set(sessions.flatMap(s => s.files))
+  const owned = sessionId ? sessionFiles(sessionId) : null
+
+  const files = []
+  const kept = []
+  for (const dir of [UPLOADS_DIR, RESULTS_DIR]) {
+    let names
+    try { names = readdirSync(dir) } catch { continue }
+    for (const name of names) {
+      if (name === '.gitkeep') continue
+      const filePath = path.join(dir, name)
+      if (idleFiles.has(filePath) || (owned && !owned.has(filePath))) continue
+      let stat
+      try { stat = statSync(filePath) } catch { continue }
+      if (!stat.isFile() || now - stat.mtimeMs <= CLEANUP_MAX_AGE_MS) continue
+      const entry = { path: filePath, url: getUrlForPath(filePath), size: stat.size, modifiedAt: new Date(stat.mtimeMs).toISOString() }
+      const ref = referenced.get(filePath)
+      if (ref) kept.push({ ...entry, ...ref })
+      else files.push(entry)
+    }
+  }
+
+  const deleted = new Set(files.map(f => f.path))
+  const orphanPhotos = [...photos.values()].filter(p => deleted.has(path.join(UPLOADS_DIR, p.filename)))
+  const orphanJobs = [...jobs.values()].filter(j => !LIVE_STATUSES.has(j.status) && deleted.has(getPathForUrl(j.result)))
+
+  return { maxAgeMs: CLEANUP_MAX_AGE_MS, sessionId, sessions, files, kept, photos: orphanPhotos, jobs: orphanJobs }
+}
+
+/**
+ * Plan as returned by the API: URLs instead of disk paths, ids instead of records.
+ * Session ids are workspace keys (sessions.js), never shown.
+ */
+export function toPublicPlan(plan) {
+  const strip = ({ path: _path, ...rest }) => rest
+  return {
+    maxAgeHours: plan.maxAgeMs / 3600000,
+    sessions: plan.sessions.map(s => ({
+      lastActivity: new Date(s.lastActivity).toISOString(),
+      photos: s.photos.length,
+      jobs: s.jobs.length,
+      files: s.files.length,
+    })),
+    files: plan.files.map(strip),
+    kept: plan.kept.map(strip),
+    photos: plan.photos.map(p => p.id),
+    jobs: plan.jobs.map(j => j.id),
+    bytes: plan.files.reduce((sum, f) => sum + f.size, 0),
+  }
+}
+
+/** Apply a cleanup plan now; returns the plan that was applied */
+export function runCleanup(plan = planCleanup()) {
+  for (const session of plan.sessions) {
+    for (const file of session.files) {
+      try { unlinkSync(file) } catch {}
+    }
+    for (const photo of session.photos) photos.delete(photo.id)
+    for (const job of session.jobs) jobs.delete(job.id)
+    forgetSession(session.sessionId)
+  }
+
+  let removed = 0
+  for (const file of plan.files) {
+    try {
+      unlinkSync(file.path)
+      removed++
+    } catch {}
+  }
+
+  // Purger les références en mémoire vers des fichiers supprimés (y compris à la main)
+  const inScope = (record) => !plan.sessionId || record.sessionId === plan.sessionId
   for (const [id, photo] of photos) {
-    if (!existsSync(path.join(UPLOADS_DIR, photo.filename))) photos.delete(id)
+    if (inScope(photo) && !existsSync(path.join(UPLOADS_DIR, photo.filename))) photos.delete(id)
   }
   for (const [id, job] of jobs) {
-    if (job.result && !existsSync(path.join(ROOT, job.result.replace(/^\//, '')))) jobs.delete(id)
+    // Résultat retiré par le quota disque : le job reste dans l'historique (quota.js)
+    if (!inScope(job) || LIVE_STATUSES.has(job.status) || job.resultEvicted) continue
+    const result = getPathForUrl(job.result)
+    if (result && !existsSync(result)) jobs.delete(id)
   }
+
+  if (plan.sessions.length > 0) console.log(`Nettoyage : ${plan.sessions.length} session(s) inactive(s) supprimée(s)`)
   if (removed > 0) console.log(`Nettoyage : ${removed} fichier(s) supprimé(s)`)
+  if (plan.kept.length > 0) console.log(`Nettoyage : ${plan.kept.length} fichier(s) ancien(s) gardé(s) (jobs en cours ou épinglés)`)
+  return plan
 }
 
 export function startCleanupTimer() {
   if (CLEANUP_INTERVAL_MS > 0) {
-    setInterval(cleanupOldFiles, CLEANUP_INTERVAL_MS)
+    setInterval(() => runCleanup(), CLEANUP_INTERVAL_MS)
     console.log(`Nettoyage auto : toutes les ${CLEANUP_INTERVAL_MS / 3600000}h, fichiers > ${CLEANUP_MAX_AGE_MS / 3600000}h`)
   }
 }
@@ -0,0 +1,248 @@
+import { existsSync, readdirSync, statSync, mkdirSync, mkdtempSync, copyFileSync, rmSync } from 'fs'
+import os from 'os'
+import path from 'path'
+import { parseArgs } from 'util'
+import { STEPS, resolveParams, paramError, fallbackChain } from './steps/index.js'
+import { runPythonStep, isAiReady, isSetupRunning, setScriptLogging } from './python.js'
+import { getStepLimits } from './limits.js'
+import { isImageFile } from './images.js'
+import { sanitizeFilename } from './utils.js'
+import { readManifest, writeManifest, pipelineSignature, sameInput } from './manifest.js'
+
+// Pipeline en ligne de commande, sans Express ni journal : mêmes étapes (server/steps) et
+// mêmes scripts que le serveur, sur un fichier ou une arborescence de scans.
+//   oldphotos run --steps spot_removal,colorize:ddcolor,upscale:x2plus scans/ -o out/
+// Les photos déjà traitées avec le même pipeline (manifeste .oldphotos.json du dossier de
+// sortie) sont sautées ; code de sortie 1 si une photo a échoué, 2 pour une erreur d'usage.
+
+const USAGE = `Usage :
+  oldphotos run --steps <étapes> -o <dossier> [options] <fichier|dossier>...
+  oldphotos steps
+
+Options de run :
+  -s, --steps <liste>       étapes dans l'ordre, modèle après « : »
+                            (spot_removal,colorize:ddcolor,upscale:x2plus)
+  -o, --output <dossier>    sortie en PNG, arborescence des dossiers d'entrée reproduite
+  -j, --jobs <n>            photos traitées en parallèle (défaut 1)
+  -p, --param <étape.nom=valeur>
+                            paramètre d'étape, répétable (upscale.scale=4)
+      --force               retraiter les photos déjà faites
+      --keep-intermediates  garder aussi l'image de chaque étape
+  -q, --quiet               n'afficher que les échecs et le bilan
+  -v, --verbose             afficher aussi les traces de chaque script
+  -h, --help`
+
+class UsageError extends Error {}
+
+/** "colorize:ddcolor" → { step, model }, checked against the step definitions */
+function parseStepSpec(spec) {
+  const [step, model] = spec.split(':').map(s => s.trim())
+  const stepDef = STEPS[step]
+  if (!stepDef) throw new UsageError(`Étape inconnue : ${step} (disponibles : ${Object.keys(STEPS).join(', ')})`)
+  if (stepDef.manual) throw new UsageError(`${step} demande une saisie manuelle, indisponible en ligne de commande`)
+  if (model && !stepDef.models?.[model]) {
+    throw new UsageError(stepDef.models
+      ? `Modèle inconnu pour ${step} : ${model} (disponibles : ${Object.keys(stepDef.models).join(', ')})`
+      : `${step} n'a pas de choix de modèle`)
+  }
+  return { step, model: stepDef.models ? (model || stepDef.defaultModel) : null }
+}
+
+/** ["upscale.scale=4"] → { upscale: { scale: 4 } } */
+function parseParams(values, steps) {
+  const params = {}
+  for (const value of values) {
+    const match = value.match(/^(\w+)\.(\w+)=(.+)$/)
+    if (!match) throw new UsageError(`Paramètre invalide : ${value} (attendu : étape.nom=valeur)`)
+    const [, step, name, raw] = match
+    if (!steps.includes(step)) throw new UsageError(`Paramètre pour une étape absente du pipeline : ${step}`)
+    const error = paramError(step, name, raw)
+    if (error) throw new UsageError(error)
+    params[step] = { ...params[step], [name]: Number(raw) }
+  }
+  return params
+}
+
+/** Images to process: { input, rel } with `rel` the output path relative to the output dir */
+function collectInputs(args) {
+  const items = []
+  const walk = (dir, prefix) => {
+    for (const entry of readdirSync(dir, { withFileTypes: true }).sort((a, b) => a.name.localeCompare(b.name))) {
+      if (entry.name.startsWith('.')) continue
+      const full = path.join(dir, entry.name)
+      if (entry.isDirectory()) walk(full, path.join(prefix, entry.name))
+      else if (entry.isFile() && isImageFile(entry.name)) items.push({ input: full, rel: path.join(prefix, entry.name) })
+    }
+  }
+  for (const arg of args) {
+    if (!existsSync(arg)) throw new UsageError(`Introuvable : ${arg}`)
+    if (statSync(arg).isDirectory()) walk(arg, '')
+    else if (isImageFile(arg)) items.push({ input: arg, rel: path.basename(arg) })
+    else throw new UsageError(`Format non pris en charge : ${arg}`)
+  }
+  const seen = new Map()
+  for (const item of items) {
+    const parsed = path.parse(item.rel)
+    item.rel = path.join(parsed.dir, `${parsed.name}.png`)
+    if (seen.has(item.rel)) throw new UsageError(`${seen.get(item.rel)} et ${item.input} donneraient tous deux ${item.rel}`)
+    seen.set(item.rel, item.input)
+  }
+  return items
+}
+
+/** Same input file, same pipeline, output still there */
+function isDone(entry, item, outDir, signature) {
+  if (!entry || entry.pipeline !== signature || !existsSync(path.join(outDir, item.rel))) return false
+  return sameInput(entry, statSync(item.input))
+}
+
+/** Run the pipeline on one image; each model failure goes down the step's fallback chain */
+async function processImage(item, pipeline, params, outDir, keepIntermediates) {
+  const tmpDir = mkdtempSync(path.join(os.tmpdir(), 'oldphotos-'))
+  const finalPath = path.join(outDir, item.rel)
+  const name = sanitizeFilename(path.parse(item.rel).name)
+  mkdirSync(path.dirname(finalPath), { recursive: true })
+  const report = []
+  let current = item.input
+  try {
+    for (const [k, { step, model }] of pipeline.entries()) {
+      const stepDef = STEPS[step]
+      const outputPath = keepIntermediates && k < pipeline.length - 1
+        ? path.join(path.dirname(finalPath), `${name}_${stepDef.prefix || step}.png`)
+        : path.join(tmpDir, `${k}_${step}.png`)
+      const chain = [model, ...fallbackChain(step, model)]
+      const attempts = []
+      let record = null
+      for (const [m, candidate] of chain.entries()) {
+        const { script, args } = stepDef.buildArgs({
+          inputPath: current, outputPath, job: { id: null }, selectedModel: candidate,
+          params: resolveParams(step, params[step]),
+        })
+        try {
+          record = await runPythonStep(script, args, null, getStepLimits(step))
+          break
+        } catch (err) {
+          attempts.push(`${candidate || step} : ${err.message.split('\n')[0]}`)
+          if (m === chain.length - 1) throw new Error(`${stepDef.name} — ${attempts.join(' ; ')}`)
+        }
+      }
+      report.push({ step, model: chain[attempts.length], noop: !!record.noop, ...(attempts.length ? { fallback: attempts } : {}) })
+      current = outputPath
+    }
+    copyFileSync(current, finalPath)
+    return report
+  } finally {
+    rmSync(tmpDir, { recursive: true, force: true })
+  }
+}
+
+function formatSeconds(ms) {
+  return ms >= 60000 ? `${Math.floor(ms / 60000)} min ${Math.round((ms % 60000) / 1000)} s` : `${(ms / 1000).toFixed(1)} s`
+}
+
+async function run(argv) {
+  const { values, positionals } = parseArgs({
+    args: argv,
+    allowPositionals: true,
+    options: {
+      steps: { type: 'string', short: 's' },
+      output: { type: 'string', short: 'o' },
+      jobs: { type: 'string', short: 'j', default: '1' },
+      param: { type: 'string', short: 'p', multiple: true, default: [] },
+      force: { type: 'boolean', default: false },
+      'keep-intermediates': { type: 'boolean', default: false },
+      quiet: { type: 'boolean', short: 'q', default: false },
+      verbose: { type: 'boolean', short: 'v', default: false },
+      help: { type: 'boolean', short: 'h', default: false },
+    },
+  })
+  if (values.help) {
+    console.log(USAGE)
+    return 0
+  }
+  if (!values.steps) throw new UsageError('--steps est obligatoire')
+  if (!values.output) throw new UsageError('--output est obligatoire')
+  if (!positionals.length) throw new UsageError('Aucune entrée (fichier ou dossier)')
+  const parallel = parseInt(values.jobs)
+  if (!(parallel >= 1)) throw new UsageError(`--jobs invalide : ${values.jobs}`)
+
+  const pipeline = values.steps.split(',').filter(Boolean).map(parseStepSpec)
+  const params = parseParams(values.param, pipeline.map(p => p.step))
+  const items = collectInputs(positionals)
+  if (!isAiReady()) {
+    throw new UsageError(isSetupRunning()
+      ? 'Installation IA en cours, réessayez une fois terminée'
+      : 'IA non configurée. Lancez : cd ai && bash setup.sh')
+  }
+
+  setScriptLogging(values.verbose)
+  const outDir = path.resolve(values.output)
+  mkdirSync(outDir, { recursive: true })
+  const signature = pipelineSignature(pipeline, params)
+  const manifest = readManifest(outDir)
+  const log = values.quiet ? () => {} : (msg) => console.log(msg)
+
+  const todo = values.force ? items : items.filter(item => !isDone(manifest[item.rel], item, outDir, signature))
+  const skipped = items.length - todo.length
+  const label = pipeline.map(p => p.model ? `${p.step}:${p.model}` : p.step).join(' → ')
+  log(`${items.length} image(s), ${skipped} déjà traitée(s) — ${label}`)
+
+  let next = 0
+  let done = 0
+  const failed = []
+  const worker = async () => {
+    while (next < todo.length) {
+      const item = todo[next++]
+      const start = Date.now()
+      try {
+        const report = await processImage(item, pipeline, params, outDir, values['keep-intermediates'])
+        const stat = statSync(item.input)
+        manifest[item.rel] = {
+          source: path.resolve(item.input), pipeline: signature,
+          input: { size: stat.size, mtimeMs: stat.mtimeMs },
+          steps: report, completedAt: new Date().toISOString(),
+        }
+        writeManifest(outDir, manifest)
+        done++
+        const notes = report.filter(r => r.fallback).map(r => `repli ${r.step} → ${r.model}`)
+        log(`✓ [${done + failed.length}/${todo.length}] ${item.input} → ${item.rel} (${formatSeconds(Date.now() - start)}${notes.length ? `, ${notes.join(', ')}` : ''})`)
+      } catch (err) {
+        failed.push(item)
+        console.error(`✗ [${done + failed.length}/${todo.length}] ${item.input} : ${err.message}`)
+      }
+    }
+  }
+  await Promise.all(Array.from({ length: Math.min(parallel, todo.length) }, worker))
+
+  console.log(`Terminé : ${done} traitée(s), ${skipped} sautée(s), ${failed.length} en échec`)
+  return failed.length ? 1 : 0
+}
+
+function listSteps() {
+  for (const [key, step] of Object.entries(STEPS)) {
+    if (step.manual) continue
+    console.log(`${key} — ${step.name}`)
+    for (const [model, info] of Object.entries(step.models || {})) {
+      console.log(`  ${model === step.defaultModel ? '*' : ' '} ${key}:${model} — ${info.name}`)
+    }
+    for (const [name, spec] of Object.entries(step.params || {})) {
+      console.log(`    -p ${key}.${name}=<${spec.min ?? ''}…${spec.max ?? ''}> (défaut ${spec.default}) — ${spec.name}`)
+    }
+  }
+  return 0
+}
+
+/** Entry point of bin/oldphotos; resolves with the exit code */
+export async function main(argv) {
+  const [command, ...rest] = argv
+  try {
+    if (command === 'run') return await run(rest)
+    if (command === 'steps') return listSteps()
+    console.log(USAGE)
+    return command && command !== 'help' && command !== '--help' && command !== '-h' ? 2 : 0
+  } catch (err) {
+    if (!(err instanceof UsageError) && err.code !== 'ERR_PARSE_ARGS_UNKNOWN_OPTION' && err.code !== 'ERR_PARSE_ARGS_INVALID_OPTION_VALUE') throw err
+    console.error(err.message)
+    return 2
+  }
+}
@@ -1,12 +1,15 @@
 import path from 'path'
 import { fileURLToPath } from 'url'
 import { mkdirSync, existsSync } from 'fs'
+import os from 'os'
 
 const __dirname = path.dirname(fileURLToPath(import.meta.url))
 
 export const ROOT = path.join(__dirname, '..')
 export const UPLOADS_DIR = process.env.UPLOADS_DIR || path.join(ROOT, 'uploads')
 export const RESULTS_DIR = process.env.RESULTS_DIR || path.join(ROOT, 'results')
+export const DATA_DIR = process.env.DATA_DIR || path.dirname(UPLOADS_DIR)
+export const STORE_FILE = process.env.STORE_FILE || path.join(DATA_DIR, 'store.jsonl')
 export const AI_DIR = path.join(ROOT, 'ai')
 export const DIST_DIR = path.join(ROOT, 'dist')
 export const VENV_PYTHON = path.join(AI_DIR, 'venv', 'bin', 'python')
@@ -16,10 +19,62 @@ export const SETUP_LOG_FILE = '/data/setup.log'
 export const SETUP_ERROR_FILE = '/data/setup.error'
 
 export const PORT = process.env.PORT || 3001
+// Adresse publique du serveur (https://photos.example) : URLs absolues dans les webhooks
+export const PUBLIC_URL = process.env.PUBLIC_URL || ''
+// Cookie identifiant l'espace de travail du navigateur (voir sessions.js)
+export const SESSION_COOKIE = process.env.SESSION_COOKIE || 'oldphotos_session'
 export const HEARTBEAT_TIMEOUT_MS = parseInt(process.env.HEARTBEAT_TIMEOUT_SECONDS || '10') * 1000
+// Jobs d'une session sans frontend : cancel (défaut), pause, ou none (détachés, continuent seuls)
+export const HEARTBEAT_ACTION = process.env.HEARTBEAT_ACTION || 'cancel'
 export const MAX_CONCURRENT_LIMIT = Math.max(1, parseInt(process.env.MAX_CONCURRENT_JOBS) || 2)
 export const CLEANUP_INTERVAL_MS = (parseInt(process.env.CLEANUP_INTERVAL_HOURS) || 2) * 60 * 60 * 1000
 export const CLEANUP_MAX_AGE_MS = (parseInt(process.env.CLEANUP_MAX_AGE_HOURS) || 2) * 60 * 60 * 1000
+// Jobs détachés terminés sans que leur session soit revenue les voir : gardés jusqu'à ce délai
+export const CLEANUP_DETACHED_MAX_AGE_MS = (parseInt(process.env.CLEANUP_DETACHED_MAX_AGE_HOURS) || 168) * 60 * 60 * 1000
+
+// Workers Python persistants (modèles gardés en mémoire entre deux photos)
+export const PYTHON_WORKERS = process.env.PYTHON_WORKERS !== '0'
+export const PYTHON_WORKER_IDLE_MS = (parseInt(process.env.PYTHON_WORKER_IDLE_SECONDS) || 300) * 1000
+export const PYTHON_MAX_WORKERS = Math.max(1, parseInt(process.env.PYTHON_MAX_WORKERS) || 4)
+// Albums envoyés en archive (.zip, .tar, .tar.gz) : taille de l'archive et nombre d'entrées
+export const MAX_ARCHIVE_SIZE = (parseInt(process.env.MAX_ARCHIVE_SIZE_MB) || 4096) * 1024 * 1024
+export const MAX_ARCHIVE_ENTRIES = parseInt(process.env.MAX_ARCHIVE_ENTRIES) || 2000
+
+// Authentification : mot de passe unique (utilisateur « admin ») et/ou fichier d'utilisateurs
+// « nom:scrypt$… » (npm run hash-password). Sans l'un ni l'autre, l'accès est libre.
+export const AUTH_PASSWORD = process.env.AUTH_PASSWORD || ''
+export const AUTH_USERS_FILE = process.env.AUTH_USERS_FILE || ''
+// Clé HMAC des cookies de connexion ; générée et gardée dans DATA_DIR si absente
+export const AUTH_SECRET = process.env.AUTH_SECRET || ''
+export const AUTH_SESSION_MS = (parseInt(process.env.AUTH_SESSION_DAYS) || 30) * 24 * 60 * 60 * 1000
+
+// Quota disque pour uploads + results (Go, décimales acceptées) ; 0 = pas de limite, voir quota.js
+export const STORAGE_QUOTA_BYTES = Math.round((parseFloat(process.env.STORAGE_QUOTA_GB) || 0) * 1024 ** 3)
+
+// Budget machine des étapes en parallèle (voir resources.js) : mémoire en Mo et cœurs.
+// Par défaut 75 % de la RAM et tous les cœurs ; 0 = pas de limite sur cette ressource
+export const RESOURCE_MEMORY_MB = process.env.RESOURCE_MEMORY_MB
+  ? Math.max(0, parseInt(process.env.RESOURCE_MEMORY_MB) || 0)
+  : Math.round(os.totalmem() / 1024 ** 2 * 0.75)
+export const RESOURCE_CPUS = process.env.RESOURCE_CPUS
+  ? Math.max(0, parseFloat(process.env.RESOURCE_CPUS) || 0)
+  : os.availableParallelism?.() || os.cpus().length
+
+// Modèle de repli automatique quand une étape échoue (chaînes `fallbacks` dans server/steps/*.js)
+export const STEP_FALLBACKS = process.env.STEP_FALLBACKS !== '0'
+
+// Dossier surveillé (voir watch.js) : scans déposés dans WATCH_DIR traités par le préréglage
+// WATCH_PRESET (nom ou id), résultats dans WATCH_OUTBOX ; WATCH_DIR vide = désactivé
+export const WATCH_DIR = process.env.WATCH_DIR || ''
+export const WATCH_OUTBOX = process.env.WATCH_OUTBOX || path.join(DATA_DIR, 'outbox')
+export const WATCH_PRESET = process.env.WATCH_PRESET || ''
+export const WATCH_INTERVAL_MS = (parseInt(process.env.WATCH_INTERVAL_SECONDS) || 10) * 1000
+
+// Historique des durées d'étapes (mégapixels, modèle, device) pour estimer les fins de jobs
+export const STEP_TIMINGS_FILE = process.env.STEP_TIMINGS_FILE || path.join(DATA_DIR, 'step-timings.jsonl')
+
+// Délai entre SIGTERM et SIGKILL quand un script doit être arrêté
+export const KILL_GRACE_MS = (parseInt(process.env.KILL_GRACE_SECONDS) || 5) * 1000
 
 // Ensure directories exist
 if (!existsSync(UPLOADS_DIR)) mkdirSync(UPLOADS_DIR, { recursive: true })
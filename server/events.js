@@ -0,0 +1,32 @@
+import { saveJob } from './storage.js'
+
+// Diffusion des changements d'état des jobs (flux SSE du navigateur, ...)
+
+const listeners = new Set()
+
+/** Subscribe to job events — returns the unsubscribe function */
+export function subscribe(listener) {
+  listeners.add(listener)
+  return () => listeners.delete(listener)
+}
+
+/**
+ * Persist a job and notify subscribers.
+ * Types: job_created, job_updated, step_started, step_completed, waiting_input,
+ *        job_completed, job_failed, job_cancelled, job_paused
+ */
+export function publishJob(type, job) {
+  saveJob(job)
+  emitJob(type, job)
+}
+
+/** Notify subscribers without persisting (transient updates like step_progress) */
+export function emitJob(type, job) {
+  for (const listener of listeners) {
+    try {
+      listener({ type, job })
+    } catch (err) {
+      console.error(`Event listener failed (${type}):`, err.message)
+    }
+  }
+}
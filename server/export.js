@@ -0,0 +1,133 @@
+import path from 'path'
+import { existsSync } from 'fs'
+import { photos } from './storage.js'
+import { STEPS, resolveParams } from './steps/index.js'
+import { ZipWriter } from './archive.js'
+import { getPathForUrl } from './utils.js'
+
+// Export groupé : un dossier par job (rangé sous l'album de la photo s'il y en a un)
+//   <album>/<photo>_<job>/original.jpg, final.png, steps/01_spot_removal.png
+// et un manifest.json décrivant étapes, modèles, paramètres et durées de chaque job.
+
+const MANIFEST_VERSION = 1
+
+/** Path segment safe on every OS, accents kept */
+function safeSegment(name) {
+  return name.replace(/[\\/:*?"<>|\x00-\x1f]+/g, '_').replace(/^\.+/, '_').trim().slice(0, 100) || '_'
+}
+
+function jobFolder(job, photo, used) {
+  const base = safeSegment(path.parse(job.photoName || 'photo').name)
+  const label = job.branchLabel ? ` (${safeSegment(job.branchLabel)})` : ''
+  const album = photo?.album ? photo.album.split('/').map(safeSegment).join('/') + '/' : ''
+  let folder = `${album}${base}${label}`
+  // Même photo traitée plusieurs fois : suffixe court de l'id
+  if (used.has(folder)) folder += `_${job.id.slice(0, 6)}`
+  used.add(folder)
+  return folder
+}
+
+function durationBetween(start, end) {
+  return start && end ? new Date(end) - new Date(start) : null
+}
+
+/**
+ * Result of the index-th step of the pipeline: the one recorded with that index, or for older
+ * jobs the same occurrence of that step (a pipeline may run a step twice)
+ */
+function stepResultAt(job, index) {
+  const results = (job.stepResults || []).filter(r => r.result)
+  const recorded = results.find(r => r.index === index)
+  if (recorded) return recorded
+  const step = job.steps[index]
+  const occurrence = job.steps.slice(0, index).filter(s => s === step).length
+  return results.filter(r => r.index == null && r.step === step)[occurrence] || null
+}
+
+function stepEntry(job, step, index, file) {
+  const stepDef = STEPS[step]
+  const sr = stepResultAt(job, index)
+  const model = sr?.fallback?.used || (stepDef?.models ? (job.options?.[step] || stepDef.defaultModel) : null)
+  const status = sr ? (sr.noop ? 'unchanged' : 'done')
+    : job.status === 'completed' ? 'skipped'
+    : job.failedStep === step ? 'failed'
+    : 'pending'
+  return {
+    index: index + 1,
+    step,
+    name: stepDef?.name || step,
+    model,
+    modelName: model ? stepDef.models[model]?.name || model : stepDef?.model || null,
+    ...(stepDef?.params ? { params: resolveParams(step, job.params?.[step]) } : {}),
+    status,
+    startedAt: sr?.startedAt || null,
+    durationMs: sr?.durationMs ?? null,
+    ...(sr?.reason ? { reason: sr.reason } : {}),
+    ...(sr?.metrics ? { metrics: sr.metrics } : {}),
+    ...(sr?.warnings?.length ? { warnings: sr.warnings } : {}),
+    ...(sr?.fallback ? { fallback: sr.fallback } : {}),
+    ...(sr?.comparison ? { comparedModels: sr.comparison.map(c => c.model), chosen: sr.chosen } : {}),
+    file,
+  }
+}
+
+/**
+ * Stream a ZIP of the given jobs to `out`: originals, final results, optionally every
+ * intermediate step image, and manifest.json last. Files gone from disk (cleanup) are
+ * listed in the manifest with `file: null`.
+ */
+export async function writeExport(jobList, out, { intermediates = false, original = true } = {}) {
+  const zip = new ZipWriter(out)
+  const used = new Set()
+  const manifest = { version: MANIFEST_VERSION, exportedAt: new Date().toISOString(), jobs: [] }
+
+  const add = async (name, url) => {
+    const file = getPathForUrl(url)
+    if (!file || !existsSync(file)) return null
+    await zip.addFile(name, file)
+    return name
+  }
+
+  for (const job of jobList) {
+    const photo = photos.get(job.photoId)
+    const folder = jobFolder(job, photo, used)
+
+    const originalFile = original
+      ? await add(`${folder}/original${path.extname(job.original || '')}`, job.original)
+      : null
+
+    const steps = []
+    for (const [i, step] of job.steps.entries()) {
+      const sr = stepResultAt(job, i)
+      const file = intermediates && sr
+        ? await add(`${folder}/steps/${String(i + 1).padStart(2, '0')}_${step}${path.extname(sr.result)}`, sr.result)
+        : null
+      steps.push(stepEntry(job, step, i, file))
+    }
+
+    const resultFile = job.result ? await add(`${folder}/final${path.extname(job.result)}`, job.result) : null
+
+    manifest.jobs.push({
+      id: job.id,
+      status: job.status,
+      source: {
+        filename: job.photoName,
+        album: photo?.album || null,
+        file: originalFile,
+      },
+      ...(job.branchLabel ? { variant: { label: job.branchLabel, parentJobId: job.parentJobId } } : {}),
+      ...(job.presetId ? { presetId: job.presetId } : {}),
+      createdAt: job.createdAt,
+      startedAt: job.startedAt || null,
+      completedAt: job.completedAt || null,
+      durationMs: durationBetween(job.startedAt, job.completedAt),
+      steps,
+      result: resultFile,
+      ...(job.error ? { error: job.error } : {}),
+    })
+  }
+
+  await zip.addBuffer('manifest.json', Buffer.from(JSON.stringify(manifest, null, 2)))
+  await zip.finish()
+  return manifest
+}
@@ -0,0 +1,35 @@
+import { test } from 'node:test'
+import assert from 'node:assert/strict'
+import { mkdirSync, mkdtempSync, writeFileSync } from 'fs'
+import { Writable } from 'stream'
+import os from 'os'
+import path from 'path'
+
+// Sans dossiers du dépôt : avant le premier import de config.js
+const dir = mkdtempSync(path.join(os.tmpdir(), 'oldphotos-test-'))
+process.env.UPLOADS_DIR = path.join(dir, 'uploads')
+process.env.RESULTS_DIR = path.join(dir, 'results')
+
+const { writeExport } = await import('./export.js')
+
+test('a step run twice exports each run under its own index', async () => {
+  mkdirSync(process.env.RESULTS_DIR, { recursive: true })
+  for (const name of ['first.png', 'second.png']) writeFileSync(path.join(process.env.RESULTS_DIR, name), name)
+  const job = {
+    id: 'job-1', photoName: 'scan.png', status: 'completed', steps: ['upscale', 'upscale'],
+    stepResults: [
+      { step: 'upscale', index: 0, result: '/results/first.png', durationMs: 10 },
+      { step: 'upscale', index: 1, result: '/results/second.png', durationMs: 20 },
+    ],
+    result: '/results/second.png',
+  }
+  // Job enregistré avant l'index des étapes : même occurrence de l'étape
+  const legacy = { ...job, id: 'job-2', stepResults: job.stepResults.map(({ index, ...sr }) => sr) }
+  const sink = new Writable({ write(_chunk, _enc, done) { done() } })
+
+  const manifest = await writeExport([job, legacy], sink, { intermediates: true, original: false })
+  for (const exported of manifest.jobs) {
+    assert.deepEqual(exported.steps.map(s => s.durationMs), [10, 20], exported.id)
+    assert.match(exported.steps[1].file, /02_upscale\.png$/)
+  }
+})
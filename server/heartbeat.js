@@ -1,28 +1,126 @@
-import { HEARTBEAT_TIMEOUT_MS } from './config.js'
-import { jobs, runningProcs } from './storage.js'
+import { HEARTBEAT_TIMEOUT_MS, HEARTBEAT_ACTION, CLEANUP_MAX_AGE_MS } from './config.js'
+import { jobs, settings, runningProcs } from './storage.js'
+import { publishJob } from './events.js'
+import { killProc } from './python.js'
+import { pauseJob, processNext } from './queue.js'
 
-export let lastHeartbeat = Date.now()
+export const HEARTBEAT_ACTIONS = ['cancel', 'pause', 'none']
 
-export function touchHeartbeat() {
-  lastHeartbeat = Date.now()
+const startedAt = Date.now()
+
+// Présence par session : flux SSE ouverts et dernier signe de vie (requête API authentifiée,
+// voir sessionHeartbeat, ou fermeture de flux). Tant qu'un flux est ouvert, le frontend de
+// cette session est considéré comme présent.
+const presence = new Map()  // sessionId → { openConnections, lastHeartbeat }
+// Au-delà, une session sans flux ouvert est oubliée : absente pour le heartbeat, et trop
+// ancienne pour que le nettoyage (lastSeen) en tienne compte
+const PRESENCE_TTL_MS = Math.max(HEARTBEAT_TIMEOUT_MS, CLEANUP_MAX_AGE_MS)
+
+function presenceOf(sessionId) {
+  let state = presence.get(sessionId)
+  if (!state) {
+    state = { openConnections: 0, lastHeartbeat: Date.now() }
+    presence.set(sessionId, state)
+  }
+  return state
+}
+
+export function touchHeartbeat(sessionId) {
+  presenceOf(sessionId).lastHeartbeat = Date.now()
+}
+
+export function connectionOpened(sessionId) {
+  presenceOf(sessionId).openConnections++
+  touchHeartbeat(sessionId)
+}
+
+export function connectionClosed(sessionId) {
+  const state = presenceOf(sessionId)
+  state.openConnections = Math.max(0, state.openConnections - 1)
+  // Le délai de grâce court à partir de la fermeture (rechargement de page, etc.)
+  touchHeartbeat(sessionId)
+}
+
+/** Last sign of life of a session since startup (ms timestamp), null if never seen */
+export function lastSeen(sessionId) {
+  return presence.get(sessionId)?.lastHeartbeat ?? null
+}
+
+/** Drop the presence state of an expired session */
+export function forgetSession(sessionId) {
+  if (!presence.get(sessionId)?.openConnections) presence.delete(sessionId)
+}
+
+/**
+ * What happens to the active jobs of a session whose frontend is gone:
+ * 'cancel', 'pause' (after the current step) or 'none' (jobs keep running, detached).
+ * Settings (PUT /api/settings { heartbeatAction }) override HEARTBEAT_ACTION.
+ */
+export function getHeartbeatAction() {
+  const action = settings.get('heartbeatAction') ?? HEARTBEAT_ACTION
+  return HEARTBEAT_ACTIONS.includes(action) ? action : 'cancel'
+}
+
+/** null resets to the env default */
+export function setHeartbeatAction(action) {
+  if (action === null) settings.delete('heartbeatAction')
+  else if (HEARTBEAT_ACTIONS.includes(action)) settings.set('heartbeatAction', action)
+}
+
+function isPresent(state, now) {
+  return !!state && (state.openConnections > 0 || now - state.lastHeartbeat < HEARTBEAT_TIMEOUT_MS)
+}
+
+function prunePresence(now) {
+  for (const [sessionId, state] of presence) {
+    if (!state.openConnections && now - state.lastHeartbeat > PRESENCE_TTL_MS) presence.delete(sessionId)
+  }
 }
 
 function checkHeartbeat() {
-  if (Date.now() - lastHeartbeat < HEARTBEAT_TIMEOUT_MS) return
-
-  const active = [...jobs.values()].filter(j => j.status === 'processing' || j.status === 'pending')
-  if (active.length === 0) return
-
-  console.log(`Heartbeat timeout — annulation de ${active.length} job(s) actif(s)`)
-  for (const job of active) {
-    job.status = 'cancelled'
-    job.currentStep = null
-    job.waitingStep = null
-    job.waitingImage = null
-    const proc = runningProcs.get(job.id)
-    if (proc) {
-      proc.kill('SIGTERM')
-      runningProcs.delete(job.id)
+  const now = Date.now()
+  prunePresence(now)
+  // Après un redémarrage, laisser aux navigateurs le temps de se reconnecter
+  if (now - startedAt < HEARTBEAT_TIMEOUT_MS) return
+  const action = getHeartbeatAction()
+  if (action === 'none') return
+
+  const bySession = new Map()
+  for (const job of jobs.values()) {
+    if (job.status !== 'processing' && job.status !== 'pending') continue
+    // Jobs détachés : continuent sans frontend (lots de nuit)
+    if (job.detached || job.pauseRequested) continue
+    const key = job.sessionId || ''
+    if (!bySession.has(key)) bySession.set(key, [])
+    bySession.get(key).push(job)
+  }
+
+  for (const [sessionId, active] of bySession) {
+    // Jobs d'avant les sessions : gardés tant qu'un frontend, quel qu'il soit, est présent
+    const present = sessionId
+      ? isPresent(presence.get(sessionId), now)
+      : [...presence.values()].some(state => isPresent(state, now))
+    if (present) continue
+
+    if (action === 'pause') {
+      console.log(`Heartbeat timeout (session ${sessionId.slice(0, 8) || '-'}) — mise en pause de ${active.length} job(s) actif(s)`)
+      for (const job of active) pauseJob(job)
+      processNext()
+      continue
+    }
+
+    console.log(`Heartbeat timeout (session ${sessionId.slice(0, 8) || '-'}) — annulation de ${active.length} job(s) actif(s)`)
+    for (const job of active) {
+      job.status = 'cancelled'
+      job.currentStep = null
+      job.waitingStep = null
+      job.waitingImage = null
+      const proc = runningProcs.get(job.id)
+      if (proc) {
+        killProc(proc)
+        runningProcs.delete(job.id)
+      }
+      publishJob('job_cancelled', job)
     }
   }
 }
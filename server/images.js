@@ -0,0 +1,10 @@
+import path from 'path'
+
+// Formats d'image acceptés, sans dépendance : partagé par l'upload, le dossier surveillé et la CLI
+
+export const IMAGE_EXTENSIONS = ['.jpg', '.jpeg', '.png', '.webp', '.tiff', '.bmp']
+export const MAX_IMAGE_SIZE = 50 * 1024 * 1024
+
+export function isImageFile(filename) {
+  return IMAGE_EXTENSIONS.includes(path.extname(filename).toLowerCase())
+}
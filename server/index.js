@@ -4,33 +4,65 @@ dotenv.config({ path: ['.env.local', '.env'] })
 import express from 'express'
 import { PORT, UPLOADS_DIR, RESULTS_DIR, DIST_DIR } from './config.js'
 import { isAiReady, isSetupRunning } from './python.js'
+import { loadStore } from './storage.js'
+import { loadTimings } from './timings.js'
+import { recoverInterruptedJobs } from './queue.js'
 import { startHeartbeatTimer } from './heartbeat.js'
 import { startCleanupTimer } from './cleanup.js'
+import { sessionMiddleware, sessionHeartbeat } from './sessions.js'
+import { requireAuth, authMode } from './auth.js'
+import { trackAccess, startQuotaWatcher } from './quota.js'
+import { startWatchFolder } from './watch.js'
+import { startWebhooks } from './webhooks.js'
+import { startMetrics } from './metrics.js'
 
 import photosRouter from './routes/photos.js'
 import jobsRouter from './routes/jobs.js'
 import settingsRouter from './routes/settings.js'
 import statusRouter from './routes/status.js'
+import eventsRouter from './routes/events.js'
+import presetsRouter from './routes/presets.js'
+import authRouter from './routes/auth.js'
+import cleanupRouter from './routes/cleanup.js'
+import metricsRouter from './routes/metrics.js'
 
 const app = express()
 app.use(express.json())
+app.use(sessionMiddleware)
 
-// Static files
+// Frontend public (écran de connexion) ; photos, résultats et API derrière l'authentification
 app.use(express.static(DIST_DIR))
-app.use('/uploads', express.static(UPLOADS_DIR))
-app.use('/results', express.static(RESULTS_DIR))
+app.use('/api/auth', authRouter)
+app.use(requireAuth)
+app.use('/api', sessionHeartbeat)
+app.use('/uploads', trackAccess(UPLOADS_DIR), express.static(UPLOADS_DIR))
+app.use('/results', trackAccess(RESULTS_DIR), express.static(RESULTS_DIR))
 
 // API routes
 app.use('/api/photos', photosRouter)
 app.use('/api/jobs', jobsRouter)
 app.use('/api/settings', settingsRouter)
+app.use('/api/events', eventsRouter)
+app.use('/api/presets', presetsRouter)
+app.use('/api/cleanup', cleanupRouter)
 app.use('/api', statusRouter)
+app.use('/metrics', metricsRouter)
+
+// Restore photos/jobs from the journal
+loadStore()
+loadTimings()
+recoverInterruptedJobs()
 
 // Timers
 startHeartbeatTimer()
 startCleanupTimer()
+startQuotaWatcher()
+startWebhooks()
+startMetrics()
+startWatchFolder()
 
 app.listen(PORT, () => {
   console.log(`Server running on http://localhost:${PORT}`)
+  console.log(`Auth: ${authMode() || 'disabled — set AUTH_PASSWORD or AUTH_USERS_FILE before exposing this port'}`)
   console.log(`AI ready: ${isAiReady() ? 'YES' : 'NO — setup ' + (isSetupRunning() ? 'in progress...' : 'not started')}`)
 })
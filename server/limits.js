@@ -0,0 +1,54 @@
+import { STEPS } from './steps/index.js'
+import { settings } from './storage.js'
+
+const DEFAULT_TIMEOUT_SECONDS = 5 * 60
+
+function envInt(name) {
+  const value = parseInt(process.env[name])
+  return Number.isFinite(value) && value >= 0 ? value : undefined
+}
+
+/**
+ * Effective limits of a step, first defined wins:
+ *   1. settings (PUT /api/settings { stepLimits: { upscale: { timeoutSeconds, memoryLimitMb } } })
+ *   2. env: STEP_TIMEOUT_UPSCALE=3600, STEP_MEMORY_LIMIT_MB_UPSCALE=12000
+ *   3. timeoutSeconds / memoryLimitMb of the step definition
+ * 0 disables the corresponding limit.
+ */
+export function getStepLimits(stepId) {
+  const def = STEPS[stepId] || {}
+  const key = stepId.toUpperCase()
+  const override = settings.get('stepLimits')?.[stepId] || {}
+  return {
+    timeoutSeconds: override.timeoutSeconds
+      ?? envInt(`STEP_TIMEOUT_${key}`)
+      ?? def.timeoutSeconds
+      ?? DEFAULT_TIMEOUT_SECONDS,
+    memoryLimitMb: override.memoryLimitMb
+      ?? envInt(`STEP_MEMORY_LIMIT_MB_${key}`)
+      ?? def.memoryLimitMb
+      ?? 0,
+  }
+}
+
+export function getAllStepLimits() {
+  return Object.fromEntries(Object.keys(STEPS).map(id => [id, getStepLimits(id)]))
+}
+
+/** Merge overrides into the stored settings; null resets a value to env/default */
+export function setStepLimits(patch) {
+  const current = { ...(settings.get('stepLimits') || {}) }
+  for (const [stepId, limits] of Object.entries(patch || {})) {
+    if (!STEPS[stepId] || typeof limits !== 'object' || !limits) continue
+    const next = { ...current[stepId] }
+    for (const field of ['timeoutSeconds', 'memoryLimitMb']) {
+      if (!(field in limits)) continue
+      const value = limits[field]
+      if (value === null) delete next[field]
+      else if (typeof value === 'number' && value >= 0) next[field] = Math.round(value)
+    }
+    if (Object.keys(next).length) current[stepId] = next
+    else delete current[stepId]
+  }
+  settings.set('stepLimits', current)
+}
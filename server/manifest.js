@@ -0,0 +1,32 @@
+import { readFileSync, writeFileSync, renameSync } from 'fs'
+import path from 'path'
+
+// Manifeste d'un dossier de sortie (ligne de commande, dossier surveillé) : chemin de sortie
+// relatif → { source, pipeline, input: { size, mtimeMs }, steps, completedAt }. Une entrée
+// dont l'entrée n'a pas changé évite de retraiter la photo.
+
+export const MANIFEST_FILE = '.oldphotos.json'
+
+export function readManifest(outDir) {
+  try {
+    return JSON.parse(readFileSync(path.join(outDir, MANIFEST_FILE), 'utf8'))
+  } catch {
+    return {}
+  }
+}
+
+export function writeManifest(outDir, manifest) {
+  const file = path.join(outDir, MANIFEST_FILE)
+  writeFileSync(`${file}.tmp`, JSON.stringify(manifest, null, 2))
+  renameSync(`${file}.tmp`, file)
+}
+
+/** Comparable description of a pipeline: [{ step, model }] and per-step params */
+export function pipelineSignature(pipeline, params) {
+  return JSON.stringify({ pipeline, params })
+}
+
+/** The manifest entry was made from this very input file (same size and mtime) */
+export function sameInput(entry, stat) {
+  return entry?.input?.size === stat.size && entry?.input?.mtimeMs === stat.mtimeMs
+}
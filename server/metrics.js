@@ -0,0 +1,108 @@
+import { jobs } from './storage.js'
+import { subscribe } from './events.js'
+import { storageUsage } from './quota.js'
+import { isAiReady, isSetupRunning, getSetupError, pythonProcesses } from './python.js'
+
+// Métriques Prometheus (GET /metrics) : compteurs et histogrammes alimentés par la file
+// (recordStep / recordStepFailure dans queue.js) et les événements des jobs, jauges
+// calculées au moment de la lecture. En mémoire seulement, remis à zéro au redémarrage.
+
+const JOB_STATUSES = ['pending', 'processing', 'waiting_input', 'paused', 'completed', 'failed', 'cancelled']
+// Secondes : de l'inpainting rapide à l'upscale x4 sur CPU
+const DURATION_BUCKETS = [1, 2, 5, 10, 30, 60, 120, 300, 600, 1800, 3600]
+
+const stepDurations = new Map()  // "step\0model" → { step, model, buckets, sum, count }
+const stepFailures = new Map()   // "step\0model" → { step, model, count }
+const jobsFinished = { completed: 0, failed: 0, cancelled: 0 }
+// Dernier statut compté par job : une annulation peut être publiée deux fois
+const countedStatus = new WeakMap()
+
+function entry(map, step, model, init) {
+  const key = `${step}\0${model || ''}`
+  if (!map.has(key)) map.set(key, { step, model: model || '', ...init() })
+  return map.get(key)
+}
+
+/** One successful run of a step's script with `model` (null for steps without models) */
+export function recordStep(step, model, durationMs) {
+  const h = entry(stepDurations, step, model, () => ({ buckets: DURATION_BUCKETS.map(() => 0), sum: 0, count: 0 }))
+  const seconds = durationMs / 1000
+  DURATION_BUCKETS.forEach((le, i) => { if (seconds <= le) h.buckets[i]++ })
+  h.sum += seconds
+  h.count++
+}
+
+/** One failed run (a fallback attempt counts too; cancellations and pauses do not) */
+export function recordStepFailure(step, model) {
+  entry(stepFailures, step, model, () => ({ count: 0 })).count++
+}
+
+// --- Exposition au format texte Prometheus ---
+
+function escapeLabel(value) {
+  return String(value).replace(/\\/g, '\\\\').replace(/\n/g, '\\n').replace(/"/g, '\\"')
+}
+
+function labels(obj) {
+  const parts = Object.entries(obj).map(([k, v]) => `${k}="${escapeLabel(v)}"`)
+  return parts.length ? `{${parts.join(',')}}` : ''
+}
+
+function metric(lines, name, type, help, samples) {
+  lines.push(`# HELP ${name} ${help}`, `# TYPE ${name} ${type}`)
+  for (const [labelSet, value, suffix = ''] of samples) lines.push(`${name}${suffix}${labels(labelSet)} ${value}`)
+}
+
+/** All metrics, in the Prometheus text exposition format */
+export function renderMetrics() {
+  const lines = []
+
+  const byStatus = Object.fromEntries(JOB_STATUSES.map(s => [s, 0]))
+  for (const job of jobs.values()) byStatus[job.status] = (byStatus[job.status] || 0) + 1
+  metric(lines, 'oldphotos_jobs', 'gauge', 'Jobs by status (queue depth: pending)',
+    Object.entries(byStatus).map(([status, n]) => [{ status }, n]))
+
+  metric(lines, 'oldphotos_jobs_finished_total', 'counter', 'Jobs finished since startup, by final status',
+    Object.entries(jobsFinished).map(([status, n]) => [{ status }, n]))
+
+  const procs = pythonProcesses()
+  metric(lines, 'oldphotos_python_processes', 'gauge', 'Python processes alive (one-shot scripts and persistent workers)', [
+    [{ mode: 'oneshot', state: 'busy' }, procs.oneShot],
+    [{ mode: 'worker', state: 'busy' }, procs.workersBusy],
+    [{ mode: 'worker', state: 'idle' }, procs.workersIdle],
+  ])
+
+  const histograms = [...stepDurations.values()]
+  metric(lines, 'oldphotos_step_duration_seconds', 'histogram', 'Duration of successful step runs, by step and model',
+    histograms.flatMap(h => [
+      ...DURATION_BUCKETS.map((le, i) => [{ step: h.step, model: h.model, le }, h.buckets[i], '_bucket']),
+      [{ step: h.step, model: h.model, le: '+Inf' }, h.count, '_bucket'],
+      [{ step: h.step, model: h.model }, h.sum, '_sum'],
+      [{ step: h.step, model: h.model }, h.count, '_count'],
+    ]))
+
+  metric(lines, 'oldphotos_step_failures_total', 'counter', 'Failed step runs, by step and model',
+    [...stepFailures.values()].map(f => [{ step: f.step, model: f.model }, f.count]))
+
+  const storage = storageUsage()
+  metric(lines, 'oldphotos_storage_bytes', 'gauge', 'Bytes on disk, by directory', [
+    [{ dir: 'uploads' }, storage.uploads],
+    [{ dir: 'results' }, storage.results],
+  ])
+  if (storage.quota) metric(lines, 'oldphotos_storage_quota_bytes', 'gauge', 'Disk quota for uploads + results', [[{}, storage.quota]])
+
+  metric(lines, 'oldphotos_ai_ready', 'gauge', '1 when the Python environment and models are installed', [[{}, isAiReady() ? 1 : 0]])
+  metric(lines, 'oldphotos_ai_setup_running', 'gauge', '1 while ai/setup.sh is running', [[{}, isSetupRunning() ? 1 : 0]])
+  metric(lines, 'oldphotos_ai_setup_failed', 'gauge', '1 if the last AI setup failed', [[{}, getSetupError() ? 1 : 0]])
+
+  return lines.join('\n') + '\n'
+}
+
+export function startMetrics() {
+  subscribe(({ type, job }) => {
+    if (type !== 'job_completed' && type !== 'job_failed' && type !== 'job_cancelled') return
+    if (countedStatus.get(job) === job.status || !(job.status in jobsFinished)) return
+    countedStatus.set(job, job.status)
+    jobsFinished[job.status]++
+  })
+}
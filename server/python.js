@@ -1,7 +1,10 @@
 import { existsSync, readFileSync } from 'fs'
-import { execFile } from 'child_process'
+import { spawn, execFile } from 'child_process'
 import path from 'path'
-import { AI_DIR, VENV_PYTHON, SETUP_PID_FILE, SETUP_LOG_FILE, SETUP_ERROR_FILE } from './config.js'
+import {
+  AI_DIR, VENV_PYTHON, SETUP_PID_FILE, SETUP_LOG_FILE, SETUP_ERROR_FILE,
+  PYTHON_WORKERS, PYTHON_WORKER_IDLE_MS, PYTHON_MAX_WORKERS, KILL_GRACE_MS,
+} from './config.js'
 import { runningProcs } from './storage.js'
 
 export function getPython() {
@@ -12,6 +15,23 @@ export function isAiReady() {
   return existsSync(VENV_PYTHON)
 }
 
+let device = null
+
+/** Torch device ('cuda', 'mps' or 'cpu'); detected once the AI is installed, then cached */
+export function getDevice() {
+  if (device) return Promise.resolve(device)
+  if (!isAiReady()) return Promise.resolve('cpu')
+  return new Promise((resolve) => {
+    execFile(getPython(), ['-c',
+      'import torch; print("cuda" if torch.cuda.is_available() else "mps" if torch.backends.mps.is_available() else "cpu")'
+    ], { timeout: 10000 }, (err, stdout) => {
+      if (err) return resolve('cpu')
+      device = stdout.toString().trim() || 'cpu'
+      resolve(device)
+    })
+  })
+}
+
 export function isSetupRunning() {
   if (!existsSync(SETUP_PID_FILE)) return false
   try {
@@ -45,22 +65,311 @@ export function getSetupLog() {
   }
 }
 
-export function runPythonStep(script, args, jobId) {
+const MAX_OUTPUT = 10 * 1024 * 1024
+// Traces [OK] / [FAIL] / [worker] de chaque script ; la ligne de commande les coupe hors --verbose
+let scriptLogs = true
+
+export function setScriptLogging(enabled) {
+  scriptLogs = enabled
+}
+const MEMORY_POLL_MS = 2000
+
+/**
+ * Run an ai/ script. stdout is read line by line (see ai/protocol.py): progress lines
+ * are passed to `onProgress`, and the promise resolves with the final `@result` record
+ * { output, noop, reason, metrics, warnings, model }.
+ *
+ * With PYTHON_WORKERS on, the script runs inside a long-lived ai/worker.py process
+ * that keeps torch and the weights loaded between photos; otherwise one process per call.
+ *
+ * `timeoutSeconds` / `memoryLimitMb` (RSS, 0 = no limit) come from getStepLimits().
+ */
+export function runPythonStep(script, args, jobId, { onProgress, timeoutSeconds = 5 * 60, memoryLimitMb = 0 } = {}) {
+  const limits = { timeoutSeconds, memoryLimitMb }
+  return PYTHON_WORKERS
+    ? runInWorker(script, args, jobId, onProgress, limits)
+    : runOneShot(script, args, jobId, onProgress, limits)
+}
+
+/** SIGTERM, then SIGKILL if the process is still there after KILL_GRACE_MS */
+export function killProc(proc) {
+  if (proc.exitCode !== null || proc.signalCode !== null) return
+  proc.kill('SIGTERM')
+  const timer = setTimeout(() => {
+    if (proc.exitCode === null && proc.signalCode === null) proc.kill('SIGKILL')
+  }, KILL_GRACE_MS)
+  timer.unref()
+  proc.once('exit', () => clearTimeout(timer))
+}
+
+function readRssMb(pid) {
+  try {
+    const match = readFileSync(`/proc/${pid}/status`, 'utf8').match(/^VmRSS:\s+(\d+) kB/m)
+    return match ? Math.round(parseInt(match[1]) / 1024) : null
+  } catch {
+    return null // pas de /proc (macOS) : pas de plafond mémoire
+  }
+}
+
+function formatDuration(seconds) {
+  return seconds % 60 === 0 ? `${seconds / 60} min` : `${seconds} s`
+}
+
+/**
+ * Enforce the limits on a running script. `onExceeded(message)` is called once,
+ * the caller kills the process. Returns a function stopping the watch.
+ */
+function watchLimits(proc, script, { timeoutSeconds, memoryLimitMb }, onExceeded) {
+  let timer = null
+  let poll = null
+  const stop = () => {
+    clearTimeout(timer)
+    clearInterval(poll)
+  }
+  const exceeded = (message) => {
+    stop()
+    onExceeded(message)
+  }
+  if (timeoutSeconds > 0) {
+    timer = setTimeout(() => {
+      exceeded(`Timeout : ${script} a dépassé ${formatDuration(timeoutSeconds)}`)
+    }, timeoutSeconds * 1000)
+  }
+  if (memoryLimitMb > 0) {
+    poll = setInterval(() => {
+      const rss = readRssMb(proc.pid)
+      if (rss !== null && rss > memoryLimitMb) {
+        exceeded(`Memory limit : ${script} a dépassé ${memoryLimitMb} Mo (${rss} Mo utilisés)`)
+      }
+    }, MEMORY_POLL_MS)
+  }
+  return stop
+}
+
+/** Parses the protocol lines of one script run */
+class StepOutput {
+  constructor(onProgress) {
+    this.onProgress = onProgress
+    this.record = null
+    this.stdout = ''
+  }
+
+  line(line) {
+    if (line.startsWith('@progress ')) {
+      try {
+        const { current, total } = JSON.parse(line.slice('@progress '.length))
+        if (total > 0) this.onProgress?.({ fraction: Math.min(1, current / total), current, total })
+      } catch {}
+    } else if (line.startsWith('@phase ')) {
+      this.onProgress?.({ phase: line.slice('@phase '.length).trim() })
+    } else if (line.startsWith('@result ')) {
+      try { this.record = JSON.parse(line.slice('@result '.length)) } catch {}
+    } else {
+      this.stdout = (this.stdout + line + '\n').slice(-MAX_OUTPUT)
+    }
+  }
+
+  finish(script) {
+    // Scripts sans @result : ancienne convention « OK <path> »
+    let record = this.record
+    if (!record) {
+      const ok = this.stdout.match(/^OK (.+)$/m)
+      record = { output: ok ? ok[1].trim() : null, noop: false, reason: null, metrics: {}, warnings: [], model: null }
+    }
+    if (scriptLogs) console.log(`  [OK] ${script}:`, record.output, record.noop ? `(inchangé : ${record.reason})` : '')
+    return record
+  }
+}
+
+function failure(script, message) {
+  if (scriptLogs) console.error(`  [FAIL] ${script}:`, message)
+  return new Error(message)
+}
+
+function onLines(stream, fn) {
+  let pending = ''
+  stream.setEncoding('utf8')
+  stream.on('data', (chunk) => {
+    pending += chunk
+    const lines = pending.split('\n')
+    pending = lines.pop()
+    for (const line of lines) fn(line)
+  })
+  stream.on('end', () => {
+    if (pending) fn(pending)
+    pending = ''
+  })
+}
+
+let oneShotRuns = 0
+
+function runOneShot(script, args, jobId, onProgress, limits) {
   const scriptPath = path.join(AI_DIR, script)
   return new Promise((resolve, reject) => {
-    const proc = execFile(getPython(), [scriptPath, ...args], {
-      timeout: 5 * 60 * 1000, // 5 min max per step
-      maxBuffer: 10 * 1024 * 1024,
-    }, (err, stdout, stderr) => {
+    const proc = spawn(getPython(), [scriptPath, ...args])
+    // Compté jusqu'à la sortie du process (ou l'échec du lancement)
+    let alive = true
+    oneShotRuns++
+    const exited = () => {
+      if (alive) oneShotRuns--
+      alive = false
+    }
+    proc.once('exit', exited)
+    proc.once('error', exited)
+    const output = new StepOutput(onProgress)
+    let stderr = ''
+    let limitError = null
+
+    const stopWatch = watchLimits(proc, script, limits, (message) => {
+      limitError = message
+      killProc(proc)
+    })
+
+    onLines(proc.stdout, line => output.line(line))
+    proc.stderr.setEncoding('utf8')
+    proc.stderr.on('data', (chunk) => {
+      stderr = (stderr + chunk).slice(-MAX_OUTPUT)
+    })
+
+    proc.on('error', (err) => {
+      stopWatch()
       runningProcs.delete(jobId)
-      if (err) {
-        console.error(`  [FAIL] ${script}:`, stderr || err.message)
-        reject(new Error(stderr || err.message))
-      } else {
-        console.log(`  [OK] ${script}:`, stdout.trim())
-        resolve(stdout.trim())
+      reject(err)
+    })
+    proc.on('close', (code, signal) => {
+      stopWatch()
+      runningProcs.delete(jobId)
+      if (code === 0 && !limitError) {
+        resolve(output.finish(script))
+        return
       }
+      reject(failure(script, limitError || stderr.trim() || `${script} exited with ${signal || `code ${code}`}`))
     })
     if (jobId) runningProcs.set(jobId, proc)
   })
 }
+
+// ---- Workers persistants (ai/worker.py) ----
+// Un worker ne sert qu'un script (donc qu'une famille de modèles) et une requête à la fois.
+// Il est tué après PYTHON_WORKER_IDLE_MS d'inactivité, à l'annulation du job (le kill vise
+// runningProcs comme en mode one-shot) et après une erreur, pour repartir d'un état propre.
+
+const workers = new Set()
+
+function spawnWorker(script) {
+  const proc = spawn(getPython(), [path.join(AI_DIR, 'worker.py')])
+  const worker = { script, proc, busy: false, idleTimer: null, lastUsed: Date.now(), task: null }
+
+  onLines(proc.stdout, line => worker.task?.line(line))
+  proc.stderr.setEncoding('utf8')
+  proc.stderr.on('data', chunk => worker.task?.stderr(chunk))
+  proc.stdin.on('error', () => {}) // EPIPE si le worker meurt avant de lire la requête
+  proc.on('error', (err) => {
+    workers.delete(worker)
+    worker.task?.fail(err)
+  })
+  proc.on('close', (code, signal) => {
+    workers.delete(worker)
+    clearTimeout(worker.idleTimer)
+    worker.task?.exit(code, signal)
+  })
+
+  workers.add(worker)
+  if (scriptLogs) console.log(`  [worker] ${script} démarré (pid ${proc.pid})`)
+  return worker
+}
+
+function acquireWorker(script) {
+  for (const worker of workers) {
+    if (worker.script === script && !worker.busy) {
+      clearTimeout(worker.idleTimer)
+      worker.busy = true
+      return worker
+    }
+  }
+  // Pool plein : on libère le worker inactif le plus ancien (ses modèles avec)
+  if (workers.size >= PYTHON_MAX_WORKERS) {
+    const idle = [...workers].filter(w => !w.busy).sort((a, b) => a.lastUsed - b.lastUsed)
+    if (idle.length) stopWorker(idle[0])
+  }
+  const worker = spawnWorker(script)
+  worker.busy = true
+  return worker
+}
+
+function releaseWorker(worker) {
+  worker.busy = false
+  worker.task = null
+  worker.lastUsed = Date.now()
+  worker.idleTimer = setTimeout(() => stopWorker(worker), PYTHON_WORKER_IDLE_MS)
+  worker.idleTimer.unref()
+}
+
+function stopWorker(worker) {
+  workers.delete(worker)
+  clearTimeout(worker.idleTimer)
+  killProc(worker.proc)
+}
+
+function runInWorker(script, args, jobId, onProgress, limits) {
+  return new Promise((resolve, reject) => {
+    const worker = acquireWorker(script)
+    const output = new StepOutput(onProgress)
+    let stderr = ''
+    let limitError = null
+    let scriptError = null
+
+    const stopWatch = watchLimits(worker.proc, script, limits, (message) => {
+      limitError = message
+      stopWorker(worker)
+    })
+
+    const done = () => {
+      stopWatch()
+      runningProcs.delete(jobId)
+    }
+
+    worker.task = {
+      line(line) {
+        if (line === '@done') {
+          done()
+          releaseWorker(worker)
+          resolve(output.finish(script))
+        } else if (line.startsWith('@error')) {
+          // On attend la fin du process pour avoir tout le stderr (traceback)
+          scriptError = line.slice('@error'.length).trim() || 'erreur'
+          stopWorker(worker)
+        } else {
+          output.line(line)
+        }
+      },
+      stderr(chunk) {
+        stderr = (stderr + chunk).slice(-MAX_OUTPUT)
+      },
+      fail(err) {
+        done()
+        reject(err)
+      },
+      exit(code, signal) {
+        done()
+        reject(failure(script, limitError
+          || stderr.trim()
+          || (scriptError ? `${script} : ${scriptError}` : `worker ${script} exited with ${signal || `code ${code}`}`)))
+      },
+    }
+
+    worker.proc.stdin.write(JSON.stringify({ script, args }) + '\n')
+    if (jobId) runningProcs.set(jobId, worker.proc)
+  })
+}
+
+/** Python processes alive right now, for /metrics */
+export function pythonProcesses() {
+  const busy = [...workers].filter(w => w.busy).length
+  return { oneShot: oneShotRuns, workersBusy: busy, workersIdle: workers.size - busy }
+}
+
+// Ne pas laisser de workers orphelins (et leurs modèles en mémoire) derrière le serveur
+process.on('exit', () => {
+  for (const worker of workers) worker.proc.kill('SIGTERM')
+})
@@ -1,11 +1,20 @@
 import path from 'path'
+import { existsSync } from 'fs'
 import { UPLOADS_DIR, RESULTS_DIR, MAX_CONCURRENT_LIMIT } from './config.js'
-import { photos, jobs } from './storage.js'
-import { STEPS, MANUAL_STEPS } from './steps/index.js'
-import { runPythonStep } from './python.js'
+import { photos, jobs, saveJob, runningProcs } from './storage.js'
+import { STEPS, MANUAL_STEPS, resolveParams, fallbackChain } from './steps/index.js'
+import { runPythonStep, killProc } from './python.js'
+import { getStepLimits } from './limits.js'
 import { sanitizeFilename, getUrlForPath } from './utils.js'
+import { publishJob, emitJob } from './events.js'
+import { spawnBranches } from './branches.js'
+import { stepCost, tryReserve, waitForResources, releaseResources, drainWaiting } from './resources.js'
+import { recordStep, recordStepFailure } from './metrics.js'
+import { recordTiming } from './timings.js'
 
 export let maxConcurrent = MAX_CONCURRENT_LIMIT
+// Jobs dont processJob tourne encore (un job mis en pause immédiate termine son étape tuée)
+const activeRuns = new Set()
 
 export function setMaxConcurrent(val) {
   maxConcurrent = val
@@ -29,6 +38,7 @@ function jobWillPauseImmediately(job) {
 }
 
 export function processNext() {
+  drainWaiting()
   const allJobs = [...jobs.values()]
   const running = allJobs.filter((j) => j.status === 'processing').length
 
@@ -36,33 +46,134 @@ export function processNext() {
   const hasWaitingManual = allJobs.some(j => j.status === 'waiting_input')
 
   const pending = allJobs
-    .filter((j) => j.status === 'pending')
+    .filter((j) => j.status === 'pending' && !activeRuns.has(j.id))
     .filter((j) => !hasWaitingManual || !jobHasManualSteps(j))
     .sort((a, b) => (a.priority || 0) - (b.priority || 0))
 
   let slotsUsed = 0
   for (const job of pending) {
     const willPause = jobWillPauseImmediately(job)
-    // Les jobs qui vont immédiatement se mettre en pause ne consomment pas de slot
-    if (!willPause && running + slotsUsed >= maxConcurrent) continue
-    if (!willPause) slotsUsed++
+    // Les jobs qui vont immédiatement se mettre en pause ne consomment ni slot ni budget
+    if (!willPause) {
+      // maxConcurrent plafonne le nombre de jobs, le budget machine s'ajoute à ce plafond
+      if (running + slotsUsed >= maxConcurrent) continue
+      // Première étape réservée ici : un job lourd qui ne tient pas laisse passer les plus légers
+      if (!tryReserve(job, stepCost(job, job.resumeFromStep || 0))) continue
+      slotsUsed++
+    }
     processJob(job).then(() => processNext())
   }
 }
 
+function markPaused(job) {
+  const idx = job.resumeFromStep || 0
+  job.status = 'paused'
+  job.pauseRequested = false
+  job.pausedAt = new Date().toISOString()
+  job.currentStep = null
+  job.stepProgress = null
+  job.phase = null
+  job.progress = job.steps.length ? Math.round((idx / job.steps.length) * 100) : 0
+  publishJob('job_paused', job)
+}
+
+/**
+ * Pause a pending or processing job; false if it cannot be paused. A processing job stops
+ * after its current step, or right away with `immediate`: the running script is killed and
+ * the step starts over on resume.
+ */
+export function pauseJob(job, { immediate = false } = {}) {
+  if (job.status === 'pending' || (job.status === 'processing' && immediate)) {
+    const proc = runningProcs.get(job.id)
+    if (proc) {
+      killProc(proc)
+      runningProcs.delete(job.id)
+    }
+    markPaused(job)
+    return true
+  }
+  if (job.status === 'processing') {
+    job.pauseRequested = true
+    publishJob('job_updated', job)
+    return true
+  }
+  return false
+}
+
+/** Put a paused job back in the queue (or drop a pause requested for after the current step) */
+export function resumeJob(job) {
+  if (job.status === 'processing' && job.pauseRequested) {
+    job.pauseRequested = false
+    publishJob('job_updated', job)
+    return true
+  }
+  if (job.status !== 'paused') return false
+  job.status = 'pending'
+  job.pausedAt = null
+  publishJob('job_updated', job)
+  return true
+}
+
+/**
+ * Au démarrage : remettre en file les jobs coupés en plein traitement, à partir de la
+ * dernière étape terminée. Les jobs waiting_input restent en attente de leur saisie,
+ * les jobs en pause (ou dont la pause était demandée) restent en pause.
+ */
+export function recoverInterruptedJobs() {
+  let resumed = 0
+  for (const job of jobs.values()) {
+    if (job.status !== 'processing') continue
+    const idx = job.resumeFromStep || 0
+    const input = job.currentInputPath || job.inputPath
+    if (input && !existsSync(input)) {
+      job.status = 'failed'
+      job.error = 'Interrompu par un redémarrage du serveur (fichier intermédiaire introuvable)'
+      job.failedStep = job.steps[idx]
+      job.failedStepIndex = idx
+      job.currentStep = null
+      saveJob(job)
+      continue
+    }
+    if (job.pauseRequested) {
+      markPaused(job)
+      continue
+    }
+    job.status = 'pending'
+    job.currentStep = null
+    job.progress = job.steps.length ? Math.round((idx / job.steps.length) * 100) : 0
+    saveJob(job)
+    resumed++
+  }
+  if (resumed > 0) console.log(`Reprise de ${resumed} job(s) interrompu(s)`)
+  processNext()
+}
+
 export async function processJob(job) {
+  activeRuns.add(job.id)
+  try {
+    await runJob(job)
+  } finally {
+    activeRuns.delete(job.id)
+    releaseResources(job)
+  }
+}
+
+async function runJob(job) {
   job.status = 'processing'
+  job.startedAt = job.startedAt || new Date().toISOString()
   const photo = photos.get(job.photoId)
   if (!photo) {
     job.status = 'failed'
+    publishJob('job_failed', job)
     return
   }
+  saveJob(job)
 
   const origName = sanitizeFilename(path.parse(photo.originalName).name)
   const jobShort = job.id.slice(0, 6)
 
   const startIndex = job.resumeFromStep || 0
-  let currentInput = job.currentInputPath || path.join(UPLOADS_DIR, photo.filename)
+  let currentInput = job.currentInputPath || job.inputPath || path.join(UPLOADS_DIR, photo.filename)
 
   try {
     for (let i = startIndex; i < job.steps.length; i++) {
@@ -70,6 +181,12 @@ export async function processJob(job) {
       const stepDef = STEPS[step]
       if (!stepDef) continue
 
+      // Pause demandée pendant l'étape précédente
+      if (job.pauseRequested) {
+        markPaused(job)
+        return
+      }
+
       // Pause at manual steps needing input
       if (stepDef.needsInput?.(job)) {
         job.status = 'waiting_input'
@@ -78,42 +195,155 @@ export async function processJob(job) {
         job.currentInputPath = currentInput
         job.waitingImage = getUrlForPath(currentInput)
         job.progress = Math.round((i / job.steps.length) * 100)
+        publishJob('waiting_input', job)
         return
       }
 
       job.currentStep = step
       job.progress = Math.round((i / job.steps.length) * 100)
+      job.stepProgress = 0
+      job.phase = null
+      publishJob('step_started', job)
       console.log(`Job ${job.id} | Step ${i + 1}/${job.steps.length}: ${stepDef.name}`)
 
+      // Mémoire/CPU estimés de l'étape, à réserver sur le budget machine (voir resources.js)
+      const cost = stepCost(job, i, currentInput)
+      if (!tryReserve(job, cost)) {
+        job.phase = 'En attente de ressources'
+        emitJob('step_progress', job)
+        const granted = waitForResources(job, cost)
+        // Étape refusée pour laisser passer un job en file affamé : c'est à lui de démarrer
+        processNext()
+        if (!await granted) return
+        job.phase = null
+        emitJob('step_progress', job)
+      }
+      // Début réel du calcul (attente des ressources exclue), pour l'ETA
+      job.stepStartedAt = new Date().toISOString()
+
       // All outputs are PNG, named consistently
       const prefix = stepDef.prefix || step
       const outputFilename = `${origName}_${prefix}_${jobShort}.png`
       const outputPath = path.join(RESULTS_DIR, outputFilename)
 
-      // Let the step build its own args
+      // Let the step build its own args. `span` is the share of the step covered by this
+      // run (several runs per step in comparison mode), `label` prefixes the phases.
+      const runModel = async (selectedModel, outPath, span = [0, 1], label = null) => {
+        const { script, args } = stepDef.buildArgs({
+          inputPath: currentInput, outputPath: outPath, job, selectedModel,
+          params: resolveParams(step, job.params?.[step]),
+        })
+        const runStart = Date.now()
+        const record = await runPythonStep(script, args, job.id, {
+          ...getStepLimits(step),
+          onProgress: ({ fraction, phase }) => {
+            if (job.status !== 'processing') return
+            if (phase !== undefined) job.phase = label ? `${label} — ${phase}` : phase
+            if (fraction !== undefined) {
+              job.stepProgress = span[0] + fraction * (span[1] - span[0])
+              job.progress = Math.round(((i + job.stepProgress) / job.steps.length) * 100)
+            }
+            emitJob('step_progress', job)
+          },
+        }).catch((err) => {
+          // Job annulé ou mis en pause : script tué, ce n'est pas un échec du modèle
+          if (job.status === 'processing') recordStepFailure(step, selectedModel)
+          throw err
+        })
+        recordStep(step, selectedModel, Date.now() - runStart)
+        recordTiming(step, selectedModel, currentInput, Date.now() - runStart)
+        return record
+      }
+
+      // Check cancellation (or immediate pause) before starting step
+      if (job.status !== 'processing') return
+
+      // Mode comparaison : chaque modèle demandé sur la même entrée, puis choix manuel
+      // du gagnant (POST /api/jobs/:id/input { choice }) avant de continuer
+      const compareModels = stepDef.models ? job.compare?.[step] : null
+      if (compareModels?.length) {
+        const comparison = []
+        for (const [k, model] of compareModels.entries()) {
+          if (job.status !== 'processing') return
+          const filename = `${origName}_${prefix}-${sanitizeFilename(model)}_${jobShort}.png`
+          const span = [k / compareModels.length, (k + 1) / compareModels.length]
+          const runStart = Date.now()
+          try {
+            const { output, ...report } = await runModel(model, path.join(RESULTS_DIR, filename), span, stepDef.models[model]?.name || model)
+            comparison.push({ ...report, model, result: `/results/${filename}`, durationMs: Date.now() - runStart })
+          } catch (err) {
+            if (job.status !== 'processing') throw err
+            comparison.push({ model, result: null, error: err.message })
+          }
+        }
+        if (!comparison.some(c => c.result)) {
+          throw new Error(comparison.map(c => `${c.model} : ${c.error}`).join('\n'))
+        }
+
+        job.stepResults.push({ step, index: i, result: null, comparison })
+        job.status = 'waiting_input'
+        job.waitingStep = step
+        job.resumeFromStep = i
+        job.currentInputPath = currentInput
+        job.waitingImage = getUrlForPath(currentInput)
+        job.currentStep = null
+        job.stepProgress = null
+        job.phase = null
+        job.progress = Math.round(((i + 1) / job.steps.length) * 100)
+        publishJob('waiting_input', job)
+        return
+      }
+
       const selectedModel = stepDef.models
         ? (job.options?.[step] || stepDef.defaultModel)
         : null
-      const { script, args } = stepDef.buildArgs({
-        inputPath: currentInput, outputPath, job, selectedModel,
-      })
-
-      // Check cancellation before starting step
-      if (job.status === 'cancelled') return
+      const stepStart = Date.now()
 
-      await runPythonStep(script, args, job.id)
+      // Modèle en échec (mémoire, timeout, poids absents…) : on descend la chaîne de repli
+      // de l'étape, le résultat garde la trace du modèle demandé et des erreurs rencontrées
+      const chain = [selectedModel, ...fallbackChain(step, selectedModel)]
+      const attempts = []
+      let record = null
+      for (const [k, model] of chain.entries()) {
+        if (k > 0) {
+          console.log(`Job ${job.id} | ${stepDef.name} : ${chain[k - 1]} en échec, repli sur ${model}`)
+          job.phase = `Repli sur ${stepDef.models[model]?.name || model}`
+          job.stepProgress = 0
+          emitJob('step_progress', job)
+        }
+        try {
+          record = await runModel(model, outputPath)
+          break
+        } catch (err) {
+          if (job.status !== 'processing' || chain.length === 1) throw err
+          attempts.push({ model, error: err.message.slice(0, 500) })
+          if (k === chain.length - 1) {
+            throw new Error(attempts.map(a => `${a.model} : ${a.error}`).join('\n'))
+          }
+        }
+      }
 
-      // Check cancellation after step completes
-      if (job.status === 'cancelled') return
+      // Check cancellation after step completes (paused: the step is redone on resume)
+      if (job.status !== 'processing') return
 
       // Clean up after step
       if (stepDef.onComplete) stepDef.onComplete(job)
 
-      // Record step result
-      job.stepResults.push({ step, result: `/results/${outputFilename}` })
+      // Record step result, with the script's report (no-op, metrics, warnings...)
+      const { output, ...report } = record
+      job.stepResults.push({
+        step, index: i, result: `/results/${outputFilename}`, ...report,
+        startedAt: new Date(stepStart).toISOString(), durationMs: Date.now() - stepStart,
+        ...(attempts.length ? { fallback: { requested: selectedModel, used: chain[attempts.length], attempts } } : {}),
+      })
 
       currentInput = outputPath
       job.currentInputPath = currentInput
+      job.resumeFromStep = i + 1
+      job.stepProgress = null
+      job.phase = null
+      publishJob('step_completed', job)
+      releaseResources(job)
 
       // Après chaque étape, relancer la queue — permet au prochain job manuel
       // de démarrer pendant que ce job continue ses étapes automatiques
@@ -122,24 +352,38 @@ export async function processJob(job) {
 
     // Job completed: final result is the last step's output
     job.status = 'completed'
+    job.pauseRequested = false
+    job.completedAt = new Date().toISOString()
     job.progress = 100
+    job.phase = null
     job.currentStep = null
     job.waitingStep = null
     job.waitingImage = null
     job.result = job.stepResults.length > 0
       ? job.stepResults[job.stepResults.length - 1].result
-      : null
+      : job.inputPath ? getUrlForPath(job.inputPath) : null
+    spawnBranches(job)
+    publishJob('job_completed', job)
   } catch (err) {
     if (job.status === 'cancelled') {
       console.log(`Job ${job.id} cancelled`)
       job.currentStep = null
+      publishJob('job_cancelled', job)
+      return
+    }
+    if (job.status !== 'processing') {
+      console.log(`Job ${job.id} paused, interrupted step will be redone on resume`)
       return
     }
     console.error(`Job ${job.id} failed at step "${job.currentStep}":`, err.message)
     job.status = 'failed'
+    job.failedAt = new Date().toISOString()
+    job.phase = null
+    job.stepProgress = null
     job.error = err.message
     job.failedStep = job.currentStep
     job.failedStepIndex = job.steps.indexOf(job.currentStep)
     job.currentStep = null
+    publishJob('job_failed', job)
   }
 }
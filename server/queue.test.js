@@ -0,0 +1,39 @@
+import { test } from 'node:test'
+import assert from 'node:assert/strict'
+import { mkdtempSync } from 'fs'
+import os from 'os'
+import path from 'path'
+
+// Budget large (trois étapes y tiennent), sans dossiers du dépôt : avant le premier import de config.js
+const dir = mkdtempSync(path.join(os.tmpdir(), 'oldphotos-test-'))
+process.env.UPLOADS_DIR = path.join(dir, 'uploads')
+process.env.RESULTS_DIR = path.join(dir, 'results')
+process.env.RESOURCE_MEMORY_MB = '100000'
+process.env.RESOURCE_CPUS = '16'
+
+const { processNext, setMaxConcurrent } = await import('./queue.js')
+const { tryReserve, releaseResources, stepCost } = await import('./resources.js')
+const { jobs } = await import('./storage.js')
+
+test('maxConcurrent caps running jobs even when the budget has room for more', () => {
+  setMaxConcurrent(1)
+  const job = (id, status) => ({ id, status, photoId: 'missing', steps: ['upscale'], params: {}, priority: 0 })
+  const running = job('running', 'processing')
+  jobs.set(running.id, running)
+  assert.equal(tryReserve(running, stepCost(running, 0)), true)
+  const queued = ['a', 'b', 'c'].map(id => job(id, 'pending'))
+  for (const j of queued) {
+    jobs.set(j.id, j)
+    assert.equal(tryReserve(j, stepCost(j, 0)), true, 'the budget alone would let it start')
+    releaseResources(j)
+  }
+
+  processNext()
+  assert.deepEqual(queued.map(j => j.status), ['pending', 'pending', 'pending'], 'the only slot is taken')
+
+  running.status = 'completed'
+  releaseResources(running)
+  processNext()
+  // Sans photo, le job démarré échoue aussitôt : un seul a quitté la file sur ce passage
+  assert.equal(queued.filter(j => j.status !== 'pending').length, 1)
+})
@@ -0,0 +1,174 @@
+import { readdirSync, statSync, unlinkSync, utimesSync } from 'fs'
+import path from 'path'
+import { UPLOADS_DIR, RESULTS_DIR, STORAGE_QUOTA_BYTES } from './config.js'
+import { photos, jobs, saveJob } from './storage.js'
+import { subscribe } from './events.js'
+import { referencedFiles } from './cleanup.js'
+import { getPathForUrl } from './utils.js'
+
+// Quota disque sur uploads + results : au-delà, les fichiers les moins récemment consultés
+// sont supprimés, d'abord les intermédiaires, puis les résultats finaux. Jamais les originaux
+// des photos, ni les fichiers des jobs en cours ou épinglés. Un fichier supprimé reste dans
+// l'historique, marqué `evicted` (étapes) ou `resultEvicted` (job). La dernière consultation
+// est l'atime du fichier, mis à jour explicitement à chaque accès par /uploads et /results.
+
+const TIER_INTERMEDIATE = 0
+const TIER_FINAL = 1
+const TIER_NAMES = ['intermédiaire', 'final']
+
+const TOUCH_INTERVAL_MS = 60 * 1000
+const RECENT_MS = 60 * 1000
+// Parcours des dossiers pour /api/status et /metrics, gardé un moment
+const USAGE_TTL_MS = 15 * 1000
+const lastTouched = new Map()  // chemin → dernière mise à jour de l'atime
+let usageCache = null  // { at, usage }
+
+function listFiles() {
+  const files = []
+  for (const dir of [UPLOADS_DIR, RESULTS_DIR]) {
+    let names
+    try { names = readdirSync(dir) } catch { continue }
+    for (const name of names) {
+      if (name === '.gitkeep') continue
+      const filePath = path.join(dir, name)
+      try {
+        const stat = statSync(filePath)
+        if (!stat.isFile()) continue
+        files.push({ path: filePath, dir, size: stat.size, modifiedMs: stat.mtimeMs, accessedMs: Math.max(stat.atimeMs, stat.mtimeMs) })
+      } catch {}
+    }
+  }
+  return files
+}
+
+/** Bytes used by uploads and results, and the configured quota (null when unlimited); cached USAGE_TTL_MS */
+export function storageUsage() {
+  const now = Date.now()
+  if (usageCache && now - usageCache.at < USAGE_TTL_MS) return usageCache.usage
+  const files = listFiles()
+  const pinned = new Set([...referencedFiles()].filter(([, ref]) => ref.pinned).map(([file]) => file))
+  const sum = (list) => list.reduce((total, f) => total + f.size, 0)
+  const usage = {
+    used: sum(files),
+    quota: STORAGE_QUOTA_BYTES || null,
+    uploads: sum(files.filter(f => f.dir === UPLOADS_DIR)),
+    results: sum(files.filter(f => f.dir === RESULTS_DIR)),
+    pinned: sum(files.filter(f => pinned.has(f.path))),
+  }
+  usageCache = { at: now, usage }
+  return usage
+}
+
+/** Express middleware for the static file routes: records the access time (LRU order) */
+export function trackAccess(dir) {
+  return (req, _res, next) => {
+    if (STORAGE_QUOTA_BYTES && req.method === 'GET') {
+      const now = Date.now()
+      try {
+        const filePath = path.join(dir, path.basename(decodeURIComponent(req.path)))
+        if (now - (lastTouched.get(filePath) || 0) > TOUCH_INTERVAL_MS) {
+          lastTouched.set(filePath, now)
+          utimesSync(filePath, new Date(now), statSync(filePath).mtime)
+        }
+      } catch {}
+    }
+    next()
+  }
+}
+
+/** Delete least-recently-accessed files until usage fits the quota; returns what was evicted */
+export function enforceQuota() {
+  if (!STORAGE_QUOTA_BYTES) return []
+  const files = listFiles()
+  let used = files.reduce((total, f) => total + f.size, 0)
+  if (used <= STORAGE_QUOTA_BYTES) return []
+
+  const protectedFiles = referencedFiles()
+  const finals = new Set([...jobs.values()].map(j => getPathForUrl(j.result)).filter(Boolean))
+  // Originaux : ceux des photos, et ceux des jobs dont la photo a été retirée de la liste
+  const originals = new Set([
+    ...[...photos.values()].map(p => path.join(UPLOADS_DIR, p.filename)),
+    ...[...jobs.values()].map(j => getPathForUrl(j.original)).filter(Boolean),
+  ])
+  const now = Date.now()
+  const candidates = files
+    // Fichiers tout récents : peut-être en cours d'écriture par un script
+    .filter(f => !protectedFiles.has(f.path) && !originals.has(f.path) && now - f.modifiedMs > RECENT_MS)
+    .map(f => ({ ...f, tier: finals.has(f.path) ? TIER_FINAL : TIER_INTERMEDIATE }))
+    .sort((a, b) => a.tier - b.tier || a.accessedMs - b.accessedMs)
+
+  const evicted = []
+  for (const file of candidates) {
+    if (used <= STORAGE_QUOTA_BYTES) break
+    try {
+      unlinkSync(file.path)
+    } catch {
+      continue
+    }
+    used -= file.size
+    lastTouched.delete(file.path)
+    evicted.push(file)
+  }
+  usageCache = null
+  if (!evicted.length) {
+    console.log(`Quota : ${formatBytes(used)} utilisés pour ${formatBytes(STORAGE_QUOTA_BYTES)}, rien d'évictable pour l'instant (originaux, jobs en cours, épinglés ou fichiers récents)`)
+    return evicted
+  }
+
+  forgetEvicted(new Set(evicted.map(f => f.path)))
+  const byTier = TIER_NAMES.map((name, tier) => {
+    const count = evicted.filter(f => f.tier === tier).length
+    return count ? `${count} ${name}${count > 1 ? 's' : ''}` : null
+  }).filter(Boolean)
+  console.log(`Quota : ${evicted.length} fichier(s) supprimé(s) (${byTier.join(', ')}), ${formatBytes(used)} / ${formatBytes(STORAGE_QUOTA_BYTES)}`)
+  return evicted
+}
+
+/** Mark the step results and final results pointing at evicted files; the jobs stay in history */
+function forgetEvicted(evicted) {
+  for (const job of jobs.values()) {
+    let changed = false
+    if (!job.resultEvicted && evicted.has(getPathForUrl(job.result))) {
+      job.resultEvicted = true
+      changed = true
+    }
+    for (const sr of job.stepResults || []) {
+      if (sr.result && !sr.evicted && evicted.has(getPathForUrl(sr.result))) {
+        sr.evicted = true
+        changed = true
+      }
+      for (const candidate of sr.comparison || []) {
+        if (candidate.result && !candidate.evicted && evicted.has(getPathForUrl(candidate.result))) {
+          candidate.evicted = true
+          changed = true
+        }
+      }
+    }
+    if (changed) saveJob(job)
+  }
+}
+
+function formatBytes(bytes) {
+  return bytes >= 1024 ** 3 ? `${(bytes / 1024 ** 3).toFixed(1)} Go` : `${Math.round(bytes / 1024 ** 2)} Mo`
+}
+
+let pending = null
+
+/** Enforce the quota shortly after new files appear (debounced) */
+export function scheduleQuotaCheck() {
+  usageCache = null
+  if (!STORAGE_QUOTA_BYTES || pending) return
+  pending = setTimeout(() => {
+    pending = null
+    enforceQuota()
+  }, 1000)
+}
+
+export function startQuotaWatcher() {
+  if (!STORAGE_QUOTA_BYTES) return
+  subscribe(({ type }) => {
+    if (type === 'step_completed' || type === 'job_completed' || type === 'job_failed') scheduleQuotaCheck()
+  })
+  enforceQuota()
+  console.log(`Quota disque : ${formatBytes(STORAGE_QUOTA_BYTES)} (uploads + results)`)
+}
@@ -0,0 +1,222 @@
+import { openSync, readSync, closeSync, statSync } from 'fs'
+import path from 'path'
+import { UPLOADS_DIR, RESOURCE_MEMORY_MB, RESOURCE_CPUS } from './config.js'
+import { photos, jobs } from './storage.js'
+import { STEPS } from './steps/index.js'
+
+// Budget machine partagé par les étapes en cours : avant de lancer son script, une étape
+// réserve une estimation mémoire/CPU (`cost` dans server/steps/*.js, surchargé par modèle,
+// proportionnel aux mégapixels de son entrée). Une étape légère passe à côté d'une lourde
+// tant que le total tient dans le budget ; une étape seule démarre toujours, même trop grosse.
+
+const DEFAULT_COST = { memoryMb: 1024, memoryMbPerMp: 200, cpus: 1 }
+/** A memory or CPU budget is set (maxConcurrent still caps the number of jobs) */
+export const HAS_BUDGET = !!(RESOURCE_MEMORY_MB || RESOURCE_CPUS)
+// Au-delà, une demande refusée bloque celles arrivées après elle (pas de famine des lourdes)
+const STARVATION_MS = 2 * 60 * 1000
+
+const reserved = new Map()  // jobId → { step, models, megapixels, memoryMb, cpus }
+const refusedSince = new Map()  // jobId → première demande refusée (job en file ou étape en attente)
+let waiting = []  // { job, cost, resolve }, étapes de jobs en cours qui attendent le budget
+
+// --- Dimensions des images (en-têtes seulement) ---
+
+const HEADER_BYTES = 256 * 1024
+const sizeCache = new Map()  // chemin → { mtimeMs, megapixels }
+
+function parseImageSize(buf) {
+  if (buf.length >= 24 && buf.readUInt32BE(0) === 0x89504e47) {
+    return { width: buf.readUInt32BE(16), height: buf.readUInt32BE(20) }
+  }
+  if (buf[0] === 0xff && buf[1] === 0xd8) {
+    // JPEG : parcourir les segments jusqu'au premier SOFn
+    let i = 2
+    while (i + 9 < buf.length) {
+      if (buf[i] !== 0xff) { i++; continue }
+      const marker = buf[i + 1]
+      if (marker === 0xff) { i++; continue }
+      if (marker === 0x01 || (marker >= 0xd0 && marker <= 0xd8)) { i += 2; continue }
+      if (marker >= 0xc0 && marker <= 0xcf && ![0xc4, 0xc8, 0xcc].includes(marker)) {
+        return { width: buf.readUInt16BE(i + 7), height: buf.readUInt16BE(i + 5) }
+      }
+      i += 2 + buf.readUInt16BE(i + 2)
+    }
+    return null
+  }
+  if (buf.length >= 26 && buf.toString('ascii', 0, 2) === 'BM') {
+    return { width: Math.abs(buf.readInt32LE(18)), height: Math.abs(buf.readInt32LE(22)) }
+  }
+  if (buf.length >= 30 && buf.toString('ascii', 0, 4) === 'RIFF' && buf.toString('ascii', 8, 12) === 'WEBP') {
+    const chunk = buf.toString('ascii', 12, 16)
+    if (chunk === 'VP8X') return { width: 1 + buf.readUIntLE(24, 3), height: 1 + buf.readUIntLE(27, 3) }
+    if (chunk === 'VP8 ') return { width: buf.readUInt16LE(26) & 0x3fff, height: buf.readUInt16LE(28) & 0x3fff }
+    if (chunk === 'VP8L') {
+      const bits = buf.readUInt32LE(21)
+      return { width: (bits & 0x3fff) + 1, height: ((bits >> 14) & 0x3fff) + 1 }
+    }
+  }
+  return null
+}
+
+/** Megapixels of an image file, read from its header (cached); null if the file is missing */
+export function imageMegapixels(filePath) {
+  let stat
+  try { stat = statSync(filePath) } catch { return null }
+  const cached = sizeCache.get(filePath)
+  if (cached?.mtimeMs === stat.mtimeMs) return cached.megapixels
+
+  let size = null
+  let fd
+  try {
+    fd = openSync(filePath, 'r')
+    const buf = Buffer.alloc(Math.min(HEADER_BYTES, stat.size))
+    size = parseImageSize(buf.subarray(0, readSync(fd, buf, 0, buf.length, 0)))
+  } catch {
+  } finally {
+    if (fd !== undefined) closeSync(fd)
+  }
+  // TIFF ou en-tête illisible : estimation large d'après le poids (~1 octet par pixel)
+  const megapixels = size ? (size.width * size.height) / 1e6 : stat.size / 1e6
+  if (sizeCache.size > 1000) sizeCache.clear()
+  sizeCache.set(filePath, { mtimeMs: stat.mtimeMs, megapixels })
+  return megapixels
+}
+
+// --- Coût des étapes ---
+
+/** Input file of the next step of a job */
+export function jobInputPath(job) {
+  const photo = photos.get(job.photoId)
+  return job.currentInputPath || job.inputPath || (photo && path.join(UPLOADS_DIR, photo.filename))
+}
+
+/**
+ * Estimated cost of step `index` of a job on `inputPath`: { step, models, megapixels, memoryMb, cpus }.
+ * In comparison mode the models run one after the other, the heaviest one is reserved.
+ */
+export function stepCost(job, index, inputPath = jobInputPath(job)) {
+  const step = job.steps[index]
+  const stepDef = STEPS[step] || {}
+  const models = stepDef.models
+    ? (job.compare?.[step]?.length ? job.compare[step] : [job.options?.[step] || stepDef.defaultModel])
+    : [null]
+  const megapixels = (inputPath && imageMegapixels(inputPath)) || 0
+  const costs = models.map(model => ({ ...DEFAULT_COST, ...stepDef.cost, ...stepDef.models?.[model]?.cost }))
+  return {
+    step,
+    models: models.filter(Boolean),
+    megapixels: Math.round(megapixels * 10) / 10,
+    memoryMb: Math.max(...costs.map(c => Math.round(c.memoryMb + c.memoryMbPerMp * megapixels))),
+    cpus: Math.max(...costs.map(c => c.cpus)),
+  }
+}
+
+// --- Réservations ---
+
+function used() {
+  let memoryMb = 0
+  let cpus = 0
+  for (const r of reserved.values()) {
+    memoryMb += r.memoryMb
+    cpus += r.cpus
+  }
+  return { memoryMb, cpus }
+}
+
+function fits(cost) {
+  if (reserved.size === 0) return true
+  const { memoryMb, cpus } = used()
+  return (!RESOURCE_MEMORY_MB || memoryMb + cost.memoryMb <= RESOURCE_MEMORY_MB)
+    && (!RESOURCE_CPUS || cpus + cost.cpus <= RESOURCE_CPUS)
+}
+
+/** Another request refused for longer than STARVATION_MS, and older than this one's */
+function blockedByStarving(jobId, now) {
+  const own = refusedSince.get(jobId) ?? now
+  for (const [id, since] of refusedSince) {
+    if (id !== jobId && since < own && now - since > STARVATION_MS) return true
+  }
+  return false
+}
+
+/** This request is the oldest one refused for longer than STARVATION_MS */
+function isStarving(jobId, now) {
+  const since = refusedSince.get(jobId)
+  return since !== undefined && now - since > STARVATION_MS && !blockedByStarving(jobId, now)
+}
+
+function grant(job, cost) {
+  reserved.set(job.id, cost)
+  refusedSince.delete(job.id)
+}
+
+/**
+ * Reserve resources for a job's next step if the budget allows it right now.
+ * Synchronous, so successive calls in one processNext() pass see each other.
+ * A step already reserved for this job (by processNext) is kept.
+ */
+export function tryReserve(job, cost) {
+  if (reserved.get(job.id)?.step === cost.step) return true
+  const now = Date.now()
+  // Les étapes des jobs déjà commencés passent avant les nouveaux jobs, sauf devant un job
+  // affamé : les étapes en attente lui cèdent la place (blockedByStarving), il doit la prendre
+  const first = isStarving(job.id, now) || !waiting.some(w => w.job.id !== job.id)
+  if (first && fits(cost) && !blockedByStarving(job.id, now)) {
+    grant(job, cost)
+    return true
+  }
+  if (!refusedSince.has(job.id)) refusedSince.set(job.id, now)
+  return false
+}
+
+/**
+ * Wait until the budget allows the step (first come, first served among waiting steps).
+ * Resolves true once reserved, false if the job stopped processing meanwhile.
+ */
+export function waitForResources(job, cost) {
+  if (!refusedSince.has(job.id)) refusedSince.set(job.id, Date.now())
+  return new Promise(resolve => {
+    waiting.push({ job, cost, resolve })
+  })
+}
+
+/** Free the job's reservation and hand the budget to waiting steps */
+export function releaseResources(job) {
+  reserved.delete(job.id)
+  drainWaiting()
+}
+
+/**
+ * Start waiting steps that now fit, in arrival order; drop those whose job was
+ * cancelled. Also forgets refused jobs that are no longer queued.
+ */
+export function drainWaiting() {
+  const now = Date.now()
+  const still = []
+  for (const w of waiting) {
+    if (w.job.status !== 'processing') {
+      refusedSince.delete(w.job.id)
+      w.resolve(false)
+    } else if (!still.length && fits(w.cost) && !blockedByStarving(w.job.id, now)) {
+      grant(w.job, w.cost)
+      w.resolve(true)
+    } else {
+      still.push(w)
+    }
+  }
+  waiting = still
+  for (const id of refusedSince.keys()) {
+    const status = jobs.get(id)?.status
+    if (status !== 'pending' && !waiting.some(w => w.job.id === id)) refusedSince.delete(id)
+  }
+}
+
+/** Budget, current reservations and number of requests waiting, for /api/status */
+export function resourceUsage() {
+  return {
+    budget: { memoryMb: RESOURCE_MEMORY_MB || null, cpus: RESOURCE_CPUS || null },
+    used: used(),
+    running: [...reserved.values()],
+    waiting: refusedSince.size,
+  }
+}
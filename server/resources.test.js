@@ -0,0 +1,46 @@
+import { test } from 'node:test'
+import assert from 'node:assert/strict'
+import { mkdtempSync } from 'fs'
+import os from 'os'
+import path from 'path'
+
+// Budget fixe, sans dossiers du dépôt : à définir avant le premier import de config.js
+const dir = mkdtempSync(path.join(os.tmpdir(), 'oldphotos-test-'))
+process.env.UPLOADS_DIR = path.join(dir, 'uploads')
+process.env.RESULTS_DIR = path.join(dir, 'results')
+process.env.RESOURCE_MEMORY_MB = '4000'
+process.env.RESOURCE_CPUS = '0'
+
+const { tryReserve, waitForResources, releaseResources, resourceUsage } = await import('./resources.js')
+const { jobs } = await import('./storage.js')
+
+const cost = (step, memoryMb) => ({ step, models: [], megapixels: 0, memoryMb, cpus: 1 })
+
+test('a starving pending job is not deadlocked by a step waiting behind it', async (t) => {
+  let now = Date.now()
+  t.mock.method(Date, 'now', () => now)
+
+  const running = { id: 'running', status: 'processing' }
+  const heavy = { id: 'heavy', status: 'pending' }
+  jobs.set(running.id, running)
+  jobs.set(heavy.id, heavy)
+
+  assert.equal(tryReserve(running, cost('colorize', 3000)), true)
+  assert.equal(tryReserve(heavy, cost('upscale', 3500)), false)
+
+  // Le job lourd attend depuis plus de STARVATION_MS quand l'étape en cours se termine
+  now += 3 * 60 * 1000
+  releaseResources(running)
+  assert.equal(tryReserve(running, cost('upscale', 1000)), false, 'the next step yields to the starving job')
+  const granted = waitForResources(running, cost('upscale', 1000))
+
+  assert.equal(tryReserve(heavy, cost('upscale', 3500)), true, 'the starving job starts on the idle budget')
+  assert.equal(resourceUsage().used.memoryMb, 3500)
+
+  heavy.status = 'processing'
+  releaseResources(heavy)
+  assert.equal(await granted, true, 'the waiting step runs once the heavy job released')
+  assert.equal(resourceUsage().used.memoryMb, 1000)
+  releaseResources(running)
+  assert.equal(resourceUsage().waiting, 0)
+})
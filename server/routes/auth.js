@@ -0,0 +1,89 @@
+import { Router } from 'express'
+import { apiTokens } from '../storage.js'
+import {
+  AUTH_COOKIE, authEnabled, authMode, checkCredentials, setSessionCookie,
+  userFromRequest, requireAuth, createApiToken, toPublicToken, ADMIN_USER,
+} from '../auth.js'
+
+const router = Router()
+
+// Échecs de connexion par adresse : 10 essais par quart d'heure
+const LOGIN_WINDOW_MS = 15 * 60 * 1000
+const LOGIN_MAX_FAILURES = 10
+const failures = new Map()  // ip → { count, since }
+
+function tooManyFailures(ip) {
+  // Fenêtres expirées oubliées à chaque tentative, pas seulement celle de cette adresse
+  const now = Date.now()
+  for (const [key, entry] of failures) {
+    if (now - entry.since > LOGIN_WINDOW_MS) failures.delete(key)
+  }
+  return (failures.get(ip)?.count ?? 0) >= LOGIN_MAX_FAILURES
+}
+
+function recordFailure(ip) {
+  const entry = failures.get(ip)
+  if (entry && Date.now() - entry.since <= LOGIN_WINDOW_MS) entry.count++
+  else failures.set(ip, { count: 1, since: Date.now() })
+}
+
+// État de l'authentification, public : l'interface décide d'afficher l'écran de connexion
+router.get('/', (req, res) => {
+  res.json({
+    enabled: authEnabled(),
+    mode: authMode(),
+    user: authEnabled() ? userFromRequest(req) : null,
+  })
+})
+
+router.post('/login', (req, res) => {
+  if (!authEnabled()) return res.json({ user: null })
+  const ip = req.ip || req.socket?.remoteAddress || ''
+  if (tooManyFailures(ip)) {
+    return res.status(429).json({ error: 'Trop de tentatives, réessayez dans quelques minutes' })
+  }
+  const { username, password } = req.body || {}
+  const user = checkCredentials(username, password)
+  if (!user) {
+    recordFailure(ip)
+    console.log(`Auth : échec de connexion${username ? ` pour « ${String(username).slice(0, 40)} »` : ''} depuis ${ip}`)
+    return res.status(401).json({ error: 'Identifiants invalides' })
+  }
+  failures.delete(ip)
+  setSessionCookie(req, res, user)
+  res.json({ user })
+})
+
+router.post('/logout', (_req, res) => {
+  res.clearCookie(AUTH_COOKIE)
+  res.json({ ok: true })
+})
+
+// --- API tokens (bearer) ---
+
+// Chacun voit et révoque ses propres jetons ; l'admin, ceux de tout le monde
+function ownsToken(req, record) {
+  return record.user === req.user || req.user === ADMIN_USER
+}
+
+router.get('/tokens', requireAuth, (req, res) => {
+  if (!authEnabled()) return res.json([])
+  res.json([...apiTokens.values()].filter(t => ownsToken(req, t)).map(toPublicToken))
+})
+
+router.post('/tokens', requireAuth, (req, res) => {
+  if (!authEnabled()) return res.status(400).json({ error: 'Authentication is disabled' })
+  const name = typeof req.body?.name === 'string' ? req.body.name.trim().slice(0, 80) : ''
+  if (!name) return res.status(400).json({ error: 'name is required' })
+  const { token, record } = createApiToken(req.user, name)
+  res.json({ ...toPublicToken(record), token })
+})
+
+router.delete('/tokens/:id', requireAuth, (req, res) => {
+  const record = apiTokens.get(req.params.id)
+  if (!record || !ownsToken(req, record)) return res.status(404).json({ error: 'Token not found' })
+  apiTokens.delete(record.id)
+  res.json({ ok: true })
+})
+
+export default router
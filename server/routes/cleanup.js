@@ -0,0 +1,22 @@
+import { Router } from 'express'
+import { planCleanup, runCleanup, toPublicPlan } from '../cleanup.js'
+import { ADMIN_USER } from '../auth.js'
+
+const router = Router()
+
+// Limité à la session de l'appelant ; l'admin voit et nettoie toute l'instance
+function scope(req) {
+  return { sessionId: req.user === ADMIN_USER ? null : req.sessionId }
+}
+
+// Rapport à blanc : ce que le prochain nettoyage supprimerait, et les fichiers anciens gardés
+router.get('/', (req, res) => {
+  res.json(toPublicPlan(planCleanup(scope(req))))
+})
+
+// Nettoyage immédiat, même rapport
+router.post('/', (req, res) => {
+  res.json(toPublicPlan(runCleanup(planCleanup(scope(req)))))
+})
+
+export default router
@@ -0,0 +1,35 @@
+import { Router } from 'express'
+import { subscribe } from '../events.js'
+import { connectionOpened, connectionClosed } from '../heartbeat.js'
+import { inSession } from '../sessions.js'
+import { toPublicJob } from '../utils.js'
+
+const router = Router()
+
+// Flux Server-Sent Events : une connexion ouverte sert aussi de heartbeat pour sa session
+router.get('/', (req, res) => {
+  res.set({
+    'Content-Type': 'text/event-stream',
+    'Cache-Control': 'no-cache',
+    Connection: 'keep-alive',
+  })
+  res.flushHeaders()
+  res.write('retry: 3000\n\n')
+
+  const { sessionId } = req
+  connectionOpened(sessionId)
+  const unsubscribe = subscribe(({ type, job }) => {
+    if (!inSession(job, sessionId)) return
+    res.write(`event: ${type}\ndata: ${JSON.stringify(toPublicJob(job))}\n\n`)
+  })
+  // Commentaire périodique pour que les proxies ne coupent pas la connexion
+  const keepAlive = setInterval(() => res.write(': ping\n\n'), 15000)
+
+  req.on('close', () => {
+    clearInterval(keepAlive)
+    unsubscribe()
+    connectionClosed(sessionId)
+  })
+})
+
+export default router
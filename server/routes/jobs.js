@@ -3,15 +3,25 @@ import express from 'express'
 import { randomUUID } from 'crypto'
 import { writeFileSync, unlinkSync } from 'fs'
 import path from 'path'
-import { UPLOADS_DIR, ROOT } from '../config.js'
-import { photos, jobs, runningProcs } from '../storage.js'
-import { STEPS, MANUAL_STEPS } from '../steps/index.js'
-import { isAiReady, isSetupRunning } from '../python.js'
-import { enqueueJob, processJob, processNext } from '../queue.js'
-import { touchHeartbeat } from '../heartbeat.js'
+import { UPLOADS_DIR, RESULTS_DIR, ROOT } from '../config.js'
+import { photos, jobs, presets, runningProcs } from '../storage.js'
+import { STEPS, MANUAL_STEPS, normalizeParams } from '../steps/index.js'
+import { isAiReady, isSetupRunning, killProc } from '../python.js'
+import { enqueueJob, processJob, processNext, pauseJob, resumeJob } from '../queue.js'
+import { normalizeBranches, spawnBranches } from '../branches.js'
+import { publishJob } from '../events.js'
+import { writeExport } from '../export.js'
+import { inSession } from '../sessions.js'
+import { toPublicJob, getUrlForPath } from '../utils.js'
 
 const router = Router()
 
+/** Job named in the URL, null if unknown or owned by another session */
+function ownJob(req) {
+  const job = jobs.get(req.params.id)
+  return job && inSession(job, req.sessionId) ? job : null
+}
+
 // --- Reorder (must be before /:id) ---
 
 router.put('/reorder', express.json(), (req, res) => {
@@ -20,8 +30,9 @@ router.put('/reorder', express.json(), (req, res) => {
 
   jobIds.forEach((id, index) => {
     const job = jobs.get(id)
-    if (job && job.status === 'pending') {
+    if (job && inSession(job, req.sessionId) && job.status === 'pending') {
       job.priority = index
+      publishJob('job_updated', job)
     }
   })
   res.json({ ok: true })
@@ -29,29 +40,82 @@ router.put('/reorder', express.json(), (req, res) => {
 
 // --- Cancel all ---
 
-router.post('/cancel-all', (_req, res) => {
-  const cancellable = ['pending', 'processing', 'waiting_input']
+router.post('/cancel-all', (req, res) => {
+  const cancellable = ['pending', 'processing', 'waiting_input', 'paused']
   let count = 0
   for (const job of jobs.values()) {
-    if (!cancellable.includes(job.status)) continue
+    if (!cancellable.includes(job.status) || !inSession(job, req.sessionId)) continue
     job.status = 'cancelled'
     job.currentStep = null
     job.waitingStep = null
     job.waitingImage = null
     const proc = runningProcs.get(job.id)
     if (proc) {
-      proc.kill('SIGTERM')
+      killProc(proc)
       runningProcs.delete(job.id)
     }
+    publishJob('job_cancelled', job)
     count++
   }
   res.json({ ok: true, cancelled: count })
 })
 
+// --- Pause / resume all ---
+// { immediate: true } : les étapes en cours sont interrompues et refaites à la reprise
+
+router.post('/pause-all', (req, res) => {
+  const immediate = req.body?.immediate === true
+  let count = 0
+  for (const job of jobs.values()) {
+    if (!inSession(job, req.sessionId)) continue
+    if (pauseJob(job, { immediate })) count++
+  }
+  processNext()
+  res.json({ ok: true, paused: count })
+})
+
+router.post('/resume-all', (req, res) => {
+  let count = 0
+  for (const job of jobs.values()) {
+    if (!inSession(job, req.sessionId)) continue
+    if (resumeJob(job)) count++
+  }
+  processNext()
+  res.json({ ok: true, resumed: count })
+})
+
 // --- Create jobs ---
 
+/** { colorize: ['ddcolor', 'siggraph17'] | 'all' } → models to compare, per step (2 at least) */
+function normalizeCompare(compare, validSteps) {
+  if (!compare || typeof compare !== 'object') return null
+  const out = {}
+  for (const step of validSteps) {
+    const models = STEPS[step].models
+    if (!models || !compare[step]) continue
+    const wanted = compare[step] === 'all' ? Object.keys(models) : compare[step]
+    if (!Array.isArray(wanted)) continue
+    const valid = [...new Set(wanted)].filter(m => models[m])
+    if (valid.length >= 2) out[step] = valid
+  }
+  return Object.keys(out).length ? out : null
+}
+
+/** Comparison of the waiting step still waiting for its winner, if any */
+function pendingComparison(job) {
+  const last = job.stepResults[job.stepResults.length - 1]
+  return last?.comparison && !last.result && last.step === job.waitingStep ? last : null
+}
+
 router.post('/', express.json({ limit: '50mb' }), (req, res) => {
-  const { photoIds, steps, options, masks, cropRects } = req.body
+  const { photoIds, masks, cropRects, presetId } = req.body
+  const branches = normalizeBranches(req.body.branches)
+
+  // Un préréglage fournit étapes, modèles et paramètres ; ce qui est envoyé explicitement l'emporte
+  const preset = presetId ? presets.get(presetId) : null
+  if (presetId && !preset) return res.status(404).json({ error: 'Preset not found' })
+  const steps = req.body.steps?.length ? req.body.steps : preset?.steps
+  const options = { ...preset?.options, ...req.body.options }
 
   if (!isAiReady()) {
     const msg = isSetupRunning()
@@ -60,19 +124,29 @@ router.post('/', express.json({ limit: '50mb' }), (req, res) => {
     return res.status(503).json({ error: msg })
   }
 
-  if (!photoIds?.length || !steps?.length) {
+  if (!photoIds?.length || (!steps?.length && !branches)) {
     return res.status(400).json({ error: 'photoIds and steps are required' })
   }
 
-  const validSteps = steps.filter((s) => STEPS[s])
-  if (!validSteps.length) {
+  // Avec des branches, le tronc peut être vide (variantes directement depuis l'original)
+  const validSteps = (steps || []).filter((s) => STEPS[s])
+  if (!validSteps.length && !branches) {
     return res.status(400).json({ error: 'No valid steps provided' })
   }
 
+  const compare = normalizeCompare(req.body.compare, validSteps)
+  const params = {}
+  for (const step of validSteps) {
+    const merged = { ...preset?.params?.[step], ...req.body.params?.[step] }
+    if (Object.keys(merged).length) params[step] = merged
+  }
+
+  // Lot : les jobs de cette requête, pour le webhook batch_finished
+  const batchId = randomUUID()
   const created = []
   for (const photoId of photoIds) {
     const photo = photos.get(photoId)
-    if (!photo) continue
+    if (!photo || !inSession(photo, req.sessionId)) continue
 
     // If inpaint step is selected and a mask was provided, save it
     let maskPath = null
@@ -91,13 +165,21 @@ router.post('/', express.json({ limit: '50mb' }), (req, res) => {
 
     const job = {
       id: randomUUID(),
+      sessionId: req.sessionId,
       photoId,
       photoName: photo.originalName,
       original: `/uploads/${photo.filename}`,
       steps: validSteps,
-      options: options || {},
+      options,
+      params: normalizeParams(params, validSteps) || {},
+      presetId: preset?.id || null,
+      batchId,
+      compare,
+      branches,
       maskPath,
       cropRect,
+      // Détaché : survit à la fermeture de l'onglet, quel que soit le réglage du heartbeat
+      detached: req.body.detached === true,
       status: 'pending',
       progress: 0,
       createdAt: new Date().toISOString(),
@@ -106,6 +188,7 @@ router.post('/', express.json({ limit: '50mb' }), (req, res) => {
       priority: Date.now(),
     }
     jobs.set(job.id, job)
+    publishJob('job_created', job)
     created.push(job)
 
     enqueueJob(job)
@@ -116,48 +199,75 @@ router.post('/', express.json({ limit: '50mb' }), (req, res) => {
 
 // --- List jobs ---
 
-router.get('/', (_req, res) => {
-  touchHeartbeat()
-  const all = [...jobs.values()]
-  const statusOrder = { waiting_input: -1, processing: 0, pending: 1, completed: 2, failed: 2, cancelled: 2 }
+router.get('/', (req, res) => {
+  const all = [...jobs.values()].filter(j => inSession(j, req.sessionId))
+  const statusOrder = { waiting_input: -1, processing: 0, pending: 1, paused: 2, completed: 3, failed: 3, cancelled: 3 }
   all.sort((a, b) => {
-    const sa = statusOrder[a.status] ?? 3
-    const sb = statusOrder[b.status] ?? 3
+    const sa = statusOrder[a.status] ?? 4
+    const sb = statusOrder[b.status] ?? 4
     if (sa !== sb) return sa - sb
     if (a.status === 'pending' && b.status === 'pending') {
       return (a.priority || 0) - (b.priority || 0)
     }
     return new Date(b.createdAt) - new Date(a.createdAt)
   })
-  res.json(all.map((job) => {
-    const { maskPath, cropRect, currentInputPath, ...rest } = job
-    if (rest.status === 'waiting_input' && rest.resumeFromStep != null) {
-      rest.canGoBack = false
-      for (let i = rest.resumeFromStep - 1; i >= 0; i--) {
-        if (MANUAL_STEPS.has(job.steps[i])) { rest.canGoBack = true; break }
-      }
-    }
-    return rest
-  }))
+  res.json(all.map(toPublicJob))
+})
+
+// --- Export ZIP ---
+// ?ids=a,b,c (défaut : tous les jobs terminés) &intermediates=1 (images de chaque étape) &original=0
+
+router.get('/export', async (req, res) => {
+  const ids = typeof req.query.ids === 'string' ? req.query.ids.split(',').filter(Boolean) : null
+  const list = ids
+    ? ids.map(id => jobs.get(id)).filter(j => j && inSession(j, req.sessionId))
+    : [...jobs.values()].filter(j => j.status === 'completed' && inSession(j, req.sessionId)).sort((a, b) => new Date(a.createdAt) - new Date(b.createdAt))
+  if (!list.length) return res.status(404).json({ error: 'No jobs to export' })
+
+  const stamp = new Date().toISOString().slice(0, 19).replace(/[:T]/g, '-')
+  res.setHeader('Content-Type', 'application/zip')
+  res.setHeader('Content-Disposition', `attachment; filename="oldphotos_${stamp}.zip"`)
+  try {
+    await writeExport(list, res, {
+      intermediates: req.query.intermediates === '1' || req.query.intermediates === 'true',
+      original: req.query.original !== '0' && req.query.original !== 'false',
+    })
+  } catch (err) {
+    // En-têtes déjà partis : on ne peut plus répondre en JSON, on coupe la connexion
+    console.error('Export interrompu :', err.message)
+    res.destroy()
+  }
 })
 
 // --- Get single job ---
 
 router.get('/:id', (req, res) => {
-  const job = jobs.get(req.params.id)
+  const job = ownJob(req)
   if (!job) return res.status(404).json({ error: 'Job not found' })
-  res.json(job)
+  res.json(toPublicJob(job))
 })
 
 // --- Submit input for a waiting job ---
 
 router.post('/:id/input', express.json({ limit: '50mb' }), (req, res) => {
-  const job = jobs.get(req.params.id)
+  const job = ownJob(req)
   if (!job || job.status !== 'waiting_input') {
     return res.status(400).json({ error: 'Job is not waiting for input' })
   }
 
-  const { mask, cropRect } = req.body
+  const { mask, cropRect, choice } = req.body
+
+  const comparison = pendingComparison(job)
+  if (comparison) {
+    const chosen = comparison.comparison.find(c => c.model === choice && c.result)
+    if (!chosen) return res.status(400).json({ error: 'choice must be one of the compared models' })
+    const { error, ...report } = chosen
+    Object.assign(comparison, report, { chosen: chosen.model })
+    // Le gagnant devient le modèle de l'étape (branches, relances)
+    job.options = { ...job.options, [comparison.step]: chosen.model }
+    job.currentInputPath = path.join(RESULTS_DIR, path.basename(chosen.result))
+    job.resumeFromStep = (job.resumeFromStep || 0) + 1
+  }
 
   if (job.waitingStep === 'inpaint' && mask) {
     const maskId = randomUUID().slice(0, 8)
@@ -180,11 +290,13 @@ router.post('/:id/input', express.json({ limit: '50mb' }), (req, res) => {
 // --- Skip current manual step ---
 
 router.post('/:id/skip', express.json(), (req, res) => {
-  const job = jobs.get(req.params.id)
+  const job = ownJob(req)
   if (!job || job.status !== 'waiting_input') {
     return res.status(400).json({ error: 'Job is not waiting for input' })
   }
 
+  // Comparaison sans choix : l'étape est sautée, l'entrée reste inchangée
+  if (pendingComparison(job)) job.stepResults.pop()
   job.resumeFromStep = (job.resumeFromStep || 0) + 1
   job.waitingStep = null
   job.waitingImage = null
@@ -195,7 +307,7 @@ router.post('/:id/skip', express.json(), (req, res) => {
 // --- Go back to previous manual step ---
 
 router.post('/:id/back', express.json(), (req, res) => {
-  const job = jobs.get(req.params.id)
+  const job = ownJob(req)
   if (!job || job.status !== 'waiting_input') {
     return res.status(400).json({ error: 'Job is not waiting for input' })
   }
@@ -225,12 +337,15 @@ router.post('/:id/back', express.json(), (req, res) => {
   }
 
   // Trim step results to before targetIdx
+  if (pendingComparison(job)) job.stepResults.pop()
   job.stepResults = job.stepResults.slice(0, targetIdx)
 
   // Determine input for targetIdx
   if (job.stepResults.length > 0) {
     const lastResult = job.stepResults[job.stepResults.length - 1]
     job.currentInputPath = path.join(ROOT, lastResult.result.replace(/^\//, ''))
+  } else if (job.inputPath) {
+    job.currentInputPath = job.inputPath
   } else {
     const photo = photos.get(job.photoId)
     job.currentInputPath = photo ? path.join(UPLOADS_DIR, photo.filename) : null
@@ -246,7 +361,7 @@ router.post('/:id/back', express.json(), (req, res) => {
 // --- Retry failed step ---
 
 router.post('/:id/retry', express.json(), (req, res) => {
-  const job = jobs.get(req.params.id)
+  const job = ownJob(req)
   if (!job || job.status !== 'failed') {
     return res.status(400).json({ error: 'Job is not in failed state' })
   }
@@ -255,6 +370,7 @@ router.post('/:id/retry', express.json(), (req, res) => {
   if (model && job.failedStep) {
     job.options = job.options || {}
     job.options[job.failedStep] = model
+    if (job.compare) delete job.compare[job.failedStep]
   }
 
   job.resumeFromStep = job.failedStepIndex ?? 0
@@ -269,7 +385,7 @@ router.post('/:id/retry', express.json(), (req, res) => {
 // --- Skip failed step ---
 
 router.post('/:id/skip-failed', express.json(), (req, res) => {
-  const job = jobs.get(req.params.id)
+  const job = ownJob(req)
   if (!job || job.status !== 'failed') {
     return res.status(400).json({ error: 'Job is not in failed state' })
   }
@@ -281,10 +397,14 @@ router.post('/:id/skip-failed', express.json(), (req, res) => {
 
   if (nextIndex >= job.steps.length) {
     job.status = 'completed'
+    job.completedAt = new Date().toISOString()
     job.progress = 100
     job.result = job.stepResults.length > 0
       ? job.stepResults[job.stepResults.length - 1].result
-      : null
+      : job.inputPath ? getUrlForPath(job.inputPath) : null
+    spawnBranches(job)
+    publishJob('job_completed', job)
+    processNext()
   } else {
     job.resumeFromStep = nextIndex
     job.status = 'processing'
@@ -293,13 +413,39 @@ router.post('/:id/skip-failed', express.json(), (req, res) => {
   res.json({ ok: true })
 })
 
+// --- Pin: the final result is never removed by the disk quota nor by cleanup ---
+
+function setPinned(req, res, pinned) {
+  const job = ownJob(req)
+  if (!job) return res.status(404).json({ error: 'Job not found' })
+  job.pinned = pinned
+  publishJob('job_updated', job)
+  res.json(toPublicJob(job))
+}
+
+router.post('/:id/pin', (req, res) => setPinned(req, res, true))
+router.delete('/:id/pin', (req, res) => setPinned(req, res, false))
+
+// --- Detach (keeps running without a frontend) ---
+
+function setDetached(req, res, detached) {
+  const job = ownJob(req)
+  if (!job) return res.status(404).json({ error: 'Job not found' })
+  job.detached = detached
+  publishJob('job_updated', job)
+  res.json(toPublicJob(job))
+}
+
+router.post('/:id/detach', (req, res) => setDetached(req, res, true))
+router.delete('/:id/detach', (req, res) => setDetached(req, res, false))
+
 // --- Cancel a job ---
 
 router.post('/:id/cancel', (req, res) => {
-  const job = jobs.get(req.params.id)
+  const job = ownJob(req)
   if (!job) return res.status(404).json({ error: 'Job not found' })
 
-  const cancellable = ['pending', 'processing', 'waiting_input']
+  const cancellable = ['pending', 'processing', 'waiting_input', 'paused']
   if (!cancellable.includes(job.status)) {
     return res.status(400).json({ error: 'Job cannot be cancelled' })
   }
@@ -311,12 +457,33 @@ router.post('/:id/cancel', (req, res) => {
 
   const proc = runningProcs.get(job.id)
   if (proc) {
-    proc.kill('SIGTERM')
+    killProc(proc)
     runningProcs.delete(job.id)
   }
+  publishJob('job_cancelled', job)
 
   processNext()
   res.json({ ok: true })
 })
 
+// --- Pause / resume ---
+
+router.post('/:id/pause', (req, res) => {
+  const job = ownJob(req)
+  if (!job) return res.status(404).json({ error: 'Job not found' })
+  if (!pauseJob(job, { immediate: req.body?.immediate === true })) {
+    return res.status(400).json({ error: 'Job cannot be paused' })
+  }
+  processNext()
+  res.json(toPublicJob(job))
+})
+
+router.post('/:id/resume', (req, res) => {
+  const job = ownJob(req)
+  if (!job) return res.status(404).json({ error: 'Job not found' })
+  if (!resumeJob(job)) return res.status(400).json({ error: 'Job is not paused' })
+  processNext()
+  res.json(toPublicJob(job))
+})
+
 export default router
@@ -0,0 +1,36 @@
+import { test } from 'node:test'
+import assert from 'node:assert/strict'
+import { mkdtempSync } from 'fs'
+import os from 'os'
+import path from 'path'
+
+// Sans dossiers du dépôt : avant le premier import de config.js
+const dir = mkdtempSync(path.join(os.tmpdir(), 'oldphotos-test-'))
+process.env.UPLOADS_DIR = path.join(dir, 'uploads')
+process.env.RESULTS_DIR = path.join(dir, 'results')
+
+const { default: express } = await import('express')
+const { default: jobsRouter } = await import('./jobs.js')
+const { jobs } = await import('../storage.js')
+
+test('GET /api/jobs/:id does not expose internal paths or the session', async (t) => {
+  const app = express()
+  app.use((req, _res, next) => { req.sessionId = 'alice'; next() })
+  app.use('/api/jobs', jobsRouter)
+  const server = app.listen(0)
+  t.after(() => server.close())
+  await new Promise(resolve => server.once('listening', resolve))
+
+  jobs.set('job-1', {
+    id: 'job-1', sessionId: 'alice', photoId: 'photo-1', status: 'waiting_input', steps: ['crop'],
+    resumeFromStep: 0, inputPath: path.join(dir, 'uploads/in.png'), currentInputPath: path.join(dir, 'uploads/in.png'),
+    maskPath: path.join(dir, 'uploads/mask.png'), cropRect: { x: 0, y: 0, w: 1, h: 1 }, results: [],
+  })
+  const res = await fetch(`http://127.0.0.1:${server.address().port}/api/jobs/job-1`)
+  assert.equal(res.status, 200)
+  const job = await res.json()
+  assert.equal(job.id, 'job-1')
+  for (const key of ['inputPath', 'currentInputPath', 'maskPath', 'cropRect', 'sessionId']) {
+    assert.equal(key in job, false, key)
+  }
+})
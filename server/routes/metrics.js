@@ -0,0 +1,11 @@
+import { Router } from 'express'
+import { renderMetrics } from '../metrics.js'
+
+const router = Router()
+
+// Format texte Prometheus ; derrière l'authentification comme l'API (jeton Bearer dans le scrape)
+router.get('/', (_req, res) => {
+  res.type('text/plain; version=0.0.4').send(renderMetrics())
+})
+
+export default router
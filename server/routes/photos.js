@@ -1,50 +1,144 @@
 import { Router } from 'express'
 import { randomUUID } from 'crypto'
-import { existsSync, copyFileSync } from 'fs'
+import { existsSync, copyFileSync, unlinkSync } from 'fs'
 import path from 'path'
-import { UPLOADS_DIR, RESULTS_DIR } from '../config.js'
-import { photos } from '../storage.js'
+import { UPLOADS_DIR, RESULTS_DIR, MAX_ARCHIVE_ENTRIES } from '../config.js'
+import { photos, jobs, savePhoto } from '../storage.js'
 import { upload } from '../upload.js'
+import { isImageFile, MAX_IMAGE_SIZE } from '../images.js'
+import { archiveKind, extractArchive } from '../archive.js'
+import { inSession } from '../sessions.js'
+import { jobFiles } from '../cleanup.js'
+import { scheduleQuotaCheck } from '../quota.js'
 import { sanitizeFilename } from '../utils.js'
 import { runPythonStep } from '../python.js'
+import { getStepLimits } from '../limits.js'
 
 const router = Router()
 
+/** `file` is the original of one of the session's photos, or an input or output of one of its jobs */
+function fileInSession(file, sessionId) {
+  const target = path.resolve(file)
+  const same = (p) => !!p && path.resolve(p) === target
+  for (const photo of photos.values()) {
+    if (inSession(photo, sessionId) && same(path.join(UPLOADS_DIR, photo.filename))) return true
+  }
+  for (const job of jobs.values()) {
+    if (inSession(job, sessionId) && [job.inputPath, job.currentInputPath, ...jobFiles(job)].some(same)) return true
+  }
+  return false
+}
+
+function addPhoto(sessionId, filename, originalName, album = null) {
+  const photo = {
+    id: randomUUID(),
+    sessionId,
+    filename,
+    originalName,
+    ...(album ? { album } : {}),
+    uploadedAt: new Date().toISOString(),
+  }
+  photos.set(photo.id, photo)
+  return photo
+}
+
+// Métadonnées ajoutées par macOS / Windows dans les archives : ignorées sans les signaler
+const ARCHIVE_JUNK = /(^|\/)(__MACOSX\/|\._|\.DS_Store$|Thumbs\.db$|desktop\.ini$)/i
+
+/**
+ * Unpack the images of an album archive. The folders inside the archive become the
+ * `album` of each photo ("1953/Vacances"); images at the root take the archive name.
+ */
+async function importArchive(sessionId, file, kind) {
+  const archiveName = file.originalname.replace(/\.(zip|tar|tar\.gz|tgz)$/i, '')
+  const entries = []
+  const { extracted, rejected } = await extractArchive(file.path, kind, ({ name }) => {
+    const clean = name.replace(/\\/g, '/')
+    if (ARCHIVE_JUNK.test(clean)) return null
+    if (archiveKind(clean)) return { reason: 'Archive imbriquée non prise en charge' }
+    if (!isImageFile(clean)) return { reason: 'Format non pris en charge' }
+    const folders = clean.split('/').filter(p => p && p !== '.' && p !== '..')
+    const baseName = folders.pop()
+    const dest = path.join(UPLOADS_DIR, `${randomUUID()}${path.extname(baseName).toLowerCase()}`)
+    entries.push({ dest, baseName, album: folders.length ? folders.join('/') : archiveName })
+    return dest
+  }, { maxEntrySize: MAX_IMAGE_SIZE, maxEntries: MAX_ARCHIVE_ENTRIES })
+
+  const written = new Set(extracted.map(e => e.path))
+  const created = entries
+    .filter(e => written.has(e.dest))
+    .map(e => addPhoto(sessionId, path.basename(e.dest), e.baseName, e.album))
+  return { photos: created, rejected: rejected.map(r => ({ ...r, archive: file.originalname })) }
+}
+
+// Images isolées et/ou archives d'albums (.zip, .tar, .tar.gz) → { photos, rejected }
 router.post('/', (req, res) => {
-  upload.array('photos', 20)(req, res, (err) => {
+  upload.array('photos', 20)(req, res, async (err) => {
     if (err) {
       console.error('Upload error:', err.message)
       return res.status(400).json({ error: err.message })
     }
     const uploaded = []
-    for (const file of req.files) {
-      const photo = {
-        id: randomUUID(),
-        filename: file.filename,
-        originalName: file.originalname,
-        uploadedAt: new Date().toISOString(),
+    const rejected = [...(req.rejectedFiles || [])]
+    for (const file of req.files || []) {
+      const kind = archiveKind(file.originalname)
+      if (!kind) {
+        // Réception arrêtée au-delà de MAX_IMAGE_SIZE, fichier déjà supprimé (upload.js)
+        if (file.tooLarge) {
+          rejected.push({ name: file.originalname, reason: `Fichier trop volumineux (plus de ${MAX_IMAGE_SIZE / 1024 / 1024} Mo)` })
+          continue
+        }
+        uploaded.push(addPhoto(req.sessionId, file.filename, file.originalname))
+        continue
+      }
+      try {
+        const result = await importArchive(req.sessionId, file, kind)
+        uploaded.push(...result.photos)
+        rejected.push(...result.rejected)
+        console.log(`Archive ${file.originalname} : ${result.photos.length} photo(s), ${result.rejected.length} refusée(s)`)
+      } catch (archiveErr) {
+        rejected.push({ name: file.originalname, reason: archiveErr.message })
+      } finally {
+        try { unlinkSync(file.path) } catch {}
       }
-      photos.set(photo.id, photo)
-      uploaded.push(photo)
     }
-    res.json(uploaded)
+    scheduleQuotaCheck()
+    // Réponse historique : le tableau des photos ; ?rejected=1 pour avoir aussi les fichiers refusés
+    res.json(req.query.rejected === '1' ? { photos: uploaded, rejected } : uploaded)
   })
 })
 
-router.get('/', (_req, res) => {
-  res.json([...photos.values()])
+router.get('/', (req, res) => {
+  res.json([...photos.values()].filter(p => inSession(p, req.sessionId)))
 })
 
 router.delete('/:id', (req, res) => {
-  photos.delete(req.params.id)
+  const photo = photos.get(req.params.id)
+  if (photo && inSession(photo, req.sessionId)) photos.delete(photo.id)
   res.json({ ok: true })
 })
 
-router.delete('/', (_req, res) => {
-  photos.clear()
+// Vide la liste de la session uniquement
+router.delete('/', (req, res) => {
+  for (const photo of [...photos.values()]) {
+    if (inSession(photo, req.sessionId)) photos.delete(photo.id)
+  }
   res.json({ ok: true })
 })
 
+// --- Pin: never removed by the disk quota nor by cleanup ---
+
+function setPinned(req, res, pinned) {
+  const photo = photos.get(req.params.id)
+  if (!photo || !inSession(photo, req.sessionId)) return res.status(404).json({ error: 'Photo not found' })
+  photo.pinned = pinned
+  savePhoto(photo)
+  res.json(photo)
+}
+
+router.post('/:id/pin', (req, res) => setPinned(req, res, true))
+router.delete('/:id/pin', (req, res) => setPinned(req, res, false))
+
 // --- Import result as new photo ---
 
 router.post('/import', (req, res) => {
@@ -64,7 +158,10 @@ router.post('/import', (req, res) => {
                    !path.resolve(srcFile).startsWith(path.resolve(UPLOADS_DIR)))) {
     return res.status(403).json({ error: 'Access denied' })
   }
-  if (!existsSync(srcFile)) return res.status(404).json({ error: 'File not found' })
+  // Comme les autres routes : seulement les fichiers de la session, introuvables sinon
+  if (!fileInSession(srcFile, req.sessionId) || !existsSync(srcFile)) {
+    return res.status(404).json({ error: 'File not found' })
+  }
 
   const ext = path.extname(srcFile)
   const newFilename = `${randomUUID()}${ext}`
@@ -74,6 +171,7 @@ router.post('/import', (req, res) => {
   const originalName = path.basename(srcFile)
   const photo = {
     id: randomUUID(),
+    sessionId: req.sessionId,
     filename: newFilename,
     originalName,
     uploadedAt: new Date().toISOString(),
@@ -86,7 +184,7 @@ router.post('/import', (req, res) => {
 
 router.post('/:id/crop', async (req, res) => {
   const photo = photos.get(req.params.id)
-  if (!photo) return res.status(404).json({ error: 'Photo not found' })
+  if (!photo || !inSession(photo, req.sessionId)) return res.status(404).json({ error: 'Photo not found' })
 
   const { cropRect } = req.body
   if (!cropRect) return res.status(400).json({ error: 'cropRect is required' })
@@ -96,11 +194,12 @@ router.post('/:id/crop', async (req, res) => {
   const outputPath = path.join(UPLOADS_DIR, newFilename)
 
   try {
-    await runPythonStep('crop.py', [inputPath, outputPath, cropRect])
+    await runPythonStep('crop.py', [inputPath, outputPath, cropRect], null, getStepLimits('crop'))
 
     const baseName = sanitizeFilename(photo.originalName.replace(/\.[^.]+$/, ''))
     const croppedPhoto = {
       id: randomUUID(),
+      sessionId: req.sessionId,
       filename: newFilename,
       originalName: `${baseName}_crop.png`,
       uploadedAt: new Date().toISOString(),
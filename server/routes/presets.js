@@ -0,0 +1,120 @@
+import { Router } from 'express'
+import express from 'express'
+import { randomUUID } from 'crypto'
+import { presets } from '../storage.js'
+import { STEPS, normalizeParams } from '../steps/index.js'
+
+const router = Router()
+
+// Préréglage = sélection d'étapes réutilisable :
+//   { name: 'Archive', steps: ['spot_removal', 'upscale'], options: { upscale: 'x2plus' }, params: { upscale: { scale: 2 } } }
+// Les étapes inconnues ou désactivées sur ce serveur sont ignorées (import depuis une autre installation).
+
+const EXPORT_VERSION = 1
+
+/** Validate a preset body; returns { error } or the clean fields */
+function normalizePreset(body) {
+  if (!body || typeof body !== 'object') return { error: 'Invalid preset' }
+  const name = typeof body.name === 'string' ? body.name.trim().slice(0, 80) : ''
+  if (!name) return { error: 'name is required' }
+
+  const wanted = Array.isArray(body.steps) ? body.steps : []
+  const steps = Object.keys(STEPS).filter(s => wanted.includes(s))
+  if (!steps.length) return { error: 'No valid steps provided' }
+
+  const options = {}
+  for (const step of steps) {
+    const model = body.options?.[step]
+    if (model && STEPS[step].models?.[model]) options[step] = model
+  }
+
+  return {
+    name,
+    description: typeof body.description === 'string' ? body.description.trim().slice(0, 500) : '',
+    steps,
+    options,
+    params: normalizeParams(body.params, steps) || {},
+  }
+}
+
+function findByName(name) {
+  const lower = name.toLowerCase()
+  return [...presets.values()].find(p => p.name.toLowerCase() === lower)
+}
+
+function savePreset(fields, existing) {
+  const now = new Date().toISOString()
+  const preset = existing
+    ? { ...existing, ...fields, updatedAt: now }
+    : { id: randomUUID(), ...fields, createdAt: now, updatedAt: now }
+  presets.set(preset.id, preset)
+  return preset
+}
+
+function sortedPresets() {
+  return [...presets.values()].sort((a, b) => a.name.localeCompare(b.name))
+}
+
+router.get('/', (_req, res) => {
+  res.json(sortedPresets())
+})
+
+// Partage entre postes : fichier JSON sans identifiants, réimportable tel quel
+router.get('/export', (req, res) => {
+  const ids = typeof req.query.ids === 'string' ? req.query.ids.split(',') : null
+  const list = sortedPresets().filter(p => !ids || ids.includes(p.id))
+  res.setHeader('Content-Disposition', 'attachment; filename="presets.json"')
+  res.json({
+    version: EXPORT_VERSION,
+    exportedAt: new Date().toISOString(),
+    presets: list.map(({ name, description, steps, options, params }) => ({ name, description, steps, options, params })),
+  })
+})
+
+// Accepte le format d'export ou un simple tableau ; un préréglage de même nom est remplacé
+router.post('/import', express.json({ limit: '5mb' }), (req, res) => {
+  const list = Array.isArray(req.body) ? req.body : req.body?.presets
+  if (!Array.isArray(list)) return res.status(400).json({ error: 'presets array is required' })
+
+  const imported = []
+  const rejected = []
+  for (const entry of list) {
+    const fields = normalizePreset(entry)
+    if (fields.error) {
+      rejected.push({ name: entry?.name ?? null, error: fields.error })
+      continue
+    }
+    imported.push(savePreset(fields, findByName(fields.name)))
+  }
+  res.json({ imported, rejected })
+})
+
+router.get('/:id', (req, res) => {
+  const preset = presets.get(req.params.id)
+  if (!preset) return res.status(404).json({ error: 'Preset not found' })
+  res.json(preset)
+})
+
+router.post('/', express.json(), (req, res) => {
+  const fields = normalizePreset(req.body)
+  if (fields.error) return res.status(400).json({ error: fields.error })
+  if (findByName(fields.name)) return res.status(409).json({ error: 'A preset with this name already exists' })
+  res.status(201).json(savePreset(fields))
+})
+
+router.put('/:id', express.json(), (req, res) => {
+  const existing = presets.get(req.params.id)
+  if (!existing) return res.status(404).json({ error: 'Preset not found' })
+  const fields = normalizePreset({ ...existing, ...req.body })
+  if (fields.error) return res.status(400).json({ error: fields.error })
+  const clash = findByName(fields.name)
+  if (clash && clash.id !== existing.id) return res.status(409).json({ error: 'A preset with this name already exists' })
+  res.json(savePreset(fields, existing))
+})
+
+router.delete('/:id', (req, res) => {
+  if (!presets.delete(req.params.id)) return res.status(404).json({ error: 'Preset not found' })
+  res.json({ ok: true })
+})
+
+export default router
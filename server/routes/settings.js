@@ -3,20 +3,51 @@ import express from 'express'
 import { MAX_CONCURRENT_LIMIT } from '../config.js'
 import { maxConcurrent, setMaxConcurrent } from '../queue.js'
 import { processNext } from '../queue.js'
+import { getAllStepLimits, setStepLimits } from '../limits.js'
+import { getHeartbeatAction, setHeartbeatAction } from '../heartbeat.js'
+import { getWebhooks, setWebhooks, toPublicWebhook, WEBHOOK_EVENTS } from '../webhooks.js'
+import { authEnabled, ADMIN_USER } from '../auth.js'
 
 const router = Router()
 
-router.get('/', (_req, res) => {
-  res.json({ maxConcurrent, maxConcurrentLimit: MAX_CONCURRENT_LIMIT })
+// Réglages de toute l'instance : modifiables par l'admin seul quand l'authentification est active
+function canEdit(req) {
+  return !authEnabled() || req.user === ADMIN_USER
+}
+
+/** Settings as returned by the API; `revealed`: webhooks whose new secret is shown this once */
+function currentSettings(req, revealed = new Set()) {
+  return {
+    editable: canEdit(req),
+    maxConcurrent, maxConcurrentLimit: MAX_CONCURRENT_LIMIT, stepLimits: getAllStepLimits(),
+    heartbeatAction: getHeartbeatAction(),
+    webhooks: getWebhooks().map(h => revealed.has(h.id) ? h : toPublicWebhook(h)), webhookEvents: WEBHOOK_EVENTS,
+  }
+}
+
+router.get('/', (req, res) => {
+  res.json(currentSettings(req))
 })
 
 router.put('/', express.json(), (req, res) => {
+  if (!canEdit(req)) return res.status(403).json({ error: 'Settings are restricted to the admin' })
+  // Liste complète des webhooks [{ id?, url, events?, secret? }], remplace l'existante
+  let revealed
+  if ('webhooks' in req.body) {
+    const result = setWebhooks(req.body.webhooks)
+    if (result.error) return res.status(400).json({ error: result.error })
+    revealed = result.created
+  }
   const val = req.body.maxConcurrent
   if (typeof val === 'number' && val >= 1 && val <= MAX_CONCURRENT_LIMIT) {
     setMaxConcurrent(Math.round(val))
   }
+  // { upscale: { timeoutSeconds: 3600, memoryLimitMb: null } } — null revient à l'env/défaut
+  if (req.body.stepLimits) setStepLimits(req.body.stepLimits)
+  // 'cancel' | 'pause' | 'none' : sort des jobs quand le frontend disparaît
+  if ('heartbeatAction' in req.body) setHeartbeatAction(req.body.heartbeatAction)
   processNext()
-  res.json({ maxConcurrent, maxConcurrentLimit: MAX_CONCURRENT_LIMIT })
+  res.json(currentSettings(req, revealed))
 })
 
 export default router
@@ -0,0 +1,44 @@
+import { test } from 'node:test'
+import assert from 'node:assert/strict'
+import { mkdtempSync } from 'fs'
+import os from 'os'
+import path from 'path'
+
+// Authentification active, sans dossiers du dépôt : avant le premier import de config.js
+const dir = mkdtempSync(path.join(os.tmpdir(), 'oldphotos-test-'))
+process.env.UPLOADS_DIR = path.join(dir, 'uploads')
+process.env.RESULTS_DIR = path.join(dir, 'results')
+process.env.AUTH_PASSWORD = 'secret'
+process.env.AUTH_USERS_FILE = ''
+
+const { default: express } = await import('express')
+const { default: settingsRouter } = await import('./settings.js')
+const { maxConcurrent } = await import('../queue.js')
+
+test('only the admin can change the instance settings when auth is on', async (t) => {
+  const app = express()
+  // requireAuth simulé : l'utilisateur vient d'un en-tête
+  app.use((req, _res, next) => { req.user = req.get('X-Test-User'); next() })
+  app.use('/api/settings', settingsRouter)
+  const server = app.listen(0)
+  t.after(() => server.close())
+  await new Promise(resolve => server.once('listening', resolve))
+  const url = `http://127.0.0.1:${server.address().port}/api/settings`
+  const put = (user, body) => fetch(url, {
+    method: 'PUT',
+    headers: { 'Content-Type': 'application/json', 'X-Test-User': user },
+    body: JSON.stringify(body),
+  })
+
+  const before = maxConcurrent
+  for (const body of [{ maxConcurrent: 1 }, { heartbeatAction: 'none' }, { stepLimits: {} }, { webhooks: [] }]) {
+    assert.equal((await put('alice', body)).status, 403, JSON.stringify(body))
+  }
+  const settings = await (await fetch(url, { headers: { 'X-Test-User': 'alice' } })).json()
+  assert.equal(settings.editable, false)
+  assert.equal(settings.maxConcurrent, before)
+
+  const res = await put('admin', { maxConcurrent: 1 })
+  assert.equal(res.status, 200)
+  assert.equal((await res.json()).maxConcurrent, 1)
+})
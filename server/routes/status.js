@@ -3,31 +3,38 @@ import path from 'path'
 import { UPLOADS_DIR, AI_DIR } from '../config.js'
 import { photos } from '../storage.js'
 import { STEPS } from '../steps/index.js'
-import { isAiReady, isSetupRunning, getSetupLog, getSetupError, getPython } from '../python.js'
+import { getStepLimits } from '../limits.js'
+import { inSession } from '../sessions.js'
+import { storageUsage } from '../quota.js'
+import { resourceUsage } from '../resources.js'
+import { watchStatus } from '../watch.js'
+import { queueEta } from '../timings.js'
+import { isAiReady, isSetupRunning, getSetupLog, getSetupError, getPython, getDevice } from '../python.js'
 
 const router = Router()
 
 router.get('/status', async (_req, res) => {
   const aiReady = isAiReady()
   const setupRunning = isSetupRunning()
-  let device = 'cpu'
-  if (aiReady) {
-    try {
-      const { execFileSync } = await import('child_process')
-      device = execFileSync(getPython(), ['-c',
-        'import torch; print("cuda" if torch.cuda.is_available() else "mps" if torch.backends.mps.is_available() else "cpu")'
-      ], { timeout: 10000 }).toString().trim()
-    } catch {}
-  }
+  const device = await getDevice()
   res.json({
     aiReady,
     device,
     setupRunning,
     setupStatus: setupRunning ? getSetupLog() : null,
-    setupError: getSetupError()
+    setupError: getSetupError(),
+    storage: storageUsage(),
+    resources: resourceUsage(),
+    watch: watchStatus(),
+    eta: queueEta(),
   })
 })
 
+// Espace disque seul (sans la détection du device, coûteuse)
+router.get('/storage', (_req, res) => {
+  res.json(storageUsage())
+})
+
 router.get('/steps', (_req, res) => {
   // Build the same shape as old STEP_MODELS — expose metadata without internal fields
   const filtered = {}
@@ -39,7 +46,11 @@ router.get('/steps', (_req, res) => {
       script: step.script,
       ...(step.needsMask ? { needsMask: true } : {}),
       ...(step.models ? { models: step.models, defaultModel: step.defaultModel } : {}),
+      ...(step.params ? { params: step.params } : {}),
+      ...(step.fallbacks ? { fallbacks: step.fallbacks } : {}),
+      ...(step.cost ? { cost: step.cost } : {}),
       ...(step.requiresApiKey ? { requiresApiKey: step.requiresApiKey } : {}),
+      ...getStepLimits(key),
     }
   }
   res.json(filtered)
@@ -47,7 +58,7 @@ router.get('/steps', (_req, res) => {
 
 router.get('/auto-crop/:photoId', async (req, res) => {
   const photo = photos.get(req.params.photoId)
-  if (!photo) return res.status(404).json({ error: 'Photo not found' })
+  if (!photo || !inSession(photo, req.sessionId)) return res.status(404).json({ error: 'Photo not found' })
 
   const inputPath = path.join(UPLOADS_DIR, photo.filename)
   try {
@@ -0,0 +1,60 @@
+import { createHash, randomUUID } from 'crypto'
+import { SESSION_COOKIE } from './config.js'
+import { touchHeartbeat } from './heartbeat.js'
+import { readCookie } from './utils.js'
+
+// Espace de travail par navigateur : photos, jobs, heartbeat et nettoyage sont isolés par session.
+// Ce n'est pas un contrôle d'accès, juste une séparation entre les personnes d'une même instance.
+
+const SESSION_ID = /^[\w-]{4,128}$/
+const COOKIE_MAX_AGE_MS = 365 * 24 * 60 * 60 * 1000
+
+function setSessionCookie(res, id) {
+  res.cookie(SESSION_COOKIE, id, { httpOnly: true, sameSite: 'lax', maxAge: COOKIE_MAX_AGE_MS })
+}
+
+/**
+ * Express middleware setting `req.sessionId`, from (in order) the `X-Session-Id` header
+ * (scripts), a `?session=` link (joins that workspace and keeps it in the cookie),
+ * the session cookie, the API token (one workspace per token), or a new random id sent
+ * back as a cookie.
+ */
+export function sessionMiddleware(req, res, next) {
+  const header = req.get('X-Session-Id')
+  const query = typeof req.query?.session === 'string' ? req.query.session : null
+  let id = header && SESSION_ID.test(header) ? header : null
+
+  if (!id && query && SESSION_ID.test(query)) {
+    id = query
+    setSessionCookie(res, id)
+  }
+  if (!id) {
+    const cookie = readCookie(req, SESSION_COOKIE)
+    id = cookie && SESSION_ID.test(cookie) ? cookie : null
+  }
+  if (!id && req.get('Authorization')) {
+    id = 'api-' + createHash('sha256').update(req.get('Authorization')).digest('hex').slice(0, 24)
+  }
+  if (!id) {
+    id = randomUUID()
+    setSessionCookie(res, id)
+  }
+
+  req.sessionId = id
+  next()
+}
+
+/**
+ * Express middleware for /api, mounted after requireAuth: an authenticated API request is a
+ * sign of life of its session (scripts polling their jobs), like an open SSE stream.
+ * The public healthcheck is not.
+ */
+export function sessionHeartbeat(req, _res, next) {
+  if (req.path !== '/status') touchHeartbeat(req.sessionId)
+  next()
+}
+
+/** Whether a photo or job belongs to the session. Records from before sessions are shared. */
+export function inSession(record, sessionId) {
+  return !record.sessionId || record.sessionId === sessionId
+}
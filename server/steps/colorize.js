@@ -4,14 +4,24 @@ export default {
   repo: 'https://github.com/piddnad/DDColor',
   script: 'colorize_ddcolor.py',
   prefix: 'COL',
+  timeoutSeconds: 10 * 60,
+  memoryLimitMb: 8192,
+  // Les modèles travaillent sur une réduction de l'image : le coût dépend surtout des poids
+  cost: { memoryMb: 2500, memoryMbPerMp: 100, cpus: 2 },
   models: {
     ddcolor: { name: 'DDColor', desc: 'ICCV 2023 - meilleure qualité (~912MB)' },
     deoldify_artistic: { name: 'DeOldify Artistic', desc: 'Couleurs vibrantes, idéal pour portraits (~255MB)' },
     deoldify_stable: { name: 'DeOldify Stable', desc: 'Couleurs conservatrices, plus cohérent (~834MB)' },
-    siggraph17: { name: 'Siggraph17', desc: 'Zhang et al. 2017 - rapide (~130MB)' },
-    eccv16: { name: 'ECCV16', desc: 'Zhang et al. 2016 - classique (~130MB)' },
+    siggraph17: { name: 'Siggraph17', desc: 'Zhang et al. 2017 - rapide (~130MB)', cost: { memoryMb: 800, memoryMbPerMp: 100, cpus: 1 } },
+    eccv16: { name: 'ECCV16', desc: 'Zhang et al. 2016 - classique (~130MB)', cost: { memoryMb: 800, memoryMbPerMp: 100, cpus: 1 } },
   },
   defaultModel: 'ddcolor',
+  fallbacks: {
+    ddcolor: ['siggraph17'],
+    deoldify_artistic: ['siggraph17'],
+    deoldify_stable: ['siggraph17'],
+    siggraph17: ['eccv16'],
+  },
 
   buildArgs({ inputPath, outputPath, job, selectedModel }) {
     if (selectedModel === 'ddcolor') {
@@ -4,6 +4,9 @@ export default {
   repo: '',
   script: 'crop.py',
   prefix: 'CROP',
+  timeoutSeconds: 60,
+  memoryLimitMb: 2048,
+  cost: { memoryMb: 200, memoryMbPerMp: 40, cpus: 1 },
   manual: true,
 
   needsInput(job) {
@@ -4,8 +4,14 @@ export default {
   repo: 'https://github.com/TencentARC/GFPGAN',
   script: 'face_restore.py',
   prefix: 'FACE',
+  timeoutSeconds: 10 * 60,
+  memoryLimitMb: 6144,
+  cost: { memoryMb: 2000, memoryMbPerMp: 150, cpus: 2 },
+  params: {
+    weight: { name: 'Intensité', type: 'number', min: 0, max: 1, step: 0.1, default: 0.5, desc: '0 = fidèle à l\'original, 1 = visage entièrement reconstruit' },
+  },
 
-  buildArgs({ inputPath, outputPath }) {
-    return { script: 'face_restore.py', args: [inputPath, outputPath] }
+  buildArgs({ inputPath, outputPath, params }) {
+    return { script: 'face_restore.py', args: [inputPath, outputPath, String(params.weight)] }
   },
 }
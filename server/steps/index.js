@@ -1,6 +1,12 @@
 // ═══════════════════════════════════════════
 //  ÉTAPES DISPONIBLES — commenter pour désactiver
 // ═══════════════════════════════════════════
+// timeoutSeconds / memoryLimitMb : limites par défaut du script,
+// surchargeables par env ou réglages (voir server/limits.js)
+// fallbacks : { modèle: [replis, dans l'ordre] } essayés quand le modèle échoue
+// cost : { memoryMb, memoryMbPerMp, cpus } estimés pour le budget machine (server/resources.js),
+// surchargeable par modèle (models.x.cost) ; mémoire = memoryMb + memoryMbPerMp × mégapixels
+// outputMegapixels(mp, { model, params }) : taille de la sortie si l'étape la change (estimation des durées)
 import crop from './crop.js'
 import inpaint from './inpaint.js'
 import spot_removal from './spot_removal.js'
@@ -9,6 +15,7 @@ import face_restore from './face_restore.js'
 import colorize from './colorize.js'
 import upscale from './upscale.js'
 import online_restore from './online_restore.js'
+import { STEP_FALLBACKS } from '../config.js'
 
 const ALL_STEPS = {
   crop, inpaint, spot_removal, scratch_removal,
@@ -24,6 +31,68 @@ export const STEPS = Object.fromEntries(
   })
 )
 
+/** Rounds `value` to the nearest multiple of `spec.step` from `spec.min`, then clamps it */
+function snapParam(spec, value) {
+  if (spec.step > 0) {
+    const base = spec.min ?? 0
+    const decimals = (String(spec.step).split('.')[1] || '').length
+    // toFixed : 0.1 * 3 donnerait 0.30000000000000004
+    value = Number((base + Math.round((value - base) / spec.step) * spec.step).toFixed(decimals))
+  }
+  return Math.min(spec.max ?? Infinity, Math.max(spec.min ?? -Infinity, value))
+}
+
+/**
+ * Parameters passed to a step's buildArgs: the declared defaults (`params` on the
+ * step definition) overridden by `values`, snapped to their step and clamped to their min/max.
+ */
+export function resolveParams(step, values) {
+  const out = {}
+  for (const [key, spec] of Object.entries(STEPS[step]?.params || {})) {
+    const value = Number(values?.[key])
+    out[key] = Number.isFinite(value) ? snapParam(spec, value) : spec.default
+  }
+  return out
+}
+
+/**
+ * Why `value` is not accepted as-is for `step.name` (unknown parameter, not a number,
+ * out of range or off its step), or null. resolveParams would clamp/snap it instead.
+ */
+export function paramError(step, name, value) {
+  const spec = STEPS[step]?.params?.[name]
+  if (!spec) return `Paramètre inconnu : ${step}.${name}`
+  // Number(' ') vaut 0
+  const number = typeof value === 'string' && !value.trim() ? NaN : Number(value)
+  if (!Number.isFinite(number)) return `${step}.${name} doit être un nombre : ${value}`
+  if (number < (spec.min ?? -Infinity) || number > (spec.max ?? Infinity)) {
+    return `${step}.${name} hors limites : ${value} (entre ${spec.min} et ${spec.max})`
+  }
+  if (snapParam(spec, number) !== number) return `${step}.${name} doit aller par pas de ${spec.step} : ${value}`
+  return null
+}
+
+/** Keep only declared parameters of the given steps: { upscale: { scale: 4 } } */
+export function normalizeParams(params, steps) {
+  if (!params || typeof params !== 'object') return null
+  const out = {}
+  for (const step of steps) {
+    const specs = STEPS[step]?.params
+    if (!specs || !params[step] || typeof params[step] !== 'object') continue
+    const resolved = resolveParams(step, params[step])
+    const given = Object.keys(specs).filter(k => params[step][k] != null && params[step][k] !== '')
+    if (given.length) out[step] = Object.fromEntries(given.map(k => [k, resolved[k]]))
+  }
+  return Object.keys(out).length ? out : null
+}
+
+/** Models to try, in order, when `model` fails on `step` (empty when fallbacks are off) */
+export function fallbackChain(step, model) {
+  const stepDef = STEPS[step]
+  if (!STEP_FALLBACKS || !model || !stepDef?.fallbacks) return []
+  return (stepDef.fallbacks[model] || []).filter(m => m !== model && stepDef.models?.[m])
+}
+
 export const MANUAL_STEPS = new Set(
   Object.entries(STEPS).filter(([_, s]) => s.manual).map(([k]) => k)
 )
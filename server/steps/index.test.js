@@ -0,0 +1,21 @@
+import { test } from 'node:test'
+import assert from 'node:assert/strict'
+import { resolveParams, normalizeParams, paramError } from './index.js'
+
+test('step parameters are snapped to their step and clamped', () => {
+  assert.deepEqual(resolveParams('upscale', { scale: 2.5 }), { scale: 3 })
+  assert.deepEqual(resolveParams('upscale', { scale: 2.4 }), { scale: 2 })
+  assert.deepEqual(resolveParams('upscale', { scale: 9 }), { scale: 4 })
+  assert.deepEqual(resolveParams('face_restore', { weight: 0.33 }), { weight: 0.3 })
+  assert.deepEqual(resolveParams('face_restore', { weight: 'x' }), { weight: 0.5 })
+  assert.deepEqual(normalizeParams({ upscale: { scale: '3.7' } }, ['upscale']), { upscale: { scale: 4 } })
+})
+
+test('paramError rejects what resolveParams would have to change', () => {
+  assert.equal(paramError('upscale', 'scale', '3'), null)
+  assert.equal(paramError('face_restore', 'weight', '0.3'), null)
+  assert.match(paramError('upscale', 'scale', '2.5'), /pas de 1/)
+  assert.match(paramError('upscale', 'scale', '9'), /hors limites/)
+  assert.match(paramError('upscale', 'scale', ' '), /nombre/)
+  assert.match(paramError('upscale', 'size', '2'), /inconnu/)
+})
@@ -6,6 +6,9 @@ export default {
   repo: 'https://github.com/advimman/lama',
   script: 'inpaint.py',
   prefix: 'INPAINT',
+  timeoutSeconds: 5 * 60,
+  memoryLimitMb: 6144,
+  cost: { memoryMb: 1500, memoryMbPerMp: 400, cpus: 2 },
   manual: true,
   needsMask: true,
 
@@ -11,6 +11,10 @@ export default {
   repo: 'https://platform.openai.com/docs/guides/images',
   script: 'restore_openai.py',
   prefix: 'CLOUD',
+  timeoutSeconds: 5 * 60,
+  memoryLimitMb: 1024,
+  // Le calcul se fait chez OpenAI : seulement l'envoi et la réception de l'image
+  cost: { memoryMb: 150, memoryMbPerMp: 30, cpus: 0.25 },
   models: {
     full: { name: 'Restauration complète', desc: 'Répare + colorise + améliore (tout-en-un)' },
     restore: { name: 'Restauration seule', desc: 'Supprime rayures et taches uniquement' },
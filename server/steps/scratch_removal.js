@@ -4,11 +4,17 @@ export default {
   repo: 'https://github.com/advimman/lama',
   script: 'restore.py',
   prefix: 'REST',
+  timeoutSeconds: 10 * 60,
+  memoryLimitMb: 6144,
+  cost: { memoryMb: 1500, memoryMbPerMp: 400, cpus: 2 },
   models: {
     lama: { name: 'LaMa (IA)', desc: 'Meilleure qualité, plus lent (~200MB)' },
-    opencv: { name: 'OpenCV', desc: 'Rapide, Navier-Stokes' },
+    opencv: { name: 'OpenCV', desc: 'Rapide, Navier-Stokes', cost: { memoryMb: 200, memoryMbPerMp: 80, cpus: 1 } },
   },
   defaultModel: 'lama',
+  fallbacks: {
+    lama: ['opencv'],
+  },
 
   buildArgs({ inputPath, outputPath, job, selectedModel }) {
     return { script: 'restore.py', args: [inputPath, outputPath, selectedModel] }
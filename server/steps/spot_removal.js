@@ -4,11 +4,17 @@ export default {
   repo: 'https://github.com/advimman/lama',
   script: 'clean_spots.py',
   prefix: 'SPOTS',
+  timeoutSeconds: 10 * 60,
+  memoryLimitMb: 6144,
+  cost: { memoryMb: 1500, memoryMbPerMp: 400, cpus: 2 },
   models: {
     lama: { name: 'LaMa (IA)', desc: 'Détection multi-échelle + inpainting IA' },
-    opencv: { name: 'OpenCV', desc: 'Détection multi-échelle + Navier-Stokes (rapide)' },
+    opencv: { name: 'OpenCV', desc: 'Détection multi-échelle + Navier-Stokes (rapide)', cost: { memoryMb: 200, memoryMbPerMp: 80, cpus: 1 } },
   },
   defaultModel: 'lama',
+  fallbacks: {
+    lama: ['opencv'],
+  },
 
   buildArgs({ inputPath, outputPath, job, selectedModel }) {
     return { script: 'clean_spots.py', args: [inputPath, outputPath, selectedModel] }
@@ -4,16 +4,35 @@ export default {
   repo: 'https://github.com/xinntao/Real-ESRGAN',
   script: 'upscale.py',
   prefix: 'UPS',
+  timeoutSeconds: 30 * 60,
+  memoryLimitMb: 8192,
+  // Traitement par tuiles, mais la sortie (×4 en pixels à l'échelle 2) reste en mémoire
+  cost: { memoryMb: 1500, memoryMbPerMp: 600, cpus: 2 },
   models: {
-    compact: { name: 'Real-ESRGAN Compact', desc: 'Rapide, bonne qualité (~1MB)' },
+    compact: { name: 'Real-ESRGAN Compact', desc: 'Rapide, bonne qualité (~1MB)', cost: { memoryMb: 600, memoryMbPerMp: 300, cpus: 2 } },
     x4plus: { name: 'Real-ESRGAN x4plus', desc: 'Polyvalent, meilleure qualité (~64MB)' },
     'x4plus-anime': { name: 'Real-ESRGAN Anime', desc: 'Optimisé illustrations (~17MB)' },
     x2plus: { name: 'Real-ESRGAN x2plus', desc: 'Upscale natif x2 (~64MB)' },
-    lanczos: { name: 'Lanczos (sans IA)', desc: 'Instantané, interpolation classique' },
+    lanczos: { name: 'Lanczos (sans IA)', desc: 'Instantané, interpolation classique', cost: { memoryMb: 150, memoryMbPerMp: 100, cpus: 1 } },
   },
   defaultModel: 'compact',
+  // Du plus gourmand au plus léger : Lanczos ne dépend ni du GPU ni des poids
+  fallbacks: {
+    x4plus: ['compact', 'lanczos'],
+    'x4plus-anime': ['compact', 'lanczos'],
+    x2plus: ['compact', 'lanczos'],
+    compact: ['lanczos'],
+  },
+  params: {
+    scale: { name: 'Facteur', type: 'number', min: 1, max: 4, step: 1, default: 2 },
+  },
+
+  // Taille de la sortie, pour estimer la durée des étapes suivantes (server/timings.js)
+  outputMegapixels(megapixels, { params }) {
+    return megapixels * params.scale ** 2
+  },
 
-  buildArgs({ inputPath, outputPath, job, selectedModel }) {
-    return { script: 'upscale.py', args: [inputPath, outputPath, selectedModel, '2'] }
+  buildArgs({ inputPath, outputPath, job, selectedModel, params }) {
+    return { script: 'upscale.py', args: [inputPath, outputPath, selectedModel, String(params.scale)] }
   },
 }
@@ -1,7 +1,102 @@
-// In-memory storage — lost on restart
+import { existsSync, readFileSync, writeFileSync, appendFileSync, renameSync } from 'fs'
+import { STORE_FILE } from './config.js'
 
-export const photos = new Map()
-export const jobs = new Map()
+// Photos, jobs, réglages et préréglages en mémoire, journalisés dans STORE_FILE (une ligne JSON par mutation)
+// puis reconstruits au démarrage par loadStore(). Rien n'est écrit avant loadStore().
 
-// Track running processes per job so they can be killed on cancel
+const COMPACT_AFTER_LINES = 5000
+
+let persistent = false
+let journalLines = 0
+
+class JournaledMap extends Map {
+  constructor(kind) {
+    super()
+    this.kind = kind
+  }
+
+  set(id, value) {
+    super.set(id, value)
+    append({ op: 'put', kind: this.kind, id, value })
+    return this
+  }
+
+  delete(id) {
+    const existed = super.delete(id)
+    if (existed) append({ op: 'del', kind: this.kind, id })
+    return existed
+  }
+
+  clear() {
+    super.clear()
+    append({ op: 'clear', kind: this.kind })
+  }
+}
+
+export const photos = new JournaledMap('photo')
+export const jobs = new JournaledMap('job')
+// Réglages modifiés depuis l'UI/API (clé → valeur), prioritaires sur l'env
+export const settings = new JournaledMap('setting')
+// Préréglages de pipeline nommés (étapes, modèles, paramètres), voir routes/presets.js
+export const presets = new JournaledMap('preset')
+// Jetons d'API (empreinte SHA-256 seulement, le jeton n'est montré qu'à sa création), voir auth.js
+export const apiTokens = new JournaledMap('token')
+
+// Track running processes per job so they can be killed on cancel (never persisted)
 export const runningProcs = new Map()
+
+const STORES = { photo: photos, job: jobs, setting: settings, preset: presets, token: apiTokens }
+
+/** Persist in-place changes made to a job object */
+export function saveJob(job) {
+  if (jobs.has(job.id)) jobs.set(job.id, job)
+}
+
+/** Persist in-place changes made to a photo object */
+export function savePhoto(photo) {
+  if (photos.has(photo.id)) photos.set(photo.id, photo)
+}
+
+function append(record) {
+  if (!persistent) return
+  try {
+    appendFileSync(STORE_FILE, JSON.stringify(record) + '\n')
+  } catch (err) {
+    console.error('Journal : écriture impossible :', err.message)
+    return
+  }
+  if (++journalLines > COMPACT_AFTER_LINES) compact()
+}
+
+/** Rewrite the journal as one `put` per live entry (atomic rename) */
+function compact() {
+  const tmp = `${STORE_FILE}.tmp`
+  const lines = []
+  for (const [kind, store] of Object.entries(STORES)) {
+    for (const [id, value] of store) lines.push(JSON.stringify({ op: 'put', kind, id, value }))
+  }
+  writeFileSync(tmp, lines.length ? lines.join('\n') + '\n' : '')
+  renameSync(tmp, STORE_FILE)
+  journalLines = lines.length
+}
+
+/** Replay the journal into the Maps, compact it and start journaling */
+export function loadStore() {
+  let restored = 0
+  if (existsSync(STORE_FILE)) {
+    for (const line of readFileSync(STORE_FILE, 'utf8').split('\n')) {
+      if (!line) continue
+      let record
+      try { record = JSON.parse(line) } catch { continue } // dernière ligne tronquée par un crash
+      const store = STORES[record.kind]
+      if (!store) continue
+      if (record.op === 'put') Map.prototype.set.call(store, record.id, record.value)
+      else if (record.op === 'del') Map.prototype.delete.call(store, record.id)
+      else if (record.op === 'clear') Map.prototype.clear.call(store)
+      restored++
+    }
+  }
+  compact()
+  persistent = true
+  if (restored > 0) console.log(`Journal : ${photos.size} photo(s), ${jobs.size} job(s) restauré(s)`)
+}
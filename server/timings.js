@@ -0,0 +1,241 @@
+import { existsSync, readFileSync, writeFileSync, appendFileSync, renameSync } from 'fs'
+import { STEP_TIMINGS_FILE, RESOURCE_MEMORY_MB, RESOURCE_CPUS } from './config.js'
+import { jobs } from './storage.js'
+import { STEPS, resolveParams } from './steps/index.js'
+import { getDevice } from './python.js'
+import { imageMegapixels, jobInputPath, stepCost, HAS_BUDGET } from './resources.js'
+import { maxConcurrent } from './queue.js'
+
+// Historique des durées d'étapes : chaque exécution réussie est ajoutée à STEP_TIMINGS_FILE
+// (une ligne JSON : étape, modèle, device, mégapixels, durée). Les durées à venir en sont
+// déduites par (étape, modèle, device), en fonction des mégapixels de l'entrée (voir fitFor).
+// Sans historique, l'étape est signalée inconnue plutôt que devinée. La file est simulée sur
+// maxConcurrent créneaux, moins si les jobs typiques ne tiennent pas tous dans le budget
+// machine (resources.js) : une estimation, pas une promesse.
+
+const MAX_SAMPLES = 100  // par (étape, modèle, device), les plus récents
+const MIN_FIT_SAMPLES = 5
+const SCHEDULE_TTL_MS = 1000
+
+const history = new Map()  // "step\0model\0device" → [{ megapixels, durationMs }]
+const fits = new Map()     // même clé → estimation calculée
+let device = 'cpu'
+let fileLines = 0
+let schedule = null        // { at, jobs: Map(jobId → eta), queue }
+
+const keyOf = (step, model, dev) => `${step}\0${model || ''}\0${dev}`
+
+function addSample(record) {
+  const key = keyOf(record.step, record.model, record.device)
+  const samples = history.get(key) || []
+  samples.push({ megapixels: record.megapixels, durationMs: record.durationMs })
+  if (samples.length > MAX_SAMPLES) samples.shift()
+  history.set(key, samples)
+  fits.delete(key)
+}
+
+/** Record one successful run of a step script on `inputPath` */
+export async function recordTiming(step, model, inputPath, durationMs) {
+  const record = {
+    step, model: model || null, device: await getDevice(),
+    megapixels: imageMegapixels(inputPath), durationMs, at: new Date().toISOString(),
+  }
+  device = record.device
+  addSample(record)
+  schedule = null
+  try {
+    appendFileSync(STEP_TIMINGS_FILE, JSON.stringify(record) + '\n')
+    fileLines++
+  } catch (err) {
+    console.error('Historique des durées : écriture impossible :', err.message)
+  }
+  // Le fichier ne garde que ce qui est en mémoire, réécrit quand il a doublé
+  if (fileLines > 2 * MAX_SAMPLES * Math.max(history.size, 10)) compact()
+}
+
+function compact() {
+  const lines = []
+  for (const [key, samples] of history) {
+    const [step, model, dev] = key.split('\0')
+    for (const s of samples) lines.push(JSON.stringify({ step, model: model || null, device: dev, ...s }))
+  }
+  const tmp = `${STEP_TIMINGS_FILE}.tmp`
+  writeFileSync(tmp, lines.length ? lines.join('\n') + '\n' : '')
+  renameSync(tmp, STEP_TIMINGS_FILE)
+  fileLines = lines.length
+}
+
+function median(values) {
+  const sorted = [...values].sort((a, b) => a - b)
+  const mid = Math.floor(sorted.length / 2)
+  return sorted.length % 2 ? sorted[mid] : (sorted[mid - 1] + sorted[mid]) / 2
+}
+
+/**
+ * { intercept, perMp } in ms, or { median } alone when the duration does not grow with the
+ * image. Varied sizes: least squares (model loading = intercept, cost per megapixel = slope).
+ * Similar sizes: proportional to the typical duration per megapixel.
+ */
+function fitFor(key) {
+  if (fits.has(key)) return fits.get(key)
+  const samples = history.get(key)
+  let fit = null
+  if (samples?.length) {
+    const sized = samples.filter(s => s.megapixels > 0)
+    fit = { median: median(samples.map(s => s.durationMs)) }
+    const varied = sized.length >= MIN_FIT_SAMPLES
+      && Math.max(...sized.map(s => s.megapixels)) > 1.5 * Math.min(...sized.map(s => s.megapixels))
+    let perMp
+    let intercept = 0
+    if (varied) {
+      const n = sized.length
+      const mx = sized.reduce((t, s) => t + s.megapixels, 0) / n
+      const my = sized.reduce((t, s) => t + s.durationMs, 0) / n
+      perMp = sized.reduce((t, s) => t + (s.megapixels - mx) * (s.durationMs - my), 0)
+        / sized.reduce((t, s) => t + (s.megapixels - mx) ** 2, 0)
+      intercept = my - perMp * mx
+    }
+    if (varied && perMp > 0 && intercept >= 0) fit = { ...fit, intercept, perMp }
+    else if (sized.length && (!varied || perMp > 0)) fit = { ...fit, intercept: 0, perMp: median(sized.map(s => s.durationMs / s.megapixels)) }
+  }
+  fits.set(key, fit)
+  return fit
+}
+
+/** Expected duration (seconds) of one run of `step` with `model` on this machine, null if never seen */
+export function estimateStep(step, model, megapixels) {
+  const fit = fitFor(keyOf(step, model, device))
+  if (!fit) return null
+  const ms = megapixels > 0 && fit.perMp !== undefined ? fit.intercept + fit.perMp * megapixels : fit.median
+  return ms / 1000
+}
+
+/**
+ * Work left in a job, in seconds, from its next step to the last one (the running step
+ * counts for what remains of it). Steps never timed on this device are listed apart.
+ */
+function remainingWork(job, now) {
+  const start = job.resumeFromStep || 0
+  let megapixels = imageMegapixels(jobInputPath(job))
+  let seconds = 0
+  const unknownSteps = []
+  for (let i = start; i < job.steps.length; i++) {
+    const step = job.steps[i]
+    const stepDef = STEPS[step]
+    if (!stepDef) continue
+    const selected = stepDef.models ? (job.options?.[step] || stepDef.defaultModel) : null
+    const models = stepDef.models && job.compare?.[step]?.length ? job.compare[step] : [selected]
+    const estimates = models.map(m => estimateStep(step, m, megapixels))
+    if (estimates.some(e => e === null)) {
+      unknownSteps.push(step)
+    } else {
+      let estimate = estimates.reduce((t, e) => t + e, 0)
+      if (i === start && job.status === 'processing' && job.currentStep === step) {
+        estimate = job.stepProgress > 0
+          ? estimate * (1 - job.stepProgress)
+          : estimate - (job.stepStartedAt ? (now - Date.parse(job.stepStartedAt)) / 1000 : 0)
+      }
+      seconds += Math.max(0, estimate)
+    }
+    if (stepDef.outputMegapixels && megapixels) {
+      megapixels = stepDef.outputMegapixels(megapixels, { model: selected, params: resolveParams(step, job.params?.[step]) })
+    }
+  }
+  return { seconds, unknownSteps }
+}
+
+/**
+ * ETAs of every active job and of the whole queue: running jobs keep their slot, pending
+ * jobs take the first free one in priority order. Jobs waiting for input are counted
+ * from now (the wait itself cannot be guessed); paused jobs are left out.
+ */
+function computeSchedule(now) {
+  const active = [...jobs.values()].filter(j => ['processing', 'pending', 'waiting_input'].includes(j.status))
+  const etas = new Map()
+  const slots = []
+  const order = [
+    ...active.filter(j => j.status === 'processing'),
+    ...active.filter(j => j.status === 'pending').sort((a, b) => (a.priority || 0) - (b.priority || 0)),
+  ]
+  // Jobs en parallèle : maxConcurrent, au plus autant d'étapes typiques (médiane des coûts)
+  // que le budget en contient
+  let parallel = Math.max(1, maxConcurrent)
+  if (HAS_BUDGET && order.length) {
+    const costs = order.map(j => stepCost(j, Math.min(j.resumeFromStep || 0, j.steps.length - 1)))
+    const cpus = median(costs.map(c => c.cpus))
+    const memoryMb = median(costs.map(c => c.memoryMb))
+    parallel = Math.max(1, Math.min(
+      parallel,
+      RESOURCE_CPUS && cpus > 0 ? Math.floor(RESOURCE_CPUS / cpus) : parallel,
+      RESOURCE_MEMORY_MB && memoryMb > 0 ? Math.floor(RESOURCE_MEMORY_MB / memoryMb) : parallel,
+    ))
+  }
+  for (const job of order) {
+    const work = remainingWork(job, now)
+    let startsIn = 0
+    if (slots.length < parallel) {
+      slots.push(work.seconds)
+    } else {
+      const earliest = slots.indexOf(Math.min(...slots))
+      startsIn = slots[earliest]
+      slots[earliest] += work.seconds
+    }
+    etas.set(job.id, { startsIn, ...work })
+  }
+  for (const job of active.filter(j => j.status === 'waiting_input')) {
+    etas.set(job.id, { startsIn: 0, ...remainingWork(job, now), waitingInput: true })
+  }
+
+  const toEta = ({ startsIn, seconds, unknownSteps, waitingInput }) => ({
+    seconds: Math.round(startsIn + seconds),
+    startsIn: Math.round(startsIn),
+    finishAt: new Date(now + (startsIn + seconds) * 1000).toISOString(),
+    unknownSteps,
+    ...(waitingInput ? { waitingInput: true } : {}),
+  })
+  const total = Math.max(0, ...slots, ...[...etas.values()].map(e => e.startsIn + e.seconds))
+  return {
+    at: now,
+    jobs: new Map([...etas].map(([id, eta]) => [id, toEta(eta)])),
+    queue: {
+      seconds: Math.round(total),
+      finishAt: active.length ? new Date(now + total * 1000).toISOString() : null,
+      jobs: active.length,
+      device,
+      // Jobs dont une étape n'a jamais été chronométrée ici : ETA sous-estimée
+      incomplete: [...etas.values()].filter(e => e.unknownSteps.length).length,
+    },
+  }
+}
+
+function currentSchedule() {
+  const now = Date.now()
+  if (!schedule || now - schedule.at > SCHEDULE_TTL_MS) schedule = computeSchedule(now)
+  return schedule
+}
+
+/** `job.eta`: { seconds, startsIn, finishAt, unknownSteps, waitingInput? }, null if not active */
+export function jobEta(job) {
+  return currentSchedule().jobs.get(job.id) || null
+}
+
+/** Queue-wide ETA for /api/status */
+export function queueEta() {
+  return currentSchedule().queue
+}
+
+/** Load the timing history and detect the device it is matched against */
+export function loadTimings() {
+  if (existsSync(STEP_TIMINGS_FILE)) {
+    for (const line of readFileSync(STEP_TIMINGS_FILE, 'utf8').split('\n')) {
+      if (!line) continue
+      try { addSample(JSON.parse(line)) } catch {}
+      fileLines++
+    }
+  }
+  getDevice().then(d => {
+    device = d
+    fits.clear()
+    schedule = null
+  })
+}
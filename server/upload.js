@@ -1,22 +1,53 @@
 import multer from 'multer'
 import path from 'path'
+import { createWriteStream, unlink } from 'fs'
+import { Transform } from 'stream'
 import { randomUUID } from 'crypto'
-import { UPLOADS_DIR } from './config.js'
+import { UPLOADS_DIR, MAX_ARCHIVE_SIZE } from './config.js'
+import { archiveKind } from './archive.js'
+import { isImageFile, MAX_IMAGE_SIZE } from './images.js'
 
-const storage = multer.diskStorage({
-  destination: UPLOADS_DIR,
-  filename: (_req, file, cb) => {
-    const ext = path.extname(file.originalname)
-    cb(null, `${randomUUID()}${ext}`)
+// Stockage disque plafonnant chaque fichier pendant la réception : une image au-delà de
+// MAX_IMAGE_SIZE n'est plus écrite (le reste est lu et jeté), puis supprimée et marquée
+// `tooLarge` sans faire échouer les autres fichiers. Les archives sont limitées par fileSize.
+const storage = {
+  _handleFile(_req, file, cb) {
+    const kind = archiveKind(file.originalname)
+    const filename = `${randomUUID()}${kind === 'tgz' ? '.tar.gz' : path.extname(file.originalname)}`
+    const filePath = path.join(UPLOADS_DIR, filename)
+    const maxSize = kind ? Infinity : MAX_IMAGE_SIZE
+    let size = 0
+    const capped = new Transform({
+      transform(chunk, _enc, done) {
+        size += chunk.length
+        done(null, size > maxSize ? undefined : chunk)
+      },
+    })
+    const out = createWriteStream(filePath)
+    out.on('error', cb)
+    out.on('finish', () => {
+      if (size <= maxSize) return cb(null, { destination: UPLOADS_DIR, filename, path: filePath, size })
+      unlink(filePath, () => cb(null, { size, tooLarge: true }))
+    })
+    file.stream.pipe(capped).pipe(out)
   },
-})
+  _removeFile(_req, file, cb) {
+    if (!file.path) return cb(null)
+    unlink(file.path, () => cb(null))
+  },
+}
 
+// Images et archives d'albums ; les fichiers refusés sont listés dans req.rejectedFiles
 export const upload = multer({
   storage,
-  fileFilter: (_req, file, cb) => {
-    const allowed = ['.jpg', '.jpeg', '.png', '.webp', '.tiff', '.bmp']
-    const ext = path.extname(file.originalname).toLowerCase()
-    cb(null, allowed.includes(ext))
+  fileFilter: (req, file, cb) => {
+    const accepted = isImageFile(file.originalname) || archiveKind(file.originalname) !== null
+    if (!accepted) {
+      req.rejectedFiles = req.rejectedFiles || []
+      req.rejectedFiles.push({ name: file.originalname, reason: 'Format non pris en charge' })
+    }
+    cb(null, accepted)
   },
-  limits: { fileSize: 50 * 1024 * 1024 },
+  // Plafond des archives ; celui des images est appliqué par le stockage
+  limits: { fileSize: MAX_ARCHIVE_SIZE },
 })
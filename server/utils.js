@@ -1,5 +1,7 @@
 import path from 'path'
-import { RESULTS_DIR } from './config.js'
+import { RESULTS_DIR, UPLOADS_DIR } from './config.js'
+import { MANUAL_STEPS } from './steps/index.js'
+import { jobEta } from './timings.js'
 
 /** Sanitize filename: remove accents, replace special chars */
 export function sanitizeFilename(name) {
@@ -14,3 +16,41 @@ export function getUrlForPath(filePath) {
   if (filePath.startsWith(RESULTS_DIR)) return `/results/${path.basename(filePath)}`
   return `/uploads/${path.basename(filePath)}`
 }
+
+/**
+ * Inverse of getUrlForPath: '/results/x.png' → file on disk, null for anything else
+ * (malformed escapes, or a decoded name leaving the directory such as '..%2Fstore.jsonl')
+ */
+export function getPathForUrl(url) {
+  if (typeof url !== 'string') return null
+  const match = url.match(/^\/(results|uploads)\/([^/]+)$/)
+  if (!match) return null
+  const dir = match[1] === 'results' ? RESULTS_DIR : UPLOADS_DIR
+  let name
+  try { name = decodeURIComponent(match[2]) } catch { return null }
+  // Même forme que les chemins construits ailleurs (path.join), comparés tels quels
+  return path.dirname(path.resolve(dir, name)) === path.resolve(dir) ? path.join(dir, name) : null
+}
+
+/** Value of a request cookie, null if absent or malformed */
+export function readCookie(req, name) {
+  for (const part of (req.headers.cookie || '').split(';')) {
+    const eq = part.indexOf('=')
+    if (eq < 0 || part.slice(0, eq).trim() !== name) continue
+    try { return decodeURIComponent(part.slice(eq + 1).trim()) } catch { return null }
+  }
+  return null
+}
+
+/** Job as exposed to the browser: internal paths and session stripped, back navigation and ETA computed */
+export function toPublicJob(job) {
+  const { maskPath, cropRect, currentInputPath, inputPath, sessionId, ...rest } = job
+  rest.eta = jobEta(job)
+  if (rest.status === 'waiting_input' && rest.resumeFromStep != null) {
+    rest.canGoBack = false
+    for (let i = rest.resumeFromStep - 1; i >= 0; i--) {
+      if (MANUAL_STEPS.has(job.steps[i])) { rest.canGoBack = true; break }
+    }
+  }
+  return rest
+}
@@ -0,0 +1,256 @@
+import { readdirSync, statSync, mkdirSync, copyFileSync } from 'fs'
+import { execFile } from 'child_process'
+import { promisify } from 'util'
+import { randomUUID } from 'crypto'
+import path from 'path'
+import { UPLOADS_DIR, AI_DIR, WATCH_DIR, WATCH_OUTBOX, WATCH_PRESET, WATCH_INTERVAL_MS } from './config.js'
+import { photos, jobs, presets } from './storage.js'
+import { STEPS, MANUAL_STEPS, normalizeParams } from './steps/index.js'
+import { isAiReady, getPython } from './python.js'
+import { enqueueJob } from './queue.js'
+import { publishJob, subscribe } from './events.js'
+import { isImageFile, MAX_IMAGE_SIZE } from './images.js'
+import { scheduleQuotaCheck } from './quota.js'
+import { getPathForUrl } from './utils.js'
+import { readManifest, writeManifest, pipelineSignature, sameInput } from './manifest.js'
+
+// Dossier surveillé : les scans déposés dans WATCH_DIR (sous-dossiers compris) sont importés
+// dans la session « watch », passent par le préréglage WATCH_PRESET, et le résultat final est
+// écrit dans WATCH_OUTBOX avec la même arborescence. Le manifeste de l'outbox (manifest.js)
+// garde la trace de chaque scan : un fichier n'est retraité que s'il change.
+// Étapes manuelles : recadrage prérempli par la détection auto des bords, les autres sautées.
+
+const SESSION_ID = 'watch'
+const TERMINAL_STATUSES = new Set(['completed', 'failed', 'cancelled'])
+const execFileAsync = promisify(execFile)
+
+let manifest = {}
+let lastScan = new Map()  // scan → « taille:mtime » au passage précédent
+let scanning = false
+let lastWarning = null
+
+function warnOnce(message) {
+  if (message === lastWarning) return
+  lastWarning = message
+  console.log(`Dossier surveillé : ${message}`)
+}
+
+/** Preset named by WATCH_PRESET (id or name, case-insensitive) */
+function findPreset() {
+  const wanted = WATCH_PRESET.toLowerCase()
+  return presets.get(WATCH_PRESET) || [...presets.values()].find(p => p.name.toLowerCase() === wanted) || null
+}
+
+/** Images of the inbox: { input, rel, stat }; hidden entries and the outbox are skipped */
+function listInbox() {
+  const items = []
+  const outbox = path.resolve(WATCH_OUTBOX)
+  const walk = (dir, prefix) => {
+    let entries
+    try { entries = readdirSync(dir, { withFileTypes: true }).sort((a, b) => a.name.localeCompare(b.name)) } catch { return }
+    for (const entry of entries) {
+      if (entry.name.startsWith('.')) continue
+      const full = path.join(dir, entry.name)
+      if (entry.isDirectory()) {
+        if (path.resolve(full) !== outbox) walk(full, path.join(prefix, entry.name))
+      } else if (entry.isFile() && isImageFile(entry.name)) {
+        try { items.push({ input: full, rel: path.join(prefix, entry.name), stat: statSync(full) }) } catch {}
+      }
+    }
+  }
+  walk(WATCH_DIR, '')
+  return items
+}
+
+/** Output path in the outbox; "a.jpg" next to an already handled "a.png" becomes "a_jpg.png" */
+function outputRel(item) {
+  const parsed = path.parse(item.rel)
+  const out = path.join(parsed.dir, `${parsed.name}.png`)
+  return manifest[out] && manifest[out].source !== item.input
+    ? path.join(parsed.dir, `${parsed.name}_${parsed.ext.slice(1).toLowerCase()}.png`)
+    : out
+}
+
+/** Bounds found by auto_crop.py as a crop.py rectangle "x,y,w,h", null if detection failed */
+async function detectCropRect(inputPath) {
+  try {
+    const { stdout } = await execFileAsync(getPython(), [path.join(AI_DIR, 'auto_crop.py'), inputPath], { timeout: 30000 })
+    const { x, y, w, h } = JSON.parse(stdout)
+    return [x, y, w, h].every(Number.isFinite) && w > 0 && h > 0 ? [x, y, w, h].map(Math.round).join(',') : null
+  } catch (err) {
+    console.error(`Dossier surveillé : recadrage auto impossible pour ${inputPath} :`, err.message)
+    return null
+  }
+}
+
+function recordFailure(out, item, error) {
+  manifest[out] = {
+    source: item.input, input: { size: item.stat.size, mtimeMs: item.stat.mtimeMs },
+    error, failedAt: new Date().toISOString(),
+  }
+  writeManifest(WATCH_OUTBOX, manifest)
+  console.error(`Dossier surveillé : ${item.rel} — ${error}`)
+}
+
+/** Import one scan as a photo of the watch session and queue the preset's job on it */
+async function importScan(item, preset, out, batchId) {
+  if (item.stat.size > MAX_IMAGE_SIZE) {
+    return recordFailure(out, item, `Fichier trop volumineux (${Math.round(item.stat.size / 1024 / 1024)} Mo)`)
+  }
+  const filename = `${randomUUID()}${path.extname(item.input).toLowerCase()}`
+  copyFileSync(item.input, path.join(UPLOADS_DIR, filename))
+  const album = path.dirname(item.rel)
+  const photo = {
+    id: randomUUID(),
+    sessionId: SESSION_ID,
+    filename,
+    originalName: path.basename(item.input),
+    ...(album !== '.' ? { album } : {}),
+    uploadedAt: new Date().toISOString(),
+  }
+  photos.set(photo.id, photo)
+
+  let steps = preset.steps.filter(s => STEPS[s] && (!MANUAL_STEPS.has(s) || s === 'crop'))
+  const cropRect = steps.includes('crop') ? await detectCropRect(path.join(UPLOADS_DIR, filename)) : null
+  if (!cropRect) steps = steps.filter(s => s !== 'crop')
+  if (!steps.length) return recordFailure(out, item, 'Aucune étape automatique à appliquer')
+
+  const params = normalizeParams(preset.params, steps) || {}
+  const job = {
+    id: randomUUID(),
+    sessionId: SESSION_ID,
+    photoId: photo.id,
+    photoName: photo.originalName,
+    original: `/uploads/${photo.filename}`,
+    steps,
+    options: { ...preset.options },
+    params,
+    presetId: preset.id,
+    batchId,
+    compare: null,
+    branches: null,
+    maskPath: null,
+    cropRect,
+    // Aucun navigateur derrière : le heartbeat ne doit pas l'annuler
+    detached: true,
+    watch: { source: item.input, output: out },
+    status: 'pending',
+    progress: 0,
+    createdAt: new Date().toISOString(),
+    result: null,
+    stepResults: [],
+    priority: Date.now(),
+  }
+  const pipeline = steps.map(step => ({ step, model: STEPS[step].models ? (job.options[step] || STEPS[step].defaultModel) : null }))
+  manifest[out] = {
+    source: item.input, pipeline: pipelineSignature(pipeline, params),
+    input: { size: item.stat.size, mtimeMs: item.stat.mtimeMs },
+    jobId: job.id, importedAt: job.createdAt,
+  }
+  writeManifest(WATCH_OUTBOX, manifest)
+  jobs.set(job.id, job)
+  publishJob('job_created', job)
+  enqueueJob(job)
+  console.log(`Dossier surveillé : ${item.rel} importé (${steps.join(', ')})`)
+}
+
+/** One pass over the inbox: import new or modified scans that stopped changing */
+async function scan() {
+  if (scanning || !isAiReady()) return
+  if (!WATCH_PRESET) return warnOnce('WATCH_PRESET non défini, rien n\'est importé')
+  const preset = findPreset()
+  if (!preset) return warnOnce(`préréglage « ${WATCH_PRESET} » introuvable, rien n'est importé`)
+  scanning = true
+  try {
+    const bySource = new Map(Object.entries(manifest).map(([out, entry]) => [entry.source, out]))
+    const seen = new Map()
+    // Un lot par passage : les scans déposés ensemble (webhook batch_finished)
+    const batchId = randomUUID()
+    let imported = 0
+    for (const item of listInbox()) {
+      const key = `${item.stat.size}:${item.stat.mtimeMs}`
+      seen.set(item.input, key)
+      const known = bySource.get(item.input)
+      if (known && sameInput(manifest[known], item.stat)) continue
+      // Fichier en cours de copie par le scanner : attendre qu'il soit stable d'un passage à l'autre
+      if (lastScan.get(item.input) !== key) continue
+      try {
+        await importScan(item, preset, known || outputRel(item), batchId)
+        imported++
+      } catch (err) {
+        console.error(`Dossier surveillé : import de ${item.rel} impossible :`, err.message)
+      }
+    }
+    lastScan = seen
+    if (imported) scheduleQuotaCheck()
+  } finally {
+    scanning = false
+  }
+}
+
+/** Write the final result of a finished watch job to the outbox and record it */
+function finish(job) {
+  const out = job.watch.output
+  const entry = manifest[out]
+  // Remplacé depuis par une nouvelle version du scan
+  if (!entry || entry.jobId !== job.id) return
+  if (job.status === 'completed') {
+    try {
+      const dest = path.join(WATCH_OUTBOX, out)
+      mkdirSync(path.dirname(dest), { recursive: true })
+      copyFileSync(getPathForUrl(job.result), dest)
+      entry.steps = job.stepResults.map(({ step, model, noop, fallback }) => ({
+        step, model: model ?? null, noop: !!noop, ...(fallback ? { fallback } : {}),
+      }))
+      entry.completedAt = new Date().toISOString()
+      // Job relancé après un échec
+      delete entry.error
+      delete entry.failedAt
+      console.log(`Dossier surveillé : ${out} écrit`)
+    } catch (err) {
+      entry.error = `Écriture dans l'outbox impossible : ${err.message}`
+      entry.failedAt = new Date().toISOString()
+    }
+  } else {
+    entry.error = job.status === 'cancelled' ? 'Annulé' : (job.error || 'Échec')
+    entry.failedAt = new Date().toISOString()
+  }
+  writeManifest(WATCH_OUTBOX, manifest)
+}
+
+/** Inbox, outbox, preset and counts of the manifest, for /api/status; null when disabled */
+export function watchStatus() {
+  if (!WATCH_DIR) return null
+  const entries = Object.values(manifest)
+  return {
+    inbox: WATCH_DIR,
+    outbox: WATCH_OUTBOX,
+    preset: findPreset()?.name ?? null,
+    inProgress: entries.filter(e => e.jobId && !e.completedAt && !e.error).length,
+    done: entries.filter(e => e.completedAt).length,
+    failed: entries.filter(e => e.error).length,
+  }
+}
+
+export function startWatchFolder() {
+  if (!WATCH_DIR) return
+  mkdirSync(WATCH_DIR, { recursive: true })
+  mkdirSync(WATCH_OUTBOX, { recursive: true })
+  manifest = readManifest(WATCH_OUTBOX)
+
+  subscribe(({ type, job }) => {
+    if (job.watch && (type === 'job_completed' || type === 'job_failed' || type === 'job_cancelled')) finish(job)
+  })
+  // Jobs terminés pendant un arrêt, ou disparus du journal (réimportés au prochain passage)
+  for (const [out, entry] of Object.entries(manifest)) {
+    if (!entry.jobId || entry.completedAt || entry.error) continue
+    const job = jobs.get(entry.jobId)
+    if (!job) delete manifest[out]
+    else if (TERMINAL_STATUSES.has(job.status)) finish(job)
+  }
+  writeManifest(WATCH_OUTBOX, manifest)
+
+  setInterval(scan, WATCH_INTERVAL_MS)
+  scan()
+  console.log(`Dossier surveillé : ${WATCH_DIR} → ${WATCH_OUTBOX} (préréglage « ${WATCH_PRESET} », toutes les ${WATCH_INTERVAL_MS / 1000} s)`)
+}
@@ -0,0 +1,173 @@
+import { createHmac, randomBytes } from 'crypto'
+import { PUBLIC_URL } from './config.js'
+import { jobs, settings } from './storage.js'
+import { subscribe } from './events.js'
+
+// Webhooks : chaque URL configurée (PUT /api/settings { webhooks }) reçoit en POST JSON les
+// événements auxquels elle est abonnée. Le corps est signé en HMAC-SHA256 avec le secret du
+// webhook (en-tête X-OldPhotos-Signature: sha256=<hex>). Échec réseau, 5xx, 408 ou 429 :
+// nouvel essai après RETRY_DELAYS_MS ; les essais en attente sont perdus au redémarrage.
+// Un lot (batch) = les jobs créés par une même requête POST /api/jobs (ou un passage du
+// dossier surveillé), variantes comprises ; batch_finished part quand tous sont terminés.
+// Les webhooks sont ceux de l'instance : ils reçoivent les jobs de toutes les sessions.
+
+export const WEBHOOK_EVENTS = ['job_completed', 'job_failed', 'waiting_input', 'batch_finished']
+
+const RETRY_DELAYS_MS = [10 * 1000, 60 * 1000, 5 * 60 * 1000, 30 * 60 * 1000]
+const TIMEOUT_MS = 10 * 1000
+const TERMINAL_STATUSES = new Set(['completed', 'failed', 'cancelled'])
+
+// batchId → { state: statuts des jobs au dernier batch_finished envoyé, at }, oublié après
+// NOTIFIED_BATCH_TTL_MS : seuls les événements rapprochés d'un même lot sont des doublons
+const notifiedBatches = new Map()
+const NOTIFIED_BATCH_TTL_MS = 30 * 60 * 1000
+
+/** Configured webhooks: [{ id, url, events, secret, createdAt }] */
+export function getWebhooks() {
+  return settings.get('webhooks') || []
+}
+
+/** Webhook as listed by the API: the secret is only returned once, when it is generated */
+export function toPublicWebhook({ secret, ...rest }) {
+  return rest
+}
+
+/**
+ * Replace the webhook list. Entries keep their secret when their id is known, otherwise
+ * get the given one or a new random secret. Returns { created } (ids given a generated
+ * secret), or { error } if a URL is invalid.
+ */
+export function setWebhooks(list) {
+  if (!Array.isArray(list)) return { error: 'webhooks must be an array' }
+  const existing = new Map(getWebhooks().map(h => [h.id, h]))
+  const out = []
+  const created = new Set()
+  for (const entry of list) {
+    let url
+    try { url = new URL(entry?.url) } catch { return { error: `Invalid webhook URL: ${entry?.url}` } }
+    if (url.protocol !== 'http:' && url.protocol !== 'https:') return { error: `Invalid webhook URL: ${entry.url}` }
+    const previous = existing.get(entry.id)
+    const events = Array.isArray(entry.events) ? WEBHOOK_EVENTS.filter(e => entry.events.includes(e)) : WEBHOOK_EVENTS
+    const id = previous?.id || randomBytes(8).toString('hex')
+    let secret = typeof entry.secret === 'string' && entry.secret.length >= 16 ? entry.secret : previous?.secret
+    if (!secret) {
+      secret = randomBytes(24).toString('base64url')
+      created.add(id)
+    }
+    out.push({ id, url: url.href, events, secret, createdAt: previous?.createdAt || new Date().toISOString() })
+  }
+  if (out.length) settings.set('webhooks', out)
+  else settings.delete('webhooks')
+  return { created }
+}
+
+/** Absolute URL when PUBLIC_URL is set ('/results/x.png' → 'https://photos.example/results/x.png') */
+function publicUrl(url) {
+  return url && PUBLIC_URL ? new URL(url, PUBLIC_URL).href : url || null
+}
+
+function jobPayload(job) {
+  return {
+    id: job.id,
+    photoName: job.photoName,
+    status: job.status,
+    steps: job.steps,
+    options: job.options || {},
+    batchId: job.batchId || null,
+    parentJobId: job.parentJobId || null,
+    branchLabel: job.branchLabel || null,
+    result: publicUrl(job.result),
+    stepResults: (job.stepResults || []).map(sr => ({
+      step: sr.step, result: publicUrl(sr.result), model: sr.model ?? null, noop: !!sr.noop,
+    })),
+    error: job.error || null,
+    failedStep: job.failedStep || null,
+    waitingStep: job.waitingStep || null,
+    waitingImage: publicUrl(job.waitingImage),
+    createdAt: job.createdAt,
+    completedAt: job.completedAt || null,
+    failedAt: job.failedAt || null,
+  }
+}
+
+async function send(hook, delivery, attempt = 0) {
+  // Webhook supprimé ou modifié entre deux essais : on abandonne
+  const current = getWebhooks().find(h => h.id === hook.id)
+  if (!current || current.url !== hook.url) return
+
+  let error
+  let permanent = false
+  try {
+    const res = await fetch(hook.url, {
+      method: 'POST',
+      headers: {
+        'Content-Type': 'application/json',
+        'User-Agent': 'oldphotos-webhook',
+        'X-OldPhotos-Event': delivery.event,
+        'X-OldPhotos-Delivery': delivery.id,
+        'X-OldPhotos-Signature': `sha256=${createHmac('sha256', current.secret).update(delivery.body).digest('hex')}`,
+      },
+      body: delivery.body,
+      signal: AbortSignal.timeout(TIMEOUT_MS),
+    })
+    if (res.ok) return
+    error = `HTTP ${res.status}`
+    permanent = res.status < 500 && res.status !== 408 && res.status !== 429
+  } catch (err) {
+    error = err.message
+  }
+
+  if (permanent || attempt >= RETRY_DELAYS_MS.length) {
+    console.error(`Webhook ${hook.url} : ${delivery.event} abandonné après ${attempt + 1} essai(s) (${error})`)
+    return
+  }
+  const delay = RETRY_DELAYS_MS[attempt]
+  console.log(`Webhook ${hook.url} : ${delivery.event} en échec (${error}), nouvel essai dans ${delay / 1000} s`)
+  setTimeout(() => send(hook, delivery, attempt + 1), delay).unref()
+}
+
+/** Send an event to every webhook subscribed to it */
+function dispatch(event, payload) {
+  const hooks = getWebhooks().filter(h => h.events.includes(event))
+  if (!hooks.length) return
+  const id = randomBytes(12).toString('hex')
+  const body = JSON.stringify({ event, deliveryId: id, sentAt: new Date().toISOString(), ...payload })
+  for (const hook of hooks) send(hook, { id, event, body })
+}
+
+/** batch_finished once every job of the batch is done; again if one of them is retried */
+function checkBatch(batchId) {
+  const batch = [...jobs.values()].filter(j => j.batchId === batchId)
+  if (!batch.length || !batch.every(j => TERMINAL_STATUSES.has(j.status))) return
+  const state = batch.map(j => `${j.id}:${j.status}`).join(',')
+  const now = Date.now()
+  for (const [id, notified] of notifiedBatches) {
+    if (now - notified.at > NOTIFIED_BATCH_TTL_MS) notifiedBatches.delete(id)
+  }
+  if (notifiedBatches.get(batchId)?.state === state) return
+  notifiedBatches.set(batchId, { state, at: now })
+  const count = (status) => batch.filter(j => j.status === status).length
+  dispatch('batch_finished', {
+    batch: {
+      id: batchId,
+      total: batch.length,
+      completed: count('completed'),
+      failed: count('failed'),
+      cancelled: count('cancelled'),
+      jobs: batch.map(jobPayload),
+    },
+  })
+}
+
+export function startWebhooks() {
+  subscribe(({ type, job }) => {
+    if (type === 'job_completed' || type === 'job_failed' || type === 'waiting_input') {
+      dispatch(type, { job: jobPayload(job) })
+    }
+    if (job.batchId && (type === 'job_completed' || type === 'job_failed' || type === 'job_cancelled')) {
+      checkBatch(job.batchId)
+    }
+  })
+  const hooks = getWebhooks()
+  if (hooks.length) console.log(`Webhooks : ${hooks.length} URL(s) configurée(s)`)
+}
@@ -1,11 +1,12 @@
-import type { Photo, Job, StepInfo, StepKey } from './types'
+import type { Photo, RejectedFile, Job, JobEventType, StepInfo, StepKey, BranchSpec, StepParams, Preset, AuthInfo, ApiToken, StorageUsage, HeartbeatAction, Webhook, WebhookEvent } from './types'
 
 const BASE = '/api'
 
-export async function uploadPhotos(files: File[]): Promise<Photo[]> {
+/** Images and/or album archives (.zip, .tar, .tar.gz), unpacked server-side */
+export async function uploadPhotos(files: File[]): Promise<{ photos: Photo[]; rejected: RejectedFile[] }> {
   const form = new FormData()
   files.forEach((f) => form.append('photos', f))
-  const res = await fetch(`${BASE}/photos`, { method: 'POST', body: form })
+  const res = await fetch(`${BASE}/photos?rejected=1`, { method: 'POST', body: form })
   return res.json()
 }
 
@@ -22,6 +23,17 @@ export async function deleteAllPhotos(): Promise<void> {
   await fetch(`${BASE}/photos`, { method: 'DELETE' })
 }
 
+/** Pinned photos are never removed by the disk quota nor by cleanup */
+export async function pinPhoto(id: string, pinned: boolean): Promise<Photo> {
+  const res = await fetch(`${BASE}/photos/${id}/pin`, { method: pinned ? 'POST' : 'DELETE' })
+  return res.json()
+}
+
+export async function getStorage(): Promise<StorageUsage> {
+  const res = await fetch(`${BASE}/storage`)
+  return res.json()
+}
+
 export async function getSteps(): Promise<Record<string, StepInfo>> {
   const res = await fetch(`${BASE}/steps`)
   return res.json()
@@ -32,21 +44,96 @@ export async function createJobs(
   steps: StepKey[],
   options?: Record<string, string>,
   masks?: Record<string, string>,
-  cropRects?: Record<string, string>
+  cropRects?: Record<string, string>,
+  branches?: BranchSpec[],
+  compare?: Record<string, string[]>,
+  params?: StepParams
 ): Promise<Job[]> {
   const res = await fetch(`${BASE}/jobs`, {
     method: 'POST',
     headers: { 'Content-Type': 'application/json' },
-    body: JSON.stringify({ photoIds, steps, options, masks, cropRects }),
+    body: JSON.stringify({ photoIds, steps, options, masks, cropRects, branches, compare, params }),
   })
   return res.json()
 }
 
+export async function getPresets(): Promise<Preset[]> {
+  const res = await fetch(`${BASE}/presets`)
+  return res.json()
+}
+
+type PresetInput = Pick<Preset, 'name' | 'steps' | 'options' | 'params'> & { description?: string }
+
+/** Create, or update when `id` is given; rejects with the server message (e.g. duplicate name) */
+export async function savePreset(preset: PresetInput, id?: string): Promise<Preset> {
+  const res = await fetch(id ? `${BASE}/presets/${id}` : `${BASE}/presets`, {
+    method: id ? 'PUT' : 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify(preset),
+  })
+  const data = await res.json()
+  if (!res.ok) throw new Error(data.error || `HTTP ${res.status}`)
+  return data
+}
+
+export async function deletePreset(id: string): Promise<void> {
+  await fetch(`${BASE}/presets/${id}`, { method: 'DELETE' })
+}
+
+export const PRESETS_EXPORT_URL = `${BASE}/presets/export`
+
+export async function importPresets(data: unknown): Promise<{ imported: Preset[]; rejected: { name: string | null; error: string }[] }> {
+  const res = await fetch(`${BASE}/presets/import`, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify(data),
+  })
+  const body = await res.json()
+  if (!res.ok) throw new Error(body.error || `HTTP ${res.status}`)
+  return body
+}
+
+/** ZIP of originals + final results (+ every step image) with a manifest.json */
+export function exportJobsUrl(jobIds: string[], intermediates = false): string {
+  const params = new URLSearchParams({ ids: jobIds.join(',') })
+  if (intermediates) params.set('intermediates', '1')
+  return `${BASE}/jobs/export?${params}`
+}
+
 export async function getJobs(): Promise<Job[]> {
   const res = await fetch(`${BASE}/jobs`)
   return res.json()
 }
 
+const JOB_EVENT_TYPES: JobEventType[] = [
+  'job_created',
+  'job_updated',
+  'step_started',
+  'step_progress',
+  'step_completed',
+  'waiting_input',
+  'job_completed',
+  'job_failed',
+  'job_cancelled',
+  'job_paused',
+]
+
+/**
+ * Live job updates over Server-Sent Events. The open stream doubles as the heartbeat.
+ * `onOpen` fires on every (re)connection so the caller can resync missed events.
+ */
+export function subscribeJobEvents(
+  onEvent: (type: JobEventType, job: Job) => void,
+  onOpen?: () => void
+): () => void {
+  const source = new EventSource(`${BASE}/events`)
+  for (const type of JOB_EVENT_TYPES) {
+    source.addEventListener(type, (e) => onEvent(type, JSON.parse((e as MessageEvent).data)))
+  }
+  if (onOpen) source.addEventListener('open', onOpen)
+  return () => source.close()
+}
+
 export async function applyCrop(photoId: string, cropRect: string): Promise<Photo> {
   const res = await fetch(`${BASE}/photos/${photoId}/crop`, {
     method: 'POST',
@@ -70,12 +157,22 @@ export async function importResult(resultPath: string): Promise<Photo> {
   return res.json()
 }
 
-export async function getSettings(): Promise<{ maxConcurrent: number }> {
+interface Settings {
+  /** False for non-admin users when auth is on: PUT /api/settings answers 403 */
+  editable?: boolean
+  maxConcurrent: number
+  maxConcurrentLimit?: number
+  heartbeatAction?: HeartbeatAction
+  webhooks?: Webhook[]
+  webhookEvents?: WebhookEvent[]
+}
+
+export async function getSettings(): Promise<Settings> {
   const res = await fetch(`${BASE}/settings`)
   return res.json()
 }
 
-export async function updateSettings(settings: { maxConcurrent: number }): Promise<{ maxConcurrent: number }> {
+export async function updateSettings(settings: Partial<Settings>): Promise<Settings> {
   const res = await fetch(`${BASE}/settings`, {
     method: 'PUT',
     headers: { 'Content-Type': 'application/json' },
@@ -84,6 +181,18 @@ export async function updateSettings(settings: { maxConcurrent: number }): Promi
   return res.json()
 }
 
+/** Whole webhook list; new entries (no id) get a generated secret */
+export async function updateWebhooks(webhooks: Array<Pick<Webhook, 'url' | 'events'> & { id?: string }>): Promise<Settings> {
+  const res = await fetch(`${BASE}/settings`, {
+    method: 'PUT',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify({ webhooks }),
+  })
+  const data = await res.json()
+  if (!res.ok) throw new Error(data.error || `HTTP ${res.status}`)
+  return data
+}
+
 export async function reorderJobs(jobIds: string[]): Promise<void> {
   await fetch(`${BASE}/jobs/reorder`, {
     method: 'PUT',
@@ -92,7 +201,7 @@ export async function reorderJobs(jobIds: string[]): Promise<void> {
   })
 }
 
-export async function submitJobInput(jobId: string, input: { mask?: string; cropRect?: string }): Promise<void> {
+export async function submitJobInput(jobId: string, input: { mask?: string; cropRect?: string; choice?: string }): Promise<void> {
   await fetch(`${BASE}/jobs/${jobId}/input`, {
     method: 'POST',
     headers: { 'Content-Type': 'application/json' },
@@ -120,6 +229,16 @@ export async function skipFailedStep(jobId: string): Promise<void> {
   await fetch(`${BASE}/jobs/${jobId}/skip-failed`, { method: 'POST' })
 }
 
+/** Keep the final result of a job out of quota eviction and cleanup */
+export async function pinJob(jobId: string, pinned: boolean): Promise<void> {
+  await fetch(`${BASE}/jobs/${jobId}/pin`, { method: pinned ? 'POST' : 'DELETE' })
+}
+
+/** Detached jobs keep running when the tab is closed */
+export async function detachJob(jobId: string, detached: boolean): Promise<void> {
+  await fetch(`${BASE}/jobs/${jobId}/detach`, { method: detached ? 'POST' : 'DELETE' })
+}
+
 export async function cancelJob(jobId: string): Promise<void> {
   await fetch(`${BASE}/jobs/${jobId}/cancel`, { method: 'POST' })
 }
@@ -128,3 +247,71 @@ export async function cancelAllJobs(): Promise<void> {
   await fetch(`${BASE}/jobs/cancel-all`, { method: 'POST' })
 }
 
+/** Pause after the current step, or right away with `immediate` (the step is redone on resume) */
+export async function pauseJob(jobId: string, immediate = false): Promise<void> {
+  await fetch(`${BASE}/jobs/${jobId}/pause`, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify({ immediate }),
+  })
+}
+
+export async function resumeJob(jobId: string): Promise<void> {
+  await fetch(`${BASE}/jobs/${jobId}/resume`, { method: 'POST' })
+}
+
+export async function pauseAllJobs(immediate = false): Promise<void> {
+  await fetch(`${BASE}/jobs/pause-all`, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify({ immediate }),
+  })
+}
+
+export async function resumeAllJobs(): Promise<void> {
+  await fetch(`${BASE}/jobs/resume-all`, { method: 'POST' })
+}
+
+// --- Authentication ---
+
+export async function getAuth(): Promise<AuthInfo> {
+  const res = await fetch(`${BASE}/auth`)
+  return res.json()
+}
+
+/** Sets the login cookie; rejects with the server message on wrong credentials */
+export async function login(username: string, password: string): Promise<string> {
+  const res = await fetch(`${BASE}/auth/login`, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify({ username, password }),
+  })
+  const data = await res.json()
+  if (!res.ok) throw new Error(data.error || `HTTP ${res.status}`)
+  return data.user
+}
+
+export async function logout(): Promise<void> {
+  await fetch(`${BASE}/auth/logout`, { method: 'POST' })
+}
+
+export async function getApiTokens(): Promise<ApiToken[]> {
+  const res = await fetch(`${BASE}/auth/tokens`)
+  return res.json()
+}
+
+/** The returned `token` is shown once, it cannot be read back later */
+export async function createApiToken(name: string): Promise<ApiToken & { token: string }> {
+  const res = await fetch(`${BASE}/auth/tokens`, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify({ name }),
+  })
+  const data = await res.json()
+  if (!res.ok) throw new Error(data.error || `HTTP ${res.status}`)
+  return data
+}
+
+export async function deleteApiToken(id: string): Promise<void> {
+  await fetch(`${BASE}/auth/tokens/${id}`, { method: 'DELETE' })
+}
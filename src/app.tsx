@@ -6,20 +6,63 @@ import { JobList } from './components/JobList'
 import { BeforeAfter } from './components/BeforeAfter'
 import { MaskEditor } from './components/MaskEditor'
 import { CropEditor } from './components/CropEditor'
+import { ModelPicker } from './components/ModelPicker'
 import { Tutorial } from './components/Tutorial'
+import { VariantEditor, buildBranches } from './components/VariantEditor'
+import { PresetBar } from './components/PresetBar'
+import { ApiTokens } from './components/ApiTokens'
+import { Webhooks } from './components/Webhooks'
 import * as api from './api'
-import type { Photo, Job, StepKey, StepInfo } from './types'
+import type { Photo, RejectedFile, Job, StepKey, StepInfo, StepParams, Preset, AuthInfo, StorageUsage, HeartbeatAction } from './types'
+import type { Variant } from './components/VariantEditor'
 
-export function App() {
+const ARCHIVE_EXTENSIONS = ['.zip', '.tar', '.tar.gz', '.tgz']
+
+const STATUS_ORDER: Record<string, number> = { waiting_input: -1, processing: 0, pending: 1, paused: 2, completed: 3, failed: 3, cancelled: 3 }
+
+/** Same ordering as GET /api/jobs: active first, pending by priority, then most recent */
+function sortJobs(list: Job[]): Job[] {
+  return [...list].sort((a, b) => {
+    const sa = STATUS_ORDER[a.status] ?? 4
+    const sb = STATUS_ORDER[b.status] ?? 4
+    if (sa !== sb) return sa - sb
+    if (a.status === 'pending' && b.status === 'pending') return (a.priority || 0) - (b.priority || 0)
+    return new Date(b.createdAt).getTime() - new Date(a.createdAt).getTime()
+  })
+}
+
+/** Insert or replace jobs by id */
+function mergeJobs(prev: Job[], incoming: Job[]): Job[] {
+  const byId = new Map(incoming.map((j) => [j.id, j]))
+  const merged = prev.map((j) => byId.get(j.id) || j)
+  const known = new Set(prev.map((j) => j.id))
+  const added = incoming.filter((j) => !known.has(j.id))
+  return sortJobs([...added, ...merged])
+}
+
+function formatGb(bytes: number): string {
+  return (bytes / 1024 ** 3).toFixed(bytes < 10 * 1024 ** 3 ? 1 : 0)
+}
+
+export function App({ auth }: { auth?: AuthInfo }) {
   const [photos, setPhotos] = useState<Photo[]>([])
   const [jobs, setJobs] = useState<Job[]>([])
   const [steps, setSteps] = useState<Record<string, StepInfo>>({})
   const [selectedPhotos, setSelectedPhotos] = useState<Set<string>>(new Set())
   const [selectedSteps, setSelectedSteps] = useState<Set<StepKey>>(new Set())
   const [uploading, setUploading] = useState(false)
+  // Result of the last archive upload (refused entries are listed)
+  const [archiveReport, setArchiveReport] = useState<{ count: number; rejected: RejectedFile[] } | null>(null)
   const [comparing, setComparing] = useState<Job | null>(null)
   const [device, setDevice] = useState<string>('cpu')
+  const [storage, setStorage] = useState<StorageUsage | null>(null)
   const [modelChoices, setModelChoices] = useState<Record<string, string>>({})
+  const [variants, setVariants] = useState<Variant[]>([])
+  // Comparison mode: step → models to run side by side
+  const [compareChoices, setCompareChoices] = useState<Record<string, string[]>>({})
+  const [paramChoices, setParamChoices] = useState<StepParams>({})
+  const [presets, setPresets] = useState<Preset[]>([])
+  const [activePresetId, setActivePresetId] = useState<string | null>(null)
 
   // Waiting job editing
   const [editingJobId, setEditingJobId] = useState<string | null>(null)
@@ -28,6 +71,12 @@ export function App() {
   // Concurrency setting
   const [maxConcurrent, setMaxConcurrent] = useState(1)
   const [maxConcurrentLimit, setMaxConcurrentLimit] = useState(2)
+  // Réglages de l'instance réservés à l'admin quand l'authentification est active
+  const [settingsEditable, setSettingsEditable] = useState(true)
+  const [heartbeatAction, setHeartbeatAction] = useState<HeartbeatAction>('cancel')
+  // Bilan de ce qui s'est terminé pendant que l'onglet était fermé (jobs détachés)
+  const [awaySummary, setAwaySummary] = useState<{ completed: number; failed: number; paused: number } | null>(null)
+  const lastVisitRef = useRef(0)
 
   // Local files not yet uploaded (photo.id → File)
   const localFilesRef = useRef<Map<string, File>>(new Map())
@@ -146,6 +195,9 @@ export function App() {
     step: number, total: number, message: string
   }>({ ready: true, running: false, error: null, step: 0, total: 0, message: '' })
 
+  const [showTokens, setShowTokens] = useState(false)
+  const [showWebhooks, setShowWebhooks] = useState(false)
+
   // Persisted strokes per job (survives close/reopen of MaskEditor)
   const [savedStrokes, setSavedStrokes] = useState<Record<string, string>>({})
 
@@ -159,17 +211,16 @@ export function App() {
       }
       setModelChoices(defaults)
     })
+    api.getPresets().then(setPresets)
     api.getPhotos().then((p) => setPhotos((prev) => {
       const demoPhotos = prev.filter((x) => x.id === demoIdsRef.current.photoId)
       return [...p, ...demoPhotos]
     }))
-    api.getJobs().then((j) => setJobs((prev) => {
-      const demoJobs = prev.filter((x) => demoIdsRef.current.jobIds.includes(x.id))
-      return [...j, ...demoJobs]
-    }))
     api.getSettings().then(s => {
       setMaxConcurrent(s.maxConcurrent)
       if (s.maxConcurrentLimit) setMaxConcurrentLimit(s.maxConcurrentLimit)
+      setSettingsEditable(s.editable !== false)
+      if (s.heartbeatAction) setHeartbeatAction(s.heartbeatAction)
     })
     fetch('/api/status').then(r => r.json()).then(d => {
       setDevice(d.device || 'cpu')
@@ -198,25 +249,49 @@ export function App() {
     return () => clearInterval(timer)
   }, [setupState.ready, setupState.running, setupState.error])
 
-  // Poll jobs while any are processing
+  // Disk usage, refreshed whenever a job finishes (the quota may have evicted files)
+  const finishedCount = jobs.filter((j) => j.status === 'completed' || j.status === 'failed').length
   useEffect(() => {
-    const demoIds = new Set(demoIdsRef.current.jobIds)
-    const hasActive = jobs.some(
-      (j) => !demoIds.has(j.id) && (j.status === 'pending' || j.status === 'processing' || j.status === 'waiting_input')
-    )
-    if (!hasActive) return
-
-    const timer = setInterval(async () => {
-      const updated = await api.getJobs()
-      // Preserve demo jobs during polling
-      setJobs((prev) => {
-        const demoJobs = prev.filter((j) => demoIdsRef.current.jobIds.includes(j.id))
-        return demoJobs.length > 0 ? [...updated, ...demoJobs] : updated
-      })
-    }, 1000)
+    api.getStorage().then(setStorage).catch(() => {})
+  }, [finishedCount])
 
-    return () => clearInterval(timer)
-  }, [jobs])
+  // Last time this tab was open, saved regularly: reopening the UI reports what finished since
+  useEffect(() => {
+    try { lastVisitRef.current = Number(localStorage.getItem('last-visit')) || 0 } catch {}
+    const save = () => { try { localStorage.setItem('last-visit', String(Date.now())) } catch {} }
+    const timer = setInterval(save, 30000)
+    window.addEventListener('beforeunload', save)
+    return () => {
+      clearInterval(timer)
+      window.removeEventListener('beforeunload', save)
+    }
+  }, [])
+
+  // Live job updates (SSE) — resync the full list on each (re)connection
+  useEffect(() => {
+    return api.subscribeJobEvents(
+      (_type, job) => setJobs((prev) => mergeJobs(prev, [job])),
+      async () => {
+        const updated = await api.getJobs()
+        const since = lastVisitRef.current
+        if (since) {
+          lastVisitRef.current = 0
+          const after = (iso?: string | null) => !!iso && Date.parse(iso) > since
+          const summary = {
+            completed: updated.filter((j) => j.status === 'completed' && after(j.completedAt)).length,
+            failed: updated.filter((j) => j.status === 'failed' && after(j.failedAt)).length,
+            paused: updated.filter((j) => j.status === 'paused' && after(j.pausedAt)).length,
+          }
+          if (summary.completed || summary.failed || summary.paused) setAwaySummary(summary)
+        }
+        // Preserve demo jobs
+        setJobs((prev) => {
+          const demoJobs = prev.filter((j) => demoIdsRef.current.jobIds.includes(j.id))
+          return demoJobs.length > 0 ? [...updated, ...demoJobs] : updated
+        })
+      }
+    )
+  }, [])
 
   // Auto-remove photos when all their jobs are done
   useEffect(() => {
@@ -250,19 +325,41 @@ export function App() {
   useEffect(() => {
     if (!autoDownload) return
     for (const job of jobs) {
-      if (job.status === 'completed' && job.result && !downloadedJobsRef.current.has(job.id)) {
+      // Un job avec des branches n'est qu'une base commune : seules les variantes sont finales
+      if (job.branches?.length) continue
+      if (job.status === 'completed' && job.result && !job.resultEvicted && !downloadedJobsRef.current.has(job.id)) {
         downloadedJobsRef.current.add(job.id)
         const name = job.photoName.replace(/\.[^.]+$/, '')
+        const suffix = job.branchLabel ? job.branchLabel.replace(/[^\w]+/g, '_') : 'final'
         const a = document.createElement('a')
         a.href = job.result
-        a.download = `${name}_final.jpg`
+        a.download = `${name}_${suffix}.jpg`
         a.click()
       }
     }
   }, [jobs, autoDownload])
 
+  // Albums: unpacked by the server right away, no local preview possible
+  const uploadArchives = useCallback(async (archives: File[]) => {
+    setUploading(true)
+    try {
+      const { photos: added, rejected } = await api.uploadPhotos(archives)
+      setPhotos(prev => [...prev, ...added])
+      setSelectedPhotos(prev => {
+        const n = new Set(prev)
+        for (const p of added) n.add(p.id)
+        return n
+      })
+      setArchiveReport({ count: added.length, rejected })
+    } finally {
+      setUploading(false)
+    }
+  }, [])
+
   const handleUpload = useCallback((files: File[]) => {
     const allowed = ['.jpg', '.jpeg', '.png', '.webp', '.tiff', '.bmp']
+    const archives = files.filter(f => ARCHIVE_EXTENSIONS.some(ext => f.name.toLowerCase().endsWith(ext)))
+    if (archives.length) uploadArchives(archives)
     const valid = files.filter(f => allowed.some(ext => f.name.toLowerCase().endsWith(ext)))
     if (!valid.length) return
 
@@ -283,7 +380,7 @@ export function App() {
       for (const p of newPhotos) n.add(p.id)
       return n
     })
-  }, [])
+  }, [uploadArchives])
 
   // Full-page drag & drop detection
   useEffect(() => {
@@ -302,7 +399,8 @@ export function App() {
       e.preventDefault()
       pageDragCountRef.current = 0
       setPageDrag(false)
-      const files = Array.from(e.dataTransfer?.files || []).filter((f) => f.type.startsWith('image/'))
+      const files = Array.from(e.dataTransfer?.files || []).filter((f) =>
+        f.type.startsWith('image/') || ARCHIVE_EXTENSIONS.some(ext => f.name.toLowerCase().endsWith(ext)))
       if (files.length) handleUpload(files)
     }
     window.addEventListener('dragenter', onDragEnter)
@@ -343,6 +441,11 @@ export function App() {
     })
   }, [photos])
 
+  const pinPhoto = useCallback(async (id: string, pinned: boolean) => {
+    const updated = await api.pinPhoto(id, pinned)
+    setPhotos(prev => prev.map(p => p.id === id ? { ...p, pinned: updated.pinned } : p))
+  }, [])
+
   const clearPhotos = useCallback(async () => {
     // Clean up blob URLs
     for (const photo of photos) {
@@ -376,6 +479,30 @@ export function App() {
     setModelChoices((prev) => ({ ...prev, [step]: model }))
   }, [])
 
+  const changeCompare = useCallback((step: string, models: string[] | null) => {
+    setCompareChoices((prev) => {
+      const { [step]: _, ...rest } = prev
+      return models ? { ...rest, [step]: models } : rest
+    })
+  }, [])
+
+  const changeParam = useCallback((step: string, param: string, value: number) => {
+    setParamChoices((prev) => ({ ...prev, [step]: { ...prev[step], [param]: value } }))
+  }, [])
+
+  const applyPreset = useCallback((preset: Preset) => {
+    setSelectedSteps(new Set(preset.steps.filter((s) => steps[s])))
+    setModelChoices((prev) => ({ ...prev, ...preset.options }))
+    setParamChoices(preset.params || {})
+    setCompareChoices({})
+    setActivePresetId(preset.id)
+  }, [steps])
+
+  const reloadPresets = useCallback((activeId?: string | null) => {
+    api.getPresets().then(setPresets)
+    if (activeId !== undefined) setActivePresetId(activeId)
+  }, [])
+
   const selectAll = useCallback(() => {
     setSelectedPhotos((prev) => {
       if (prev.size === photos.length) return new Set()
@@ -384,7 +511,8 @@ export function App() {
   }, [photos])
 
   const launchJobs = useCallback(async () => {
-    if (!selectedPhotos.size || !selectedSteps.size) return
+    const branches = buildBranches(variants)
+    if (!selectedPhotos.size || (!selectedSteps.size && !branches.length)) return
 
     const options: Record<string, string> = {}
     for (const step of selectedSteps) {
@@ -392,6 +520,14 @@ export function App() {
     }
 
     const stepsToRun = STEP_ORDER.filter(s => selectedSteps.has(s))
+    const params: StepParams = {}
+    for (const step of stepsToRun) {
+      if (paramChoices[step]) params[step] = paramChoices[step]
+    }
+    const compare: Record<string, string[]> = {}
+    for (const step of stepsToRun) {
+      if ((compareChoices[step]?.length || 0) > 1) compare[step] = compareChoices[step]
+    }
 
     // Upload local photos first (in batches of 5)
     const localIds = [...selectedPhotos].filter(id => localFilesRef.current.has(id))
@@ -404,9 +540,14 @@ export function App() {
         for (let i = 0; i < localIds.length; i += BATCH) {
           const batch = localIds.slice(i, i + BATCH)
           const files = batch.map(id => localFilesRef.current.get(id)!)
-          const uploaded = await api.uploadPhotos(files)
-          batch.forEach((localId, idx) => {
-            idMap.set(localId, uploaded[idx].id)
+          const { photos: uploaded } = await api.uploadPhotos(files)
+          // Match by name: a file refused by the server leaves no gap in the response
+          const pending = [...uploaded]
+          batch.forEach((localId) => {
+            const file = localFilesRef.current.get(localId)!
+            const idx = pending.findIndex(u => u.originalName === file.name)
+            if (idx < 0) return
+            idMap.set(localId, pending.splice(idx, 1)[0].id)
             // Clean up local state
             const oldPhoto = photos.find(p => p.id === localId) as any
             if (oldPhoto?._blobUrl) URL.revokeObjectURL(oldPhoto._blobUrl)
@@ -428,10 +569,12 @@ export function App() {
     // Map selected IDs to server IDs
     const photoIds = [...selectedPhotos].map(id => idMap.get(id) || id)
 
-    const newJobs = await api.createJobs(photoIds, stepsToRun, options)
-    setJobs(prev => [...newJobs, ...prev])
+    const newJobs = await api.createJobs(photoIds, stepsToRun, options, undefined, undefined,
+      branches.length ? branches : undefined, Object.keys(compare).length ? compare : undefined,
+      Object.keys(params).length ? params : undefined)
+    setJobs(prev => mergeJobs(prev, newJobs))
     setSelectedPhotos(new Set())
-  }, [selectedPhotos, selectedSteps, modelChoices, photos])
+  }, [selectedPhotos, selectedSteps, modelChoices, paramChoices, compareChoices, variants, photos])
 
   // --- Waiting job editor handlers ---
 
@@ -453,6 +596,12 @@ export function App() {
     setEditingJobId(null)
   }, [editingJobId])
 
+  const handleWaitingChoose = useCallback(async (choice: string) => {
+    if (!editingJobId) return
+    await api.submitJobInput(editingJobId, { choice })
+    setEditingJobId(null)
+  }, [editingJobId])
+
   const handleWaitingSkip = useCallback(async () => {
     if (!editingJobId) return
     await api.skipJobStep(editingJobId)
@@ -492,15 +641,20 @@ export function App() {
     await api.updateSettings({ maxConcurrent: value })
   }, [])
 
+  const handleHeartbeatActionChange = useCallback(async (value: HeartbeatAction) => {
+    setHeartbeatAction(value)
+    await api.updateSettings({ heartbeatAction: value })
+  }, [])
+
   const handleReorder = useCallback(async (orderedPendingIds: string[]) => {
     // Optimistic: reorder local jobs
     setJobs((prev) => {
       const pendingMap = new Map(prev.filter(j => j.status === 'pending').map(j => [j.id, j]))
       const reordered = orderedPendingIds.map(id => pendingMap.get(id)!).filter(Boolean)
       const nonPending = prev.filter(j => j.status !== 'pending')
-      // Keep waiting/processing first, then reordered pending, then completed/failed
+      // Keep waiting/processing first, then reordered pending, then paused/completed/failed
       const active = nonPending.filter(j => j.status === 'waiting_input' || j.status === 'processing')
-      const done = nonPending.filter(j => j.status === 'completed' || j.status === 'failed')
+      const done = nonPending.filter(j => j.status !== 'waiting_input' && j.status !== 'processing')
       return [...active, ...reordered, ...done]
     })
     await api.reorderJobs(orderedPendingIds)
@@ -521,9 +675,19 @@ export function App() {
     })
   }, [])
 
-  const canLaunch = selectedPhotos.size > 0 && selectedSteps.size > 0
+  const completedJobIds = jobs.filter(j => j.status === 'completed' && !j.id.startsWith('__demo')).map(j => j.id)
+  const selectedOrder = STEP_ORDER.filter(s => selectedSteps.has(s))
+  const currentSelection = {
+    steps: selectedOrder,
+    options: Object.fromEntries(selectedOrder.filter(s => modelChoices[s]).map(s => [s, modelChoices[s]])),
+    params: Object.fromEntries(selectedOrder.filter(s => paramChoices[s]).map(s => [s, paramChoices[s]])),
+  }
+  const canLaunch = selectedPhotos.size > 0 && (selectedSteps.size > 0 || variants.length > 0)
   const activeJobs = jobs.filter(j => j.status === 'processing' || j.status === 'pending' || j.status === 'waiting_input').length
   const editingJob = editingJobId ? jobs.find(j => j.id === editingJobId) : null
+  const lastResult = (editingJob?.stepResults || []).slice(-1)[0]
+  const pendingComparison = editingJob?.status === 'waiting_input' && lastResult?.comparison && !lastResult.result
+    && lastResult.step === editingJob.waitingStep ? lastResult : null
 
   return (
     <div class="h-screen flex flex-col bg-zinc-950 text-zinc-100">
@@ -573,9 +737,35 @@ export function App() {
               {activeJobs} job{activeJobs > 1 ? 's' : ''} en cours
             </span>
           )}
+          {storage?.quota && (
+            <span
+              class={`text-[10px] px-2 py-0.5 rounded bg-zinc-800 ${storage.used > storage.quota * 0.9 ? 'text-amber-400' : 'text-zinc-500'}`}
+              title={`Photos ${formatGb(storage.uploads)} Go · résultats ${formatGb(storage.results)} Go · épinglés ${formatGb(storage.pinned)} Go`}
+            >
+              {formatGb(storage.used)} / {formatGb(storage.quota)} Go
+            </span>
+          )}
           <span class="text-[10px] px-2 py-0.5 rounded bg-zinc-800 text-zinc-500 uppercase tracking-wider">
             {device === 'mps' ? 'GPU Metal' : device === 'cuda' ? 'GPU CUDA' : 'CPU'}
           </span>
+          {auth?.enabled && (
+            <>
+              <span class="text-[11px] text-zinc-500">{auth.user}</span>
+              <button
+                onClick={() => setShowTokens(true)}
+                class="text-[11px] text-zinc-500 hover:text-zinc-300 transition-colors"
+                title="Jetons d'API pour les scripts"
+              >
+                API
+              </button>
+              <button
+                onClick={async () => { await api.logout(); location.reload() }}
+                class="text-[11px] text-zinc-500 hover:text-zinc-300 transition-colors"
+              >
+                Déconnexion
+              </button>
+            </>
+          )}
           <button
             onClick={() => setShowTutorial(true)}
             class="w-7 h-7 rounded-full bg-zinc-800 hover:bg-zinc-700 text-zinc-400 hover:text-amber-400 text-sm font-medium transition-colors flex items-center justify-center"
@@ -620,12 +810,32 @@ export function App() {
             {uploading && (
               <p class="text-xs text-zinc-400 animate-pulse text-center">Upload en cours...</p>
             )}
+            {archiveReport && !uploading && (
+              <div class="rounded border border-zinc-800 bg-zinc-900/50 px-3 py-2 text-[11px] text-zinc-400 space-y-0.5">
+                <div class="flex items-center justify-between gap-2">
+                  <span>
+                    {archiveReport.count} photo{archiveReport.count > 1 ? 's' : ''} extraite{archiveReport.count > 1 ? 's' : ''}
+                    {archiveReport.rejected.length > 0 && `, ${archiveReport.rejected.length} fichier${archiveReport.rejected.length > 1 ? 's' : ''} refusé${archiveReport.rejected.length > 1 ? 's' : ''}`}
+                  </span>
+                  <button onClick={() => setArchiveReport(null)} class="text-zinc-600 hover:text-zinc-300 transition-colors">✕</button>
+                </div>
+                {archiveReport.rejected.slice(0, 20).map((r, i) => (
+                  <p key={i} class="truncate text-[10px] text-amber-400/70" title={`${r.archive ? r.archive + ' › ' : ''}${r.name} : ${r.reason}`}>
+                    {r.name} : {r.reason}
+                  </p>
+                ))}
+                {archiveReport.rejected.length > 20 && (
+                  <p class="text-[10px] text-zinc-600">… et {archiveReport.rejected.length - 20} autre{archiveReport.rejected.length - 20 > 1 ? 's' : ''}</p>
+                )}
+              </div>
+            )}
             <div data-tour="photo-grid">
               <PhotoGrid
                 photos={photos}
                 selected={selectedPhotos}
                 onToggle={togglePhoto}
                 onDelete={deletePhoto}
+                onPin={pinPhoto}
               />
             </div>
           </div>
@@ -638,6 +848,17 @@ export function App() {
               <h2 class="text-xs font-medium text-zinc-400 uppercase tracking-wider mb-3">
                 Étapes de restauration
               </h2>
+              {Object.keys(steps).length > 0 && (
+                <div class="mb-3">
+                  <PresetBar
+                    presets={presets}
+                    current={currentSelection}
+                    activeId={activePresetId}
+                    onApply={applyPreset}
+                    onChanged={reloadPresets}
+                  />
+                </div>
+              )}
               {Object.keys(steps).length > 0 && (
                 <StepSelector
                   steps={steps}
@@ -646,10 +867,26 @@ export function App() {
                   onToggleAll={toggleAllSteps}
                   modelChoices={modelChoices}
                   onModelChange={changeModel}
+                  compareChoices={compareChoices}
+                  onCompareChange={changeCompare}
+                  paramChoices={paramChoices}
+                  onParamChange={changeParam}
                 />
               )}
             </div>
 
+            {Object.keys(steps).length > 0 && (
+              <div>
+                <h2 class="text-xs font-medium text-zinc-400 uppercase tracking-wider mb-1">
+                  Variantes
+                </h2>
+                <p class="text-[10px] text-zinc-600 mb-2">
+                  Après les étapes ci-dessus, une sortie par variante (étapes communes calculées une seule fois)
+                </p>
+                <VariantEditor steps={steps} variants={variants} onChange={setVariants} />
+              </div>
+            )}
+
             {/* Selected summary */}
             <div class="text-center text-xs text-zinc-500">
               {selectedPhotos.size > 0 ? (
@@ -662,6 +899,11 @@ export function App() {
                   {STEP_ORDER.filter(s => selectedSteps.has(s)).map(s => steps[s]?.name).filter(Boolean).join(' → ')}
                 </span>
               )}
+              {variants.length > 0 && (
+                <span class="block mt-0.5 text-violet-300/60">
+                  ⑂ {variants.length} variante{variants.length > 1 ? 's' : ''} par photo
+                </span>
+              )}
             </div>
 
             {/* Launch */}
@@ -704,7 +946,24 @@ export function App() {
                 Jobs ({jobs.length})
               </h2>
               <div class="flex items-center gap-3">
-                {jobs.some(j => j.status === 'processing' || j.status === 'pending' || j.status === 'waiting_input') && (
+                {jobs.some(j => (j.status === 'processing' && !j.pauseRequested) || j.status === 'pending') && (
+                  <button
+                    onClick={async (e) => { await api.pauseAllJobs(e.shiftKey); api.getJobs().then(setJobs) }}
+                    class="text-[11px] text-yellow-300/60 hover:text-yellow-300 transition-colors"
+                    title="Après l'étape en cours (Maj+clic : tout de suite, les étapes interrompues seront refaites)"
+                  >
+                    Tout mettre en pause
+                  </button>
+                )}
+                {jobs.some(j => j.status === 'paused' || j.pauseRequested) && (
+                  <button
+                    onClick={async () => { await api.resumeAllJobs(); api.getJobs().then(setJobs) }}
+                    class="text-[11px] text-emerald-300/60 hover:text-emerald-300 transition-colors"
+                  >
+                    Tout reprendre
+                  </button>
+                )}
+                {jobs.some(j => j.status === 'processing' || j.status === 'pending' || j.status === 'waiting_input' || j.status === 'paused') && (
                   <button
                     onClick={async () => { await api.cancelAllJobs(); api.getJobs().then(setJobs) }}
                     class="text-[11px] text-red-400/60 hover:text-red-400 transition-colors"
@@ -712,9 +971,28 @@ export function App() {
                     Tout arrêter
                   </button>
                 )}
+                {completedJobIds.length > 0 && (
+                  <span class="text-[11px] text-zinc-500">
+                    <a
+                      href={api.exportJobsUrl(completedJobIds)}
+                      class="hover:text-zinc-300 transition-colors"
+                      title="Originaux, résultats finaux et manifest.json"
+                    >
+                      Exporter ZIP
+                    </a>
+                    {' '}
+                    <a
+                      href={api.exportJobsUrl(completedJobIds, true)}
+                      class="text-zinc-600 hover:text-zinc-300 transition-colors"
+                      title="Avec l'image de chaque étape"
+                    >
+                      (+ étapes)
+                    </a>
+                  </span>
+                )}
                 {jobs.some(j => j.status === 'completed' || j.status === 'failed' || j.status === 'cancelled') && (
                   <button
-                    onClick={() => setJobs(prev => prev.filter(j => j.status === 'processing' || j.status === 'pending' || j.status === 'waiting_input'))}
+                    onClick={() => setJobs(prev => prev.filter(j => j.status === 'processing' || j.status === 'pending' || j.status === 'waiting_input' || j.status === 'paused'))}
                     class="text-[11px] text-zinc-500 hover:text-zinc-300 transition-colors"
                   >
                     Vider terminés
@@ -729,11 +1007,47 @@ export function App() {
                 min="1"
                 max={maxConcurrentLimit}
                 value={maxConcurrent}
+                disabled={!settingsEditable}
                 onInput={(e) => handleConcurrencyChange(Number((e.target as HTMLInputElement).value))}
-                class="flex-1 h-1 accent-amber-500 cursor-pointer"
+                class="flex-1 h-1 accent-amber-500 cursor-pointer disabled:cursor-not-allowed disabled:opacity-50"
               />
               <span class="text-[10px] text-zinc-400 w-3 text-center">{maxConcurrent}</span>
             </div>
+            <div class="flex items-center gap-2">
+              <span class="text-[10px] text-zinc-500 whitespace-nowrap">Onglet fermé</span>
+              <select
+                value={heartbeatAction}
+                disabled={!settingsEditable}
+                onChange={(e) => handleHeartbeatActionChange((e.target as HTMLSelectElement).value as HeartbeatAction)}
+                class="min-w-0 flex-1 cursor-pointer rounded border border-zinc-700 bg-zinc-900 px-2 py-0.5 text-[11px] text-zinc-300 focus:border-amber-400/60 focus:outline-none disabled:cursor-not-allowed disabled:opacity-50"
+                title="Ce que deviennent les jobs quand plus aucun onglet n'est ouvert (les jobs marqués ☾ continuent toujours)"
+              >
+                <option value="cancel">Annuler les jobs</option>
+                <option value="pause">Mettre les jobs en pause</option>
+                <option value="none">Continuer sans moi</option>
+              </select>
+              {settingsEditable && (
+                <button
+                  onClick={() => setShowWebhooks(true)}
+                  class="text-[11px] text-zinc-500 hover:text-zinc-300 transition-colors"
+                  title="URLs notifiées quand un job se termine, échoue ou attend une saisie"
+                >
+                  Webhooks
+                </button>
+              )}
+            </div>
+            {awaySummary && (
+              <div class="flex items-center justify-between gap-2 rounded border border-emerald-400/20 bg-emerald-400/5 px-2 py-1 text-[11px] text-zinc-300">
+                <span>
+                  Pendant votre absence : {[
+                    awaySummary.completed && `${awaySummary.completed} terminé${awaySummary.completed > 1 ? 's' : ''}`,
+                    awaySummary.failed && `${awaySummary.failed} en erreur`,
+                    awaySummary.paused && `${awaySummary.paused} mis en pause`,
+                  ].filter(Boolean).join(', ')}
+                </span>
+                <button onClick={() => setAwaySummary(null)} class="text-zinc-600 hover:text-zinc-300 transition-colors">✕</button>
+              </div>
+            )}
           </div>
           <div data-tour="jobs-list" class="flex-1 overflow-y-auto p-3">
             <JobList jobs={jobs} steps={steps} onCompare={setComparing} onImport={handleImport} onReorder={handleReorder} onEdit={handleEdit} />
@@ -764,6 +1078,22 @@ export function App() {
         />
       )}
 
+      {/* Model comparison overlay (waiting_input) */}
+      {editingJob && pendingComparison && (
+        <ModelPicker
+          stepName={steps[pendingComparison.step]?.name || pendingComparison.step}
+          step={steps[pendingComparison.step]}
+          inputUrl={editingJob.waitingImage}
+          candidates={pendingComparison.comparison!}
+          photoLabel={editingJob.photoName}
+          onChoose={handleWaitingChoose}
+          onSkip={handleWaitingSkip}
+          onBack={handleWaitingBack}
+          canGoBack={editingJob.canGoBack}
+          onClose={handleEditorClose}
+        />
+      )}
+
       {/* Mask editor overlay (waiting_input) */}
       {editingJob && editingJob.status === 'waiting_input' && editingJob.waitingStep === 'inpaint' && editingJob.waitingImage && (
         <MaskEditor
@@ -790,6 +1120,12 @@ export function App() {
         />
       )}
 
+      {/* API tokens for scripts */}
+      {showTokens && <ApiTokens onClose={() => setShowTokens(false)} />}
+
+      {/* Webhook URLs notified of job events */}
+      {showWebhooks && <Webhooks onClose={() => setShowWebhooks(false)} />}
+
       {/* Full-page drag overlay */}
       {pageDrag && (
         <div class="fixed inset-0 z-[60] bg-black/70 flex items-center justify-center pointer-events-none">
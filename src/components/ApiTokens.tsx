@@ -0,0 +1,101 @@
+import { useState, useEffect } from 'preact/hooks'
+import * as api from '../api'
+import type { ApiToken } from '../types'
+
+interface Props {
+  onClose: () => void
+}
+
+/** Bearer tokens for scripts (`Authorization: Bearer …`) — create, copy once, revoke */
+export function ApiTokens({ onClose }: Props) {
+  const [tokens, setTokens] = useState<ApiToken[]>([])
+  const [name, setName] = useState('')
+  const [created, setCreated] = useState<string | null>(null)
+  const [error, setError] = useState<string | null>(null)
+
+  const reload = () => api.getApiTokens().then(setTokens)
+  useEffect(() => { reload() }, [])
+
+  useEffect(() => {
+    const handler = (e: KeyboardEvent) => { if (e.key === 'Escape') onClose() }
+    window.addEventListener('keydown', handler)
+    return () => window.removeEventListener('keydown', handler)
+  }, [onClose])
+
+  const create = async () => {
+    try {
+      const { token } = await api.createApiToken(name.trim())
+      setCreated(token)
+      setName('')
+      setError(null)
+      reload()
+    } catch (err) {
+      setError((err as Error).message)
+    }
+  }
+
+  const revoke = async (id: string) => {
+    await api.deleteApiToken(id)
+    reload()
+  }
+
+  const formatDate = (iso: string | null) => iso ? new Date(iso).toLocaleString() : 'jamais'
+
+  return (
+    <div class="fixed inset-0 z-50 bg-black/70 flex items-center justify-center" onClick={onClose}>
+      <div class="w-[28rem] max-w-[95vw] rounded-lg border border-zinc-800 bg-zinc-950 p-4 space-y-3" onClick={(e) => e.stopPropagation()}>
+        <div class="flex items-center justify-between">
+          <h2 class="text-sm font-medium text-zinc-200">Jetons d'API</h2>
+          <button onClick={onClose} class="text-zinc-500 hover:text-zinc-200 transition-colors">✕</button>
+        </div>
+        <p class="text-[11px] text-zinc-500">
+          Pour les scripts : en-tête <code class="text-zinc-400">Authorization: Bearer &lt;jeton&gt;</code>
+        </p>
+
+        <form class="flex items-center gap-1" onSubmit={(e) => { e.preventDefault(); create() }}>
+          <input
+            value={name}
+            onInput={(e) => setName((e.target as HTMLInputElement).value)}
+            placeholder="Nom (ex. script d'archivage)"
+            maxLength={80}
+            class="min-w-0 flex-1 rounded border border-zinc-700 bg-zinc-900 px-2 py-1 text-xs text-zinc-200 focus:border-amber-400/60 focus:outline-none"
+          />
+          <button
+            type="submit"
+            disabled={!name.trim()}
+            class="rounded px-2 py-1 text-[11px] text-zinc-400 hover:bg-zinc-800 hover:text-zinc-200 transition-colors disabled:opacity-30"
+          >
+            Créer
+          </button>
+        </form>
+        {error && <p class="text-[11px] text-red-400/80">{error}</p>}
+        {created && (
+          <div class="rounded border border-amber-400/30 bg-amber-400/5 p-2 space-y-1">
+            <p class="text-[10px] text-amber-300/80">Copiez-le maintenant, il ne sera plus affiché :</p>
+            <code class="block break-all select-all text-[11px] text-zinc-200">{created}</code>
+          </div>
+        )}
+
+        <div class="max-h-64 overflow-y-auto divide-y divide-zinc-800/60">
+          {tokens.length === 0 && <p class="py-2 text-[11px] text-zinc-600">Aucun jeton</p>}
+          {tokens.map((t) => (
+            <div key={t.id} class="flex items-center justify-between gap-2 py-1.5">
+              <div class="min-w-0">
+                <p class="truncate text-xs text-zinc-300">{t.name} <span class="text-zinc-600">…{t.hint}</span></p>
+                <p class="text-[10px] text-zinc-600">
+                  {t.user} · créé {formatDate(t.createdAt)} · utilisé {formatDate(t.lastUsedAt)}
+                </p>
+              </div>
+              <button
+                onClick={() => revoke(t.id)}
+                class="flex-shrink-0 text-[11px] text-red-400/60 hover:text-red-400 transition-colors"
+              >
+                Révoquer
+              </button>
+            </div>
+          ))}
+        </div>
+      </div>
+    </div>
+  )
+}
@@ -24,13 +24,13 @@ export function Dropzone({ onFiles }: Props) {
       <input
         ref={inputRef}
         type="file"
-        accept="image/*"
+        accept="image/*,.zip,.tar,.tgz,.gz"
         multiple
         class="hidden"
         onChange={handleChange}
       />
       <p class="text-zinc-400 text-sm">Cliquez pour ajouter des photos</p>
-      <p class="text-zinc-600 text-[10px] mt-1">JPG, PNG, WebP, TIFF, BMP</p>
+      <p class="text-zinc-600 text-[10px] mt-1">JPG, PNG, WebP, TIFF, BMP — ou un album en ZIP / TAR</p>
     </div>
   )
 }
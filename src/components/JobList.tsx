@@ -1,6 +1,6 @@
 import { useState, useEffect, useRef } from 'preact/hooks'
 import * as api from '../api'
-import type { Job, StepInfo, StepKey } from '../types'
+import type { Job, JobEta, StepInfo, StepKey } from '../types'
 
 interface Props {
   jobs: Job[]
@@ -15,6 +15,7 @@ const STATUS_STYLES: Record<string, string> = {
   pending: 'text-zinc-400 bg-zinc-800',
   processing: 'text-blue-300 bg-blue-500/20',
   waiting_input: 'text-orange-300 bg-orange-500/20',
+  paused: 'text-yellow-300 bg-yellow-500/15',
   completed: 'text-emerald-300 bg-emerald-500/20',
   failed: 'text-red-300 bg-red-500/20',
   cancelled: 'text-zinc-400 bg-zinc-700/50',
@@ -24,6 +25,7 @@ const STATUS_LABELS: Record<string, string> = {
   pending: 'File d\'attente',
   processing: 'En cours',
   waiting_input: 'Action requise',
+  paused: 'En pause',
   completed: 'Terminé',
   failed: 'Erreur',
   cancelled: 'Annulé',
@@ -295,6 +297,7 @@ function StepThumbnail({
   active,
   done,
   error,
+  unchanged,
   onClick,
   onImport,
 }: {
@@ -304,6 +307,7 @@ function StepThumbnail({
   active?: boolean
   done?: boolean
   error?: boolean
+  unchanged?: string | null
   onClick?: () => void
   onImport?: () => void
 }) {
@@ -322,6 +326,14 @@ function StepThumbnail({
           <>
             <img src={src} alt={label} class="w-full h-full object-cover group-hover:scale-110 transition-transform duration-200" />
             <div class="absolute inset-0 bg-black/0 group-hover:bg-black/30 transition-colors" />
+            {unchanged && (
+              <div
+                class="absolute top-0.5 right-0.5 px-1 rounded bg-black/70 text-[8px] text-zinc-300"
+                title={unchanged}
+              >
+                =
+              </div>
+            )}
           </>
         ) : error ? (
           <div class="w-full h-full flex items-center justify-center text-red-400 text-lg">
@@ -362,9 +374,12 @@ function ScrollableStepGallery({ job, steps, onViewerOpen, onImport }: {
   const containerRef = useRef<HTMLDivElement>(null)
   const prevStepRef = useRef<string | null>(null)
 
-  const stepResults = job.stepResults || []
+  // Une comparaison en attente de choix n'a pas encore de résultat
+  const stepResults = (job.stepResults || []).filter((sr) => sr.result)
   const completedSteps = new Set(stepResults.map((sr) => sr.step))
-  const resultMap = Object.fromEntries(stepResults.map((sr) => [sr.step, sr.result]))
+  // Intermédiaire supprimé par le quota disque : l'étape reste faite, sans vignette
+  const resultMap = Object.fromEntries(stepResults.map((sr) => [sr.step, sr.evicted ? null : sr.result]))
+  const reportMap = Object.fromEntries(stepResults.map((sr) => [sr.step, sr]))
 
   // Auto-scroll to active step when currentStep changes
   useEffect(() => {
@@ -409,6 +424,7 @@ function ScrollableStepGallery({ job, steps, onViewerOpen, onImport }: {
               active={isActive}
               done={isDone}
               error={isFailed}
+              unchanged={isDone && reportMap[step]?.noop ? reportMap[step].reason || 'Aucun changement' : null}
               onClick={isDone && src ? () => onViewerOpen(src) : undefined}
               onImport={isDone && src ? () => onImport(src) : undefined}
             />
@@ -419,6 +435,40 @@ function ScrollableStepGallery({ job, steps, onViewerOpen, onImport }: {
   )
 }
 
+/** Step reports worth reading: skipped (no-op) steps, fallback models and script warnings */
+function StepNotes({ job, steps }: { job: Job; steps: Record<string, StepInfo> }) {
+  const notes = (job.stepResults || []).flatMap((sr) => {
+    const name = steps[sr.step]?.name || sr.step
+    const out: { text: string; warn: boolean }[] = []
+    if (sr.chosen) {
+      const model = steps[sr.step]?.models?.[sr.chosen]?.name || sr.chosen
+      out.push({ text: `${name} : ${model} retenu parmi ${sr.comparison?.length} modèles`, warn: false })
+    }
+    for (const c of sr.comparison || []) {
+      if (c.error) out.push({ text: `${name} (${steps[sr.step]?.models?.[c.model]?.name || c.model}) : ${c.error}`, warn: true })
+    }
+    if (sr.fallback) {
+      const modelName = (m: string) => steps[sr.step]?.models?.[m]?.name || m
+      const failed = sr.fallback.attempts.map((a) => `${modelName(a.model)} : ${a.error}`).join(' ; ')
+      out.push({ text: `${name} : repli sur ${modelName(sr.fallback.used)} (${failed})`, warn: true })
+    }
+    if (sr.noop) out.push({ text: `${name} : ${sr.reason || 'aucun changement'}, étape ignorée`, warn: false })
+    for (const w of sr.warnings || []) out.push({ text: `${name} : ${w}`, warn: true })
+    return out
+  })
+  if (!notes.length) return null
+
+  return (
+    <div class="space-y-0.5">
+      {notes.map((n, i) => (
+        <p key={i} class={`text-[10px] truncate ${n.warn ? 'text-amber-400/70' : 'text-zinc-500'}`} title={n.text}>
+          {n.text}
+        </p>
+      ))}
+    </div>
+  )
+}
+
 function FailedActions({ job, steps }: { job: Job; steps: Record<string, StepInfo> }) {
   const [showModels, setShowModels] = useState(false)
   const failedStepInfo = job.failedStep ? steps[job.failedStep] : null
@@ -484,6 +534,37 @@ function FailedActions({ job, steps }: { job: Job; steps: Record<string, StepInf
   )
 }
 
+/** "45 s", "12 min", "1 h 05" */
+function formatDuration(seconds: number) {
+  if (seconds < 60) return `${Math.max(1, Math.round(seconds))} s`
+  const minutes = Math.round(seconds / 60)
+  if (minutes < 60) return `${minutes} min`
+  return `${Math.floor(minutes / 60)} h ${String(minutes % 60).padStart(2, '0')}`
+}
+
+function formatClock(iso: string) {
+  return new Date(iso).toLocaleTimeString('fr-FR', { hour: '2-digit', minute: '2-digit' })
+}
+
+// Estimation du serveur, d'après les durées déjà mesurées de chaque étape sur cette machine
+function EtaLine({ job, steps }: { job: Job; steps: Record<string, StepInfo> }) {
+  const eta = job.eta
+  if (!eta) return null
+  const unknown = eta.unknownSteps.map((s) => steps[s]?.name || s).join(', ')
+  if (eta.seconds === 0 && unknown) {
+    return <p class="text-[10px] text-zinc-600">Durée inconnue : {unknown} jamais chronométré ici</p>
+  }
+  const prefix = eta.waitingInput ? 'Ensuite'
+    : job.status === 'pending' && eta.startsIn > 0 ? `Démarre dans ≈ ${formatDuration(eta.startsIn)} · reste`
+    : 'Reste'
+  return (
+    <p class="text-[10px] text-zinc-500" title={unknown ? `Sans compter : ${unknown} (jamais chronométré ici)` : undefined}>
+      {prefix} ≈ {formatDuration(eta.seconds)} · fin vers {formatClock(eta.finishAt)}
+      {unknown && <span class="text-zinc-600"> (+ {unknown})</span>}
+    </p>
+  )
+}
+
 // Grip handle icon for drag
 function GripIcon() {
   return (
@@ -513,7 +594,11 @@ export function JobList({ jobs, steps, onCompare, onImport, onReorder, onEdit }:
   const waiting = jobs.filter((j) => j.status === 'waiting_input')
   const processing = jobs.filter((j) => j.status === 'processing')
   const pending = jobs.filter((j) => j.status === 'pending')
+  const paused = jobs.filter((j) => j.status === 'paused')
   const done = jobs.filter((j) => j.status === 'completed' || j.status === 'failed' || j.status === 'cancelled')
+  // Fin de la file de la session : le dernier de ses jobs actifs estimés
+  const etas = [...waiting, ...processing, ...pending].map((j) => j.eta).filter((e): e is JobEta => !!e && e.seconds > 0)
+  const lastEta = etas.reduce<JobEta | null>((last, e) => (!last || e.seconds > last.seconds ? e : last), null)
 
   const handleDragStart = (e: DragEvent, jobId: string) => {
     setDragId(jobId)
@@ -558,8 +643,8 @@ export function JobList({ jobs, steps, onCompare, onImport, onReorder, onEdit }:
     const stepResults = job.stepResults || []
     const galleryImages: ViewerImage[] = [
       { src: job.original, label: `${job.photoName} — Original` },
-      ...stepResults.map((sr) => ({
-        src: sr.result,
+      ...stepResults.filter((sr) => sr.result && !sr.evicted).map((sr) => ({
+        src: sr.result!,
         label: `${job.photoName} — ${steps[sr.step]?.name || sr.step}`,
       })),
     ]
@@ -598,7 +683,36 @@ export function JobList({ jobs, steps, onCompare, onImport, onReorder, onEdit }:
             {STATUS_LABELS[job.status]}
           </span>
           <p class="text-sm text-zinc-200 truncate flex-1">{job.photoName}</p>
-          {(job.status === 'pending' || job.status === 'processing' || job.status === 'waiting_input') && (
+          {(job.status === 'pending' || job.status === 'processing') && (
+            <button
+              onClick={(e) => job.pauseRequested ? api.resumeJob(job.id) : api.pauseJob(job.id, e.shiftKey)}
+              class={`flex-shrink-0 transition-colors ${job.pauseRequested ? 'text-yellow-300' : 'text-zinc-600 hover:text-yellow-300'}`}
+              title={job.status === 'pending' ? 'Mettre en pause'
+                : job.pauseRequested ? 'Pause après l\'étape en cours — cliquer pour annuler'
+                : 'Pause après l\'étape en cours (Maj+clic : tout de suite, l\'étape sera refaite)'}
+            >
+              <svg width="14" height="14" viewBox="0 0 14 14" fill="none" stroke="currentColor" stroke-width="1.5" stroke-linecap="round"><path d="M5 3v8M9 3v8"/></svg>
+            </button>
+          )}
+          {(job.status === 'pending' || job.status === 'processing' || job.status === 'paused') && (
+            <button
+              onClick={() => api.detachJob(job.id, !job.detached)}
+              class={`flex-shrink-0 text-xs leading-none transition-colors ${job.detached ? 'text-sky-300' : 'text-zinc-600 hover:text-sky-300'}`}
+              title={job.detached ? 'Détaché : continue onglet fermé — cliquer pour rattacher' : 'Détacher : continuer même onglet fermé'}
+            >
+              ☾
+            </button>
+          )}
+          {job.status === 'paused' && (
+            <button
+              onClick={() => api.resumeJob(job.id)}
+              class="flex-shrink-0 text-zinc-600 hover:text-emerald-300 transition-colors"
+              title="Reprendre"
+            >
+              <svg width="14" height="14" viewBox="0 0 14 14" fill="currentColor"><path d="M4 2.5v9l7.5-4.5z"/></svg>
+            </button>
+          )}
+          {(job.status === 'pending' || job.status === 'processing' || job.status === 'waiting_input' || job.status === 'paused') && (
             <button
               onClick={() => api.cancelJob(job.id)}
               class="flex-shrink-0 text-zinc-600 hover:text-red-400 transition-colors"
@@ -609,6 +723,18 @@ export function JobList({ jobs, steps, onCompare, onImport, onReorder, onEdit }:
           )}
         </div>
 
+        {/* Pipeline en arbre : variante d'un job parent, ou base commune de plusieurs variantes */}
+        {job.branchLabel && (
+          <p class="text-[10px] text-violet-300/80 truncate" title={job.branchLabel}>⑂ {job.branchLabel}</p>
+        )}
+        {job.branches && job.branches.length > 0 && (
+          <p class="text-[10px] text-violet-300/60">
+            {job.childJobIds?.length
+              ? `Base commune de ${job.childJobIds.length} variante${job.childJobIds.length > 1 ? 's' : ''}`
+              : `Puis ${job.branches.length} branche${job.branches.length > 1 ? 's' : ''}`}
+          </p>
+        )}
+
         {/* Step gallery with auto-scroll */}
         {(job.status === 'processing' || job.status === 'completed' || job.status === 'waiting_input' || job.status === 'failed' || job.status === 'cancelled') && (
           <ScrollableStepGallery
@@ -619,13 +745,19 @@ export function JobList({ jobs, steps, onCompare, onImport, onReorder, onEdit }:
           />
         )}
 
+        <StepNotes job={job} steps={steps} />
+
+        {(job.status === 'processing' || job.status === 'pending' || job.status === 'waiting_input') && (
+          <EtaLine job={job} steps={steps} />
+        )}
+
         {/* Edit button for waiting_input */}
         {job.status === 'waiting_input' && onEdit && (
           <button
             onClick={() => onEdit(job.id)}
             class="text-xs px-3 py-1.5 rounded bg-orange-500/20 text-orange-300 hover:bg-orange-500/30 transition-colors font-medium"
           >
-            Éditer — {steps[job.waitingStep!]?.name || job.waitingStep}
+            {job.stepResults?.some((sr) => sr.comparison && !sr.result) ? 'Choisir' : 'Éditer'} — {steps[job.waitingStep!]?.name || job.waitingStep}
           </button>
         )}
 
@@ -638,18 +770,30 @@ export function JobList({ jobs, steps, onCompare, onImport, onReorder, onEdit }:
                 style={{ width: `${Math.max(job.progress, 5)}%` }}
               />
             </div>
+            {job.phase && (
+              <p class="text-[10px] text-blue-300/70 mt-1 truncate">
+                {job.phase}
+                {job.stepProgress != null && job.stepProgress > 0 && ` — ${Math.round(job.stepProgress * 100)}%`}
+              </p>
+            )}
+            {job.pauseRequested && (
+              <p class="text-[10px] text-yellow-300/70 mt-1">Pause à la fin de l'étape</p>
+            )}
           </div>
         )}
 
-        {/* Pending: just show step names */}
-        {job.status === 'pending' && (
+        {/* Pending / paused: just show step names */}
+        {(job.status === 'pending' || job.status === 'paused') && (
           <p class="text-[11px] text-zinc-500">
             {job.steps.map((s) => steps[s]?.name || s).join(' \u2192 ')}
           </p>
         )}
 
         {/* Completed actions */}
-        {job.status === 'completed' && job.result && (
+        {job.status === 'completed' && job.resultEvicted && (
+          <p class="text-[10px] text-zinc-600">Résultat supprimé pour libérer de l'espace disque</p>
+        )}
+        {job.status === 'completed' && job.result && !job.resultEvicted && (
           <div class="flex gap-2 pt-1">
             <button
               onClick={() => onCompare(job)}
@@ -666,6 +810,15 @@ export function JobList({ jobs, steps, onCompare, onImport, onReorder, onEdit }:
             >
               Télécharger JPG
             </button>
+            <button
+              onClick={() => api.pinJob(job.id, !job.pinned)}
+              class={`ml-auto text-xs px-2 py-1 rounded transition-colors ${
+                job.pinned ? 'bg-amber-400/20 text-amber-300' : 'text-zinc-600 hover:text-zinc-300'
+              }`}
+              title={job.pinned ? 'Épinglé : résultat jamais supprimé automatiquement' : 'Épingler (résultat jamais supprimé automatiquement)'}
+            >
+              📌
+            </button>
           </div>
         )}
 
@@ -680,6 +833,12 @@ export function JobList({ jobs, steps, onCompare, onImport, onReorder, onEdit }:
   return (
     <>
       <div class="space-y-2">
+        {lastEta && etas.length > 1 && (
+          <p class="text-[10px] text-zinc-500">
+            {etas.length} jobs en cours ou en attente — fin estimée vers {formatClock(lastEta.finishAt)} (≈ {formatDuration(lastEta.seconds)})
+          </p>
+        )}
+
         {/* Waiting for input (top priority) */}
         {waiting.map((job) => renderJobCard(job))}
 
@@ -695,8 +854,14 @@ export function JobList({ jobs, steps, onCompare, onImport, onReorder, onEdit }:
         )}
         {pending.map((job) => renderJobCard(job, { draggable: true }))}
 
+        {/* Paused jobs */}
+        {paused.length > 0 && (pending.length > 0 || processing.length > 0 || waiting.length > 0) && (
+          <div class="border-t border-zinc-800 pt-2 mt-2" />
+        )}
+        {paused.map((job) => renderJobCard(job))}
+
         {/* Completed/failed jobs */}
-        {done.length > 0 && (processing.length > 0 || pending.length > 0 || waiting.length > 0) && (
+        {done.length > 0 && (processing.length > 0 || pending.length > 0 || waiting.length > 0 || paused.length > 0) && (
           <div class="border-t border-zinc-800 pt-2 mt-2" />
         )}
         {done.map((job) => renderJobCard(job))}
@@ -0,0 +1,71 @@
+import { useState } from 'preact/hooks'
+import * as api from '../api'
+import type { AuthInfo } from '../types'
+
+interface Props {
+  mode: AuthInfo['mode']
+  onLoggedIn: (user: string) => void
+}
+
+export function Login({ mode, onLoggedIn }: Props) {
+  const [username, setUsername] = useState('')
+  const [password, setPassword] = useState('')
+  const [error, setError] = useState<string | null>(null)
+  const [busy, setBusy] = useState(false)
+
+  const submit = async () => {
+    setBusy(true)
+    setError(null)
+    try {
+      onLoggedIn(await api.login(username.trim(), password))
+    } catch (err) {
+      setError((err as Error).message)
+      setPassword('')
+    } finally {
+      setBusy(false)
+    }
+  }
+
+  const inputClass = 'w-full rounded border border-zinc-700 bg-zinc-900 px-3 py-2 text-sm text-zinc-200 focus:border-amber-400/60 focus:outline-none'
+
+  return (
+    <div class="h-screen flex items-center justify-center bg-zinc-950 text-zinc-100">
+      <form
+        class="w-72 space-y-3"
+        onSubmit={(e) => { e.preventDefault(); submit() }}
+      >
+        <div class="mb-5 text-center">
+          <h1 class="text-lg font-bold text-zinc-100">Old Photos Restorer</h1>
+          <p class="text-xs text-zinc-500">Connexion requise</p>
+        </div>
+        {mode === 'users' && (
+          <input
+            autoFocus
+            value={username}
+            onInput={(e) => setUsername((e.target as HTMLInputElement).value)}
+            placeholder="Utilisateur"
+            autoComplete="username"
+            class={inputClass}
+          />
+        )}
+        <input
+          autoFocus={mode !== 'users'}
+          type="password"
+          value={password}
+          onInput={(e) => setPassword((e.target as HTMLInputElement).value)}
+          placeholder="Mot de passe"
+          autoComplete="current-password"
+          class={inputClass}
+        />
+        {error && <p class="text-xs text-red-400/80">{error}</p>}
+        <button
+          type="submit"
+          disabled={busy || !password}
+          class="w-full rounded bg-amber-500 px-3 py-2 text-sm font-medium text-zinc-900 hover:bg-amber-400 transition-colors disabled:opacity-40"
+        >
+          {busy ? 'Connexion…' : 'Se connecter'}
+        </button>
+      </form>
+    </div>
+  )
+}
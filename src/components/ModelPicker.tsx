@@ -0,0 +1,136 @@
+import { useState, useEffect } from 'preact/hooks'
+import type { ComparisonCandidate, StepInfo } from '../types'
+
+interface Props {
+  stepName: string
+  step: StepInfo | undefined
+  inputUrl?: string | null
+  candidates: ComparisonCandidate[]
+  photoLabel?: string
+  onChoose: (model: string) => void
+  onSkip?: () => void
+  onBack?: () => void
+  canGoBack?: boolean
+  onClose: () => void
+}
+
+/** Comparison mode: every model's output side by side, pick the one the pipeline continues from */
+export function ModelPicker({ stepName, step, inputUrl, candidates, photoLabel, onChoose, onSkip, onBack, canGoBack, onClose }: Props) {
+  const usable = candidates.filter((c) => c.result)
+  const [focused, setFocused] = useState<string | null>(null)
+  const [selected, setSelected] = useState<string>(usable[0]?.model || '')
+
+  useEffect(() => {
+    const handler = (e: KeyboardEvent) => {
+      if (e.key === 'Escape') {
+        if (focused) setFocused(null)
+        else onClose()
+      }
+    }
+    window.addEventListener('keydown', handler)
+    return () => window.removeEventListener('keydown', handler)
+  }, [focused, onClose])
+
+  const modelName = (model: string) => step?.models?.[model]?.name || model
+  const focusedSrc = focused === '__input' ? inputUrl : candidates.find((c) => c.model === focused)?.result
+
+  return (
+    <div class="fixed inset-0 z-50 bg-black/95 flex flex-col">
+      {/* Toolbar */}
+      <div class="flex-shrink-0 flex flex-wrap items-center gap-3 px-4 py-3 border-b border-zinc-800">
+        <h2 class="text-sm font-medium text-zinc-300 whitespace-nowrap">
+          Comparer — {stepName}
+          {photoLabel && <span class="text-zinc-500 font-normal"> — {photoLabel}</span>}
+        </h2>
+        <span class="text-xs text-zinc-500">{usable.length} résultat{usable.length > 1 ? 's' : ''}, cliquez pour agrandir</span>
+        <div class="flex items-center gap-2 ml-auto">
+          {onBack && (
+            <button
+              onClick={onBack}
+              disabled={!canGoBack}
+              class={`px-3 py-1.5 text-xs rounded transition-colors ${
+                canGoBack
+                  ? 'bg-zinc-800 text-zinc-300 hover:bg-zinc-700'
+                  : 'bg-zinc-800/50 text-zinc-600 cursor-not-allowed'
+              }`}
+            >
+              ← Retour
+            </button>
+          )}
+          {onSkip && (
+            <button
+              onClick={onSkip}
+              class="px-3 py-1.5 text-xs rounded bg-zinc-800 text-zinc-300 hover:bg-zinc-700 transition-colors"
+              title="Continuer sans appliquer cette étape"
+            >
+              Passer
+            </button>
+          )}
+          <button
+            onClick={() => selected && onChoose(selected)}
+            disabled={!selected}
+            class="px-4 py-1.5 text-xs rounded font-medium bg-amber-500 text-zinc-900 hover:bg-amber-400 cursor-pointer transition-colors"
+          >
+            Continuer avec {selected ? modelName(selected) : '…'}
+          </button>
+          <button
+            onClick={onClose}
+            class="ml-2 text-zinc-400 hover:text-white text-xl leading-none px-2 py-1 rounded hover:bg-zinc-700 transition-colors"
+            title="Fermer (Échap)"
+          >
+            ✕
+          </button>
+        </div>
+      </div>
+
+      {focused && focusedSrc ? (
+        <div class="flex-1 flex items-center justify-center overflow-hidden p-8" onClick={() => setFocused(null)}>
+          <img src={focusedSrc} class="max-w-full max-h-full object-contain" />
+        </div>
+      ) : (
+        <div class="flex-1 overflow-auto p-6">
+          <div class="grid gap-4" style={{ gridTemplateColumns: 'repeat(auto-fill, minmax(260px, 1fr))' }}>
+            {inputUrl && (
+              <div class="space-y-1.5">
+                <button onClick={() => setFocused('__input')} class="block w-full rounded-lg overflow-hidden border border-zinc-800 bg-zinc-900">
+                  <img src={inputUrl} class="w-full h-56 object-contain" />
+                </button>
+                <p class="text-xs text-zinc-500">Entrée</p>
+              </div>
+            )}
+            {candidates.map((c) => (
+              <div key={c.model} class="space-y-1.5">
+                {c.result ? (
+                  <button
+                    onClick={() => setFocused(c.model)}
+                    class={`block w-full rounded-lg overflow-hidden border bg-zinc-900 transition-colors ${
+                      selected === c.model ? 'border-amber-400 ring-1 ring-amber-400/40' : 'border-zinc-800 hover:border-zinc-600'
+                    }`}
+                  >
+                    <img src={c.result} class="w-full h-56 object-contain" />
+                  </button>
+                ) : (
+                  <div class="flex h-56 items-center justify-center rounded-lg border border-red-500/30 bg-red-500/5 p-3">
+                    <p class="text-[11px] text-red-300/80 line-clamp-6" title={c.error}>{c.error || 'Échec'}</p>
+                  </div>
+                )}
+                <label class={`flex items-center gap-2 text-xs ${c.result ? 'cursor-pointer text-zinc-300' : 'text-zinc-600'}`}>
+                  <input
+                    type="radio"
+                    name="model-choice"
+                    checked={selected === c.model}
+                    disabled={!c.result}
+                    onChange={() => setSelected(c.model)}
+                    class="accent-amber-500"
+                  />
+                  {modelName(c.model)}
+                  {c.noop && <span class="text-zinc-500" title={c.reason || undefined}>= inchangé</span>}
+                </label>
+              </div>
+            ))}
+          </div>
+        </div>
+      )}
+    </div>
+  )
+}
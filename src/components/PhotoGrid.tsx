@@ -5,9 +5,10 @@ interface Props {
   selected: Set<string>
   onToggle: (id: string) => void
   onDelete: (id: string) => void
+  onPin?: (id: string, pinned: boolean) => void
 }
 
-export function PhotoGrid({ photos, selected, onToggle, onDelete }: Props) {
+export function PhotoGrid({ photos, selected, onToggle, onDelete, onPin }: Props) {
   if (!photos.length) return null
 
   return (
@@ -43,6 +44,20 @@ export function PhotoGrid({ photos, selected, onToggle, onDelete }: Props) {
             >
               {isSelected && '✓'}
             </div>
+            {/* Pin button (photos already on the server only) */}
+            {onPin && !(photo as any)._blobUrl && (
+              <button
+                onClick={(e) => {
+                  e.stopPropagation()
+                  onPin(photo.id, !photo.pinned)
+                }}
+                class={`absolute top-1.5 right-8 w-5 h-5 rounded-full text-[10px] flex items-center justify-center transition-all
+                  ${photo.pinned ? 'bg-amber-400 text-zinc-900' : 'bg-black/50 text-white/70 opacity-0 group-hover:opacity-100 hover:text-white'}`}
+                title={photo.pinned ? 'Épinglée : jamais supprimée automatiquement' : 'Épingler (jamais supprimée automatiquement)'}
+              >
+                📌
+              </button>
+            )}
             {/* Delete button */}
             <button
               onClick={(e) => {
@@ -57,6 +72,7 @@ export function PhotoGrid({ photos, selected, onToggle, onDelete }: Props) {
             </button>
             {/* Name */}
             <div class="absolute bottom-0 inset-x-0 bg-gradient-to-t from-black/80 to-transparent p-1.5">
+              {photo.album && <p class="text-[9px] text-zinc-500 truncate">{photo.album}</p>}
               <p class="text-[10px] text-zinc-300 truncate">{photo.originalName}</p>
             </div>
           </div>
@@ -0,0 +1,131 @@
+import { useState, useRef } from 'preact/hooks'
+import * as api from '../api'
+import type { Preset, StepKey, StepParams } from '../types'
+
+interface Props {
+  presets: Preset[]
+  /** Current selection of the step selector, saved as-is */
+  current: { steps: StepKey[]; options: Record<string, string>; params: StepParams }
+  activeId: string | null
+  onApply: (preset: Preset) => void
+  /** Reload the list after a change on the server */
+  onChanged: (activeId?: string | null) => void
+}
+
+export function PresetBar({ presets, current, activeId, onApply, onChanged }: Props) {
+  const [naming, setNaming] = useState(false)
+  const [name, setName] = useState('')
+  const [message, setMessage] = useState<{ text: string; error: boolean } | null>(null)
+  const fileRef = useRef<HTMLInputElement>(null)
+  const active = presets.find((p) => p.id === activeId) || null
+
+  const save = async () => {
+    const trimmed = name.trim()
+    if (!trimmed) return
+    // Même nom qu'un préréglage existant : on le met à jour
+    const existing = presets.find((p) => p.name.toLowerCase() === trimmed.toLowerCase())
+    try {
+      const saved = await api.savePreset({ name: trimmed, ...current }, existing?.id)
+      setNaming(false)
+      setMessage({ text: existing ? `« ${saved.name} » mis à jour` : `« ${saved.name} » enregistré`, error: false })
+      onChanged(saved.id)
+    } catch (err) {
+      setMessage({ text: (err as Error).message, error: true })
+    }
+  }
+
+  const remove = async () => {
+    if (!active) return
+    await api.deletePreset(active.id)
+    setMessage({ text: `« ${active.name} » supprimé`, error: false })
+    onChanged(null)
+  }
+
+  const importFile = async (file: File) => {
+    try {
+      const { imported, rejected } = await api.importPresets(JSON.parse(await file.text()))
+      const text = `${imported.length} préréglage${imported.length > 1 ? 's' : ''} importé${imported.length > 1 ? 's' : ''}`
+        + (rejected.length ? `, ${rejected.length} ignoré${rejected.length > 1 ? 's' : ''} (${rejected.map((r) => r.error).join(', ')})` : '')
+      setMessage({ text, error: !imported.length })
+      onChanged()
+    } catch (err) {
+      setMessage({ text: `Import impossible : ${(err as Error).message}`, error: true })
+    }
+  }
+
+  const buttonClass = 'flex-shrink-0 rounded px-2 py-1 text-[11px] text-zinc-400 hover:bg-zinc-800 hover:text-zinc-200 transition-colors disabled:opacity-30 disabled:hover:bg-transparent'
+
+  return (
+    <div class="space-y-1.5">
+      {naming ? (
+        <form
+          class="flex items-center gap-1"
+          onSubmit={(e) => { e.preventDefault(); save() }}
+        >
+          <input
+            autoFocus
+            value={name}
+            onInput={(e) => setName((e.target as HTMLInputElement).value)}
+            onKeyDown={(e) => { if (e.key === 'Escape') setNaming(false) }}
+            placeholder="Nom du préréglage"
+            maxLength={80}
+            class="min-w-0 flex-1 rounded border border-zinc-700 bg-zinc-900 px-2 py-1 text-xs text-zinc-200 focus:border-amber-400/60 focus:outline-none"
+          />
+          <button type="submit" disabled={!name.trim()} class={buttonClass}>OK</button>
+          <button type="button" onClick={() => setNaming(false)} class={buttonClass}>Annuler</button>
+        </form>
+      ) : (
+        <div class="flex items-center gap-1">
+          <select
+            value={activeId || ''}
+            onChange={(e) => {
+              const preset = presets.find((p) => p.id === (e.target as HTMLSelectElement).value)
+              if (preset) onApply(preset)
+            }}
+            class="min-w-0 flex-1 cursor-pointer rounded border border-zinc-700 bg-zinc-900 px-2 py-1 text-xs text-zinc-200 focus:border-amber-400/60 focus:outline-none"
+          >
+            <option value="" disabled>{presets.length ? 'Préréglages…' : 'Aucun préréglage'}</option>
+            {presets.map((p) => (
+              <option key={p.id} value={p.id} title={p.description}>{p.name}</option>
+            ))}
+          </select>
+          <button
+            onClick={() => { setName(active?.name || ''); setNaming(true); setMessage(null) }}
+            disabled={!current.steps.length}
+            class={buttonClass}
+            title="Enregistrer la sélection actuelle"
+          >
+            Enregistrer
+          </button>
+          <button onClick={remove} disabled={!active} class={buttonClass} title="Supprimer le préréglage">
+            ✕
+          </button>
+        </div>
+      )}
+      <div class="flex items-center gap-3 text-[10px]">
+        <a href={api.PRESETS_EXPORT_URL} download="presets.json" class="text-zinc-500 hover:text-zinc-300 transition-colors">
+          Exporter
+        </a>
+        <button onClick={() => fileRef.current?.click()} class="text-zinc-500 hover:text-zinc-300 transition-colors">
+          Importer
+        </button>
+        <input
+          ref={fileRef}
+          type="file"
+          accept="application/json,.json"
+          class="hidden"
+          onChange={(e) => {
+            const input = e.target as HTMLInputElement
+            if (input.files?.[0]) importFile(input.files[0])
+            input.value = ''
+          }}
+        />
+        {message && (
+          <span class={`truncate ${message.error ? 'text-red-400/80' : 'text-zinc-500'}`} title={message.text}>
+            {message.text}
+          </span>
+        )}
+      </div>
+    </div>
+  )
+}
@@ -1,4 +1,4 @@
-import type { StepKey, StepInfo } from '../types'
+import type { StepKey, StepInfo, StepParams } from '../types'
 
 interface Props {
   steps: Record<string, StepInfo>
@@ -7,6 +7,11 @@ interface Props {
   onToggleAll: () => void
   modelChoices: Record<string, string>
   onModelChange: (step: string, model: string) => void
+  /** Comparison mode: models to run side by side for a step (absent = single model) */
+  compareChoices?: Record<string, string[]>
+  onCompareChange?: (step: string, models: string[] | null) => void
+  paramChoices?: StepParams
+  onParamChange?: (step: string, param: string, value: number) => void
 }
 
 const STEP_ICONS: Record<StepKey, string> = {
@@ -31,7 +36,7 @@ export const STEP_ORDER: StepKey[] = [
   'online_restore',
 ]
 
-export function StepSelector({ steps, selected, onToggle, onToggleAll, modelChoices, onModelChange }: Props) {
+export function StepSelector({ steps, selected, onToggle, onToggleAll, modelChoices, onModelChange, compareChoices = {}, onCompareChange, paramChoices = {}, onParamChange }: Props) {
   const availableSteps = STEP_ORDER.filter((k) => steps[k])
   const allSelected = availableSteps.length > 0 && availableSteps.every((k) => selected.has(k))
 
@@ -61,12 +66,15 @@ export function StepSelector({ steps, selected, onToggle, onToggleAll, modelChoi
         const hasModels = step.models && Object.keys(step.models).length > 1
         const currentModel = modelChoices[key] || step.defaultModel || ''
         const currentModelInfo = hasModels && currentModel ? step.models![currentModel] : null
+        const compared = compareChoices[key]
+        const params = step.params && onParamChange ? Object.entries(step.params) : []
+        const hasPanel = isSelected && (hasModels || params.length > 0)
 
         return (
           <div key={key} class="space-y-0">
             <button
               onClick={() => onToggle(key)}
-              class={`flex w-full items-center gap-3 border px-3 py-2.5 text-left transition-all ${hasModels && isSelected ? 'rounded-t-lg border-b-0' : 'rounded-lg'} ${
+              class={`flex w-full items-center gap-3 border px-3 py-2.5 text-left transition-all ${hasPanel ? 'rounded-t-lg border-b-0' : 'rounded-lg'} ${
                 isSelected
                   ? 'border-amber-400/60 bg-amber-400/10 text-amber-100'
                   : 'border-zinc-800 bg-zinc-900/50 text-zinc-400 hover:border-zinc-600'
@@ -75,7 +83,9 @@ export function StepSelector({ steps, selected, onToggle, onToggleAll, modelChoi
               <span class="text-lg">{STEP_ICONS[key]}</span>
               <div class="min-w-0 flex-1">
                 <p class="truncate text-sm font-medium">{step.name}</p>
-                <p class="truncate text-[11px] opacity-50">{currentModelInfo ? currentModelInfo.name : step.model}</p>
+                <p class="truncate text-[11px] opacity-50">
+                  {compared ? `Comparaison de ${compared.length} modèles` : currentModelInfo ? currentModelInfo.name : step.model}
+                </p>
               </div>
               <div
                 class={`flex h-4 w-4 flex-shrink-0 items-center justify-center rounded border text-[10px] font-bold ${
@@ -86,21 +96,72 @@ export function StepSelector({ steps, selected, onToggle, onToggleAll, modelChoi
               </div>
             </button>
 
-            {/* Model selector dropdown */}
-            {hasModels && isSelected && (
-              <div class="rounded-b-lg border border-t-0 border-amber-400/60 bg-amber-400/5 px-3 py-2">
-                <select
-                  value={currentModel}
-                  onChange={(e) => onModelChange(key, (e.target as HTMLSelectElement).value)}
-                  onClick={(e) => e.stopPropagation()}
-                  class="w-full cursor-pointer rounded border border-zinc-700 bg-zinc-900 px-2 py-1.5 text-xs text-zinc-200 focus:border-amber-400/60 focus:outline-none"
-                >
-                  {Object.entries(step.models!).map(([modelKey, modelInfo]) => (
-                    <option key={modelKey} value={modelKey}>
-                      {modelInfo.name} — {modelInfo.desc}
-                    </option>
-                  ))}
-                </select>
+            {/* Model selector dropdown + step parameters */}
+            {hasPanel && (
+              <div class="space-y-1.5 rounded-b-lg border border-t-0 border-amber-400/60 bg-amber-400/5 px-3 py-2">
+                {hasModels && !compared && (
+                  <select
+                    value={currentModel}
+                    onChange={(e) => onModelChange(key, (e.target as HTMLSelectElement).value)}
+                    onClick={(e) => e.stopPropagation()}
+                    class="w-full cursor-pointer rounded border border-zinc-700 bg-zinc-900 px-2 py-1.5 text-xs text-zinc-200 focus:border-amber-400/60 focus:outline-none"
+                  >
+                    {Object.entries(step.models!).map(([modelKey, modelInfo]) => (
+                      <option key={modelKey} value={modelKey}>
+                        {modelInfo.name} — {modelInfo.desc}
+                      </option>
+                    ))}
+                  </select>
+                )}
+                {hasModels && onCompareChange && (
+                  <label class="flex cursor-pointer items-center gap-2 text-[11px] text-zinc-400">
+                    <input
+                      type="checkbox"
+                      checked={!!compared}
+                      onChange={() => onCompareChange(key, compared ? null : Object.keys(step.models!))}
+                      class="accent-amber-500"
+                    />
+                    Comparer les modèles
+                  </label>
+                )}
+                {compared && onCompareChange && (
+                  <div class="space-y-1 pl-5">
+                    {Object.entries(step.models!).map(([modelKey, modelInfo]) => (
+                      <label key={modelKey} class="flex cursor-pointer items-center gap-2 text-[11px] text-zinc-300">
+                        <input
+                          type="checkbox"
+                          checked={compared.includes(modelKey)}
+                          onChange={() => onCompareChange(key, compared.includes(modelKey)
+                            ? compared.filter((m) => m !== modelKey)
+                            : Object.keys(step.models!).filter((m) => m === modelKey || compared.includes(m)))}
+                          class="accent-amber-500"
+                        />
+                        {modelInfo.name}
+                      </label>
+                    ))}
+                    {compared.length < 2 && (
+                      <p class="text-[10px] text-zinc-500">Au moins deux modèles pour comparer</p>
+                    )}
+                  </div>
+                )}
+                {params.map(([paramKey, param]) => {
+                  const value = paramChoices[key]?.[paramKey] ?? param.default
+                  return (
+                    <label key={paramKey} class="flex items-center gap-2 text-[11px] text-zinc-400" title={param.desc}>
+                      <span class="w-16 flex-shrink-0 truncate">{param.name}</span>
+                      <input
+                        type="range"
+                        min={param.min}
+                        max={param.max}
+                        step={param.step ?? 1}
+                        value={value}
+                        onInput={(e) => onParamChange!(key, paramKey, Number((e.target as HTMLInputElement).value))}
+                        class="min-w-0 flex-1 accent-amber-500"
+                      />
+                      <span class="w-7 text-right tabular-nums text-zinc-300">{value}</span>
+                    </label>
+                  )
+                })}
               </div>
             )}
           </div>
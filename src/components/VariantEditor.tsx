@@ -0,0 +1,149 @@
+import type { StepKey, StepInfo, BranchSpec } from '../types'
+import { STEP_ORDER } from './StepSelector'
+
+/** A variant = steps run after the common pipeline, with their models */
+export interface Variant {
+  steps: StepKey[]
+  options: Record<string, string>
+}
+
+interface Props {
+  steps: Record<string, StepInfo>
+  variants: Variant[]
+  onChange: (variants: Variant[]) => void
+}
+
+// Étapes manuelles exclues : une variante doit pouvoir tourner sans intervention
+const BRANCHABLE: StepKey[] = ['spot_removal', 'scratch_removal', 'face_restore', 'colorize', 'upscale', 'online_restore']
+
+const STEP_ICONS: Partial<Record<StepKey, string>> = {
+  spot_removal: '✨',
+  scratch_removal: '🩹',
+  face_restore: '👤',
+  colorize: '🎨',
+  upscale: '🔍',
+  online_restore: '☁️',
+}
+
+interface TreeNode {
+  step?: StepKey
+  model?: string
+  terminal: boolean
+  children: TreeNode[]
+}
+
+/**
+ * Turn flat variants into a branch tree, sharing common leading steps so that e.g.
+ * "DDColor" and "DDColor + Upscale" only colorize once.
+ */
+export function buildBranches(variants: Variant[]): BranchSpec[] {
+  const root: TreeNode = { terminal: false, children: [] }
+  for (const variant of variants) {
+    let node = root
+    for (const step of STEP_ORDER.filter((s) => variant.steps.includes(s))) {
+      const model = variant.options[step] || ''
+      let next = node.children.find((c) => c.step === step && c.model === model)
+      if (!next) {
+        next = { step, model, terminal: false, children: [] }
+        node.children.push(next)
+      }
+      node = next
+    }
+    node.terminal = true
+  }
+  return toBranches(root)
+}
+
+function toBranches(node: TreeNode): BranchSpec[] {
+  const out: BranchSpec[] = []
+  // Ce point de l'arbre est lui-même une variante : branche vide = résultat du parent
+  if (node.terminal && node.children.length) out.push({ steps: [] })
+  for (const child of node.children) {
+    const branch: BranchSpec = { steps: [], options: {} }
+    let cur = child
+    for (;;) {
+      branch.steps.push(cur.step!)
+      if (cur.model) branch.options![cur.step!] = cur.model
+      if (cur.terminal || cur.children.length !== 1) break
+      cur = cur.children[0]
+    }
+    if (cur.children.length) branch.branches = toBranches(cur)
+    out.push(branch)
+  }
+  return out
+}
+
+export function VariantEditor({ steps, variants, onChange }: Props) {
+  const available = BRANCHABLE.filter((k) => steps[k])
+  if (!available.length) return null
+
+  const update = (index: number, variant: Variant) => {
+    onChange(variants.map((v, i) => (i === index ? variant : v)))
+  }
+
+  const toggleStep = (index: number, step: StepKey) => {
+    const variant = variants[index]
+    const has = variant.steps.includes(step)
+    const options = { ...variant.options }
+    if (has) delete options[step]
+    else if (steps[step].defaultModel) options[step] = steps[step].defaultModel!
+    update(index, {
+      steps: has ? variant.steps.filter((s) => s !== step) : STEP_ORDER.filter((s) => s === step || variant.steps.includes(s)),
+      options,
+    })
+  }
+
+  return (
+    <div class="space-y-2">
+      {variants.map((variant, index) => (
+        <div key={index} class="rounded-lg border border-violet-400/30 bg-violet-400/5 p-2 space-y-1.5">
+          <div class="flex items-center gap-1">
+            <span class="text-[10px] text-violet-300/80 mr-1">⑂ {index + 1}</span>
+            {available.map((key) => (
+              <button
+                key={key}
+                onClick={() => toggleStep(index, key)}
+                title={steps[key].name}
+                class={`rounded px-1.5 py-0.5 text-sm transition-colors ${
+                  variant.steps.includes(key) ? 'bg-violet-400/25' : 'opacity-40 hover:opacity-80'
+                }`}
+              >
+                {STEP_ICONS[key]}
+              </button>
+            ))}
+            <button
+              onClick={() => onChange(variants.filter((_, i) => i !== index))}
+              class="ml-auto text-zinc-600 hover:text-red-400 transition-colors"
+              title="Supprimer la variante"
+            >
+              <svg width="12" height="12" viewBox="0 0 14 14" fill="none" stroke="currentColor" stroke-width="1.5" stroke-linecap="round"><path d="M3 3l8 8M11 3l-8 8"/></svg>
+            </button>
+          </div>
+          {variant.steps.filter((s) => steps[s]?.models && Object.keys(steps[s].models!).length > 1).map((key) => (
+            <select
+              key={key}
+              value={variant.options[key] || steps[key].defaultModel}
+              onChange={(e) => update(index, { ...variant, options: { ...variant.options, [key]: (e.target as HTMLSelectElement).value } })}
+              class="w-full cursor-pointer rounded border border-zinc-700 bg-zinc-900 px-2 py-1 text-[11px] text-zinc-200 focus:border-violet-400/60 focus:outline-none"
+            >
+              {Object.entries(steps[key].models!).map(([modelKey, modelInfo]) => (
+                <option key={modelKey} value={modelKey}>
+                  {steps[key].name} — {modelInfo.name}
+                </option>
+              ))}
+            </select>
+          ))}
+          {!variant.steps.length && (
+            <p class="text-[10px] text-zinc-500">Sans étape supplémentaire : garde le résultat commun</p>
+          )}
+        </div>
+      ))}
+      <button
+        onClick={() => onChange([...variants, { steps: [], options: {} }])}
+        class="w-full rounded-lg border border-dashed border-zinc-700 px-3 py-1.5 text-[11px] text-zinc-500 hover:border-violet-400/40 hover:text-violet-300 transition-colors"
+      >
+        + Ajouter une variante
+      </button>
+    </div>
+  )
+}
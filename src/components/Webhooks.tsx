@@ -0,0 +1,125 @@
+import { useState, useEffect } from 'preact/hooks'
+import * as api from '../api'
+import type { Webhook, WebhookEvent } from '../types'
+
+interface Props {
+  onClose: () => void
+}
+
+const EVENT_LABELS: Record<WebhookEvent, string> = {
+  job_completed: 'Job terminé',
+  job_failed: 'Job en erreur',
+  waiting_input: 'Saisie manuelle attendue',
+  batch_finished: 'Lot terminé',
+}
+const ALL_EVENTS = Object.keys(EVENT_LABELS) as WebhookEvent[]
+
+/** Webhook URLs notified of job events — add, pick events, copy the signing secret once, remove */
+export function Webhooks({ onClose }: Props) {
+  const [hooks, setHooks] = useState<Webhook[]>([])
+  const [url, setUrl] = useState('')
+  const [events, setEvents] = useState<WebhookEvent[]>(ALL_EVENTS)
+  const [error, setError] = useState<string | null>(null)
+  // Secrets générés pendant que la fenêtre est ouverte : le serveur ne les renvoie qu'une fois
+  const [secrets, setSecrets] = useState<Record<string, string>>({})
+
+  useEffect(() => { api.getSettings().then(s => setHooks(s.webhooks || [])) }, [])
+
+  useEffect(() => {
+    const handler = (e: KeyboardEvent) => { if (e.key === 'Escape') onClose() }
+    window.addEventListener('keydown', handler)
+    return () => window.removeEventListener('keydown', handler)
+  }, [onClose])
+
+  const save = async (list: Array<Pick<Webhook, 'url' | 'events'> & { id?: string }>) => {
+    try {
+      const settings = await api.updateWebhooks(list)
+      const created = (settings.webhooks || []).filter(h => h.secret)
+      if (created.length) setSecrets(prev => ({ ...prev, ...Object.fromEntries(created.map(h => [h.id, h.secret!])) }))
+      setHooks(settings.webhooks || [])
+      setError(null)
+      return true
+    } catch (err) {
+      setError((err as Error).message)
+      return false
+    }
+  }
+
+  const add = async () => {
+    if (await save([...hooks, { url: url.trim(), events }])) {
+      setUrl('')
+      setEvents(ALL_EVENTS)
+    }
+  }
+
+  const remove = (id: string) => save(hooks.filter(h => h.id !== id))
+
+  const toggleEvent = (event: WebhookEvent) => {
+    setEvents(prev => prev.includes(event) ? prev.filter(e => e !== event) : [...prev, event])
+  }
+
+  return (
+    <div class="fixed inset-0 z-50 bg-black/70 flex items-center justify-center" onClick={onClose}>
+      <div class="w-[32rem] max-w-[95vw] rounded-lg border border-zinc-800 bg-zinc-950 p-4 space-y-3" onClick={(e) => e.stopPropagation()}>
+        <div class="flex items-center justify-between">
+          <h2 class="text-sm font-medium text-zinc-200">Webhooks</h2>
+          <button onClick={onClose} class="text-zinc-500 hover:text-zinc-200 transition-colors">✕</button>
+        </div>
+        <p class="text-[11px] text-zinc-500">
+          POST JSON à chaque événement, signé par <code class="text-zinc-400">X-OldPhotos-Signature: sha256=&lt;HMAC du corps&gt;</code>.
+          Les webhooks sont communs à l'instance : ils reçoivent les jobs de toutes les sessions.
+        </p>
+
+        <form class="space-y-1.5" onSubmit={(e) => { e.preventDefault(); add() }}>
+          <div class="flex items-center gap-1">
+            <input
+              value={url}
+              onInput={(e) => setUrl((e.target as HTMLInputElement).value)}
+              placeholder="https://exemple.org/hooks/oldphotos"
+              class="min-w-0 flex-1 rounded border border-zinc-700 bg-zinc-900 px-2 py-1 text-xs text-zinc-200 focus:border-amber-400/60 focus:outline-none"
+            />
+            <button
+              type="submit"
+              disabled={!url.trim() || !events.length}
+              class="rounded px-2 py-1 text-[11px] text-zinc-400 hover:bg-zinc-800 hover:text-zinc-200 transition-colors disabled:opacity-30"
+            >
+              Ajouter
+            </button>
+          </div>
+          <div class="flex flex-wrap gap-x-3 gap-y-1">
+            {ALL_EVENTS.map(event => (
+              <label key={event} class="flex items-center gap-1 text-[11px] text-zinc-400 cursor-pointer">
+                <input type="checkbox" checked={events.includes(event)} onChange={() => toggleEvent(event)} class="accent-amber-500" />
+                {EVENT_LABELS[event]}
+              </label>
+            ))}
+          </div>
+        </form>
+        {error && <p class="text-[11px] text-red-400/80">{error}</p>}
+
+        <div class="max-h-64 overflow-y-auto divide-y divide-zinc-800/60">
+          {hooks.length === 0 && <p class="py-2 text-[11px] text-zinc-600">Aucun webhook</p>}
+          {hooks.map((h) => (
+            <div key={h.id} class="flex items-center justify-between gap-2 py-1.5">
+              <div class="min-w-0">
+                <p class="truncate text-xs text-zinc-300" title={h.url}>{h.url}</p>
+                <p class="text-[10px] text-zinc-600">{h.events.map(e => EVENT_LABELS[e]).join(' · ')}</p>
+                {secrets[h.id] && (
+                  <p class="text-[10px] text-amber-300/80">
+                    Secret : <code class="select-all text-zinc-300">{secrets[h.id]}</code> — à copier maintenant, il ne sera plus affiché
+                  </p>
+                )}
+              </div>
+              <button
+                onClick={() => remove(h.id)}
+                class="flex-shrink-0 text-[11px] text-red-400/60 hover:text-red-400 transition-colors"
+              >
+                Supprimer
+              </button>
+            </div>
+          ))}
+        </div>
+      </div>
+    </div>
+  )
+}
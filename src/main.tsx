@@ -1,4 +1,22 @@
 import { render } from 'preact'
+import { useState, useEffect } from 'preact/hooks'
 import { App } from './app'
+import { Login } from './components/Login'
+import * as api from './api'
+import type { AuthInfo } from './types'
 
-render(<App />, document.getElementById('app')!)
+// Écran de connexion tant que le serveur exige une authentification
+function Root() {
+  const [auth, setAuth] = useState<AuthInfo | null>(null)
+  useEffect(() => {
+    api.getAuth().then(setAuth).catch(() => setAuth({ enabled: false, mode: null, user: null }))
+  }, [])
+
+  if (!auth) return null
+  if (auth.enabled && !auth.user) {
+    return <Login mode={auth.mode} onLoggedIn={(user) => setAuth({ ...auth, user })} />
+  }
+  return <App auth={auth} />
+}
+
+render(<Root />, document.getElementById('app')!)
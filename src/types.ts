@@ -2,12 +2,42 @@ export interface Photo {
   id: string
   filename: string
   originalName: string
+  /** Folder inside the uploaded archive ("1953/Vacances"), or the archive name */
+  album?: string
   uploadedAt: string
+  /** Never removed by the disk quota nor by cleanup */
+  pinned?: boolean
+}
+
+/** File or archive entry refused at upload */
+export interface RejectedFile {
+  name: string
+  reason: string
+  archive?: string
+}
+
+/** Estimated cost of a step for the machine budget: memoryMb + memoryMbPerMp × megapixels */
+export interface StepCost {
+  memoryMb: number
+  memoryMbPerMp: number
+  cpus: number
 }
 
 export interface ModelVariant {
   name: string
   desc: string
+  cost?: StepCost
+}
+
+/** Numeric setting of a step (e.g. upscale factor), see `params` on server step definitions */
+export interface StepParam {
+  name: string
+  type: 'number'
+  min?: number
+  max?: number
+  step?: number
+  default: number
+  desc?: string
 }
 
 export interface StepInfo {
@@ -16,15 +46,89 @@ export interface StepInfo {
   repo: string
   models?: Record<string, ModelVariant>
   defaultModel?: string
+  params?: Record<string, StepParam>
+  /** Models tried in order when one fails: { x4plus: ['compact', 'lanczos'] } */
+  fallbacks?: Record<string, string[]>
+  cost?: StepCost
+}
+
+/** Per-step parameter values: { upscale: { scale: 4 } } */
+export type StepParams = Record<string, Record<string, number>>
+
+/** Named pipeline stored on the server */
+export interface Preset {
+  id: string
+  name: string
+  description?: string
+  steps: StepKey[]
+  options: Record<string, string>
+  params: StepParams
+  createdAt: string
+  updatedAt: string
 }
 
 export type StepKey = 'crop' | 'inpaint' | 'spot_removal' | 'scratch_removal' | 'face_restore' | 'colorize' | 'upscale' | 'online_restore'
 
+/** One model's output in comparison mode */
+export interface ComparisonCandidate {
+  model: string
+  result: string | null
+  error?: string
+  noop?: boolean
+  reason?: string | null
+  metrics?: Record<string, number>
+  warnings?: string[]
+  /** File removed by the disk quota */
+  evicted?: boolean
+}
+
 export interface StepResult {
   step: StepKey
-  result: string
+  /** null while a comparison is waiting for its winner */
+  result: string | null
+  /** Output identical to the input (nothing detected / nothing to do) */
+  noop?: boolean
+  reason?: string | null
+  metrics?: Record<string, number>
+  warnings?: string[]
+  model?: string | null
+  startedAt?: string
+  durationMs?: number
+  /** Comparison mode: every model run on the same input, `chosen` is the winner */
+  comparison?: ComparisonCandidate[]
+  chosen?: string
+  /** The requested model failed: `used` is the model of the fallback chain that succeeded */
+  fallback?: StepFallback
+  /** Intermediate image removed by the disk quota */
+  evicted?: boolean
+}
+
+export interface StepFallback {
+  requested: string
+  used: string
+  attempts: { model: string; error: string }[]
 }
 
+/** Branch of a pipeline tree: runs after its parent, from the parent's result */
+export interface BranchSpec {
+  steps: StepKey[]
+  options?: Record<string, string>
+  label?: string
+  branches?: BranchSpec[]
+}
+
+export type JobEventType =
+  | 'job_created'
+  | 'job_updated'
+  | 'step_started'
+  | 'step_progress'
+  | 'step_completed'
+  | 'waiting_input'
+  | 'job_completed'
+  | 'job_failed'
+  | 'job_cancelled'
+  | 'job_paused'
+
 export interface Job {
   id: string
   photoId: string
@@ -32,8 +136,17 @@ export interface Job {
   original: string
   steps: StepKey[]
   options?: Record<string, string>
-  status: 'pending' | 'processing' | 'waiting_input' | 'completed' | 'failed' | 'cancelled'
+  params?: StepParams
+  presetId?: string | null
+  status: 'pending' | 'processing' | 'waiting_input' | 'paused' | 'completed' | 'failed' | 'cancelled'
+  /** Processing job that pauses once its current step is done */
+  pauseRequested?: boolean
+  pausedAt?: string | null
+  /** Kept running when the browser is gone, whatever the heartbeat action */
+  detached?: boolean
   progress: number
+  stepProgress?: number | null
+  phase?: string | null
   currentStep: StepKey | null
   waitingStep?: StepKey | null
   waitingImage?: string | null
@@ -42,7 +155,77 @@ export interface Job {
   result: string | null
   stepResults?: StepResult[]
   priority?: number
+  startedAt?: string
+  completedAt?: string
+  failedAt?: string
   error?: string | null
   failedStep?: StepKey | null
   failedStepIndex?: number | null
+  /** Variant jobs: spawned from the parent's result once it completed */
+  parentJobId?: string
+  rootJobId?: string
+  branchLabel?: string
+  base?: string
+  branches?: BranchSpec[] | null
+  childJobIds?: string[]
+  /** Final result never removed by the disk quota nor by cleanup */
+  pinned?: boolean
+  /** Final result file removed by the disk quota; the job stays in history */
+  resultEvicted?: boolean
+  /** Estimated from past step timings; null once the job is no longer active */
+  eta?: JobEta | null
+}
+
+/** Remaining time of an active job, in seconds from now */
+export interface JobEta {
+  seconds: number
+  /** Wait before a free slot, for pending jobs */
+  startsIn: number
+  finishAt: string
+  /** Steps never timed on this device, left out of `seconds` */
+  unknownSteps: StepKey[]
+  /** Counted from now: the manual input itself is not estimated */
+  waitingInput?: boolean
+}
+
+/** GET /api/auth — `enabled` false means the instance is open */
+export interface AuthInfo {
+  enabled: boolean
+  mode: 'password' | 'users' | null
+  user: string | null
+}
+
+/** Bearer token for scripts; the secret itself is only returned at creation */
+export interface ApiToken {
+  id: string
+  name: string
+  user: string
+  /** Last 4 characters, to recognise the token */
+  hint: string
+  createdAt: string
+  lastUsedAt: string | null
+}
+
+/** What happens to a session's active jobs once its browser is gone */
+export type HeartbeatAction = 'cancel' | 'pause' | 'none'
+
+export type WebhookEvent = 'job_completed' | 'job_failed' | 'waiting_input' | 'batch_finished'
+
+/** URL notified in POST of job events, body signed with its secret (X-OldPhotos-Signature) */
+export interface Webhook {
+  id: string
+  url: string
+  events: WebhookEvent[]
+  /** Only in the response that generated it */
+  secret?: string
+  createdAt: string
+}
+
+/** `storage` in GET /api/status — bytes on uploads + results */
+export interface StorageUsage {
+  used: number
+  quota: number | null
+  uploads: number
+  results: number
+  pinned: number
 }